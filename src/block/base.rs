@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::fmt;
 
 use crate::block::{BlockLogic, DataConvertError, DeserializeError, make_register, SerializeError};
 use crate::block::simple::{BuildCost, cost, SimpleBlock, state_impl};
@@ -44,6 +45,14 @@ impl From<RGBA> for u32
 	}
 }
 
+impl fmt::Display for RGBA
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		write!(f, "#{:02x}{:02x}{:02x}{:02x}", self.0, self.1, self.2, self.3)
+	}
+}
+
 pub struct LampBlock
 {
 	size: u8,
@@ -100,6 +109,8 @@ impl BlockLogic for LampBlock
 	{
 		match data
 		{
+			// the game's default lamp color
+			DynData::Empty => Ok(Some(Self::create_state(RGBA(0xFF, 0xD3, 0x7F, 0xFF)))),
 			DynData::Int(rgba) => Ok(Some(Self::create_state(RGBA::from(rgba as u32)))),
 			_ => Err(DeserializeError::InvalidType{have: data.get_type(), expect: DynType::Int}),
 		}
@@ -108,7 +119,7 @@ impl BlockLogic for LampBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -86,6 +86,7 @@ impl BlockLogic for DoorBlock
 	{
 		match data
 		{
+			DynData::Empty => Ok(Some(Self::create_state(false))),
 			DynData::Boolean(opened) => Ok(Some(Self::create_state(opened))),
 			_ => Err(DeserializeError::InvalidType{have: data.get_type(), expect: DynType::Boolean}),
 		}
@@ -94,7 +95,7 @@ impl BlockLogic for DoorBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -100,7 +100,7 @@ impl BlockLogic for FluidBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
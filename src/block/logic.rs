@@ -10,6 +10,8 @@ use crate::block::{BlockLogic, DataConvertError, DeserializeError, make_register
 use crate::block::simple::{BuildCost, cost, SimpleBlock, state_impl};
 use crate::data::{self, DataRead, DataWrite, GridPos};
 use crate::data::dynamic::{DynData, DynType};
+use crate::data::image::Image;
+use crate::data::pixelart;
 use crate::item::storage::Storage;
 
 make_register!
@@ -23,6 +25,7 @@ make_register!
 	MEMORY_BANK: "memory-bank" => SimpleBlock::new(2, true, cost!(Copper: 30, Graphite: 80, Silicon: 80, PhaseFabric: 30));
 	LOGIC_DISPLAY: "logic-display" => SimpleBlock::new(3, true, cost!(Lead: 100, Metaglass: 50, Silicon: 50));
 	LARGE_LOGIC_DISPLAY: "large-logic-display" => SimpleBlock::new(6, true, cost!(Lead: 200, Metaglass: 100, Silicon: 150, PhaseFabric: 75));
+	CANVAS: "canvas" => CanvasLogic::new(2, true, cost!(Silicon: 30, Beryllium: 10), 12);
 );
 
 pub struct MessageLogic
@@ -676,3 +679,339 @@ impl fmt::Display for CreateError
 }
 
 impl Error for CreateError {}
+
+// the colors a canvas can show, pixels store an index into this
+pub const CANVAS_PALETTE: [[u8; 3]; 8] = [
+	[0x36, 0x29, 0x44], [0xC4, 0x5D, 0x9F], [0xE3, 0x9A, 0xAC], [0xF0, 0xDA, 0xB1],
+	[0x64, 0x61, 0xC2], [0x2B, 0xA9, 0xB4], [0x93, 0xD4, 0xB5], [0xF0, 0xF6, 0xE8],
+];
+// enough bits for every index into the palette
+const CANVAS_BITS: usize = 3;
+
+pub struct CanvasLogic
+{
+	size: u8,
+	symmetric: bool,
+	build_cost: BuildCost,
+	resolution: u16,
+}
+
+impl CanvasLogic
+{
+	pub const fn new(size: u8, symmetric: bool, build_cost: BuildCost, resolution: u16) -> Self
+	{
+		if size == 0
+		{
+			panic!("invalid size");
+		}
+		if resolution == 0
+		{
+			panic!("invalid resolution");
+		}
+		Self{size, symmetric, build_cost, resolution}
+	}
+	
+	pub fn get_resolution(&self) -> u16
+	{
+		self.resolution
+	}
+	
+	state_impl!(pub CanvasState);
+}
+
+impl BlockLogic for CanvasLogic
+{
+	fn get_size(&self) -> u8
+	{
+		self.size
+	}
+	
+	fn is_symmetric(&self) -> bool
+	{
+		self.symmetric
+	}
+	
+	fn create_build_cost(&self) -> Option<Storage>
+	{
+		if !self.build_cost.is_empty()
+		{
+			let mut storage = Storage::new();
+			for (ty, cnt) in self.build_cost
+			{
+				storage.add(*ty, *cnt, u32::MAX);
+			}
+			Some(storage)
+		}
+		else {None}
+	}
+	
+	fn data_from_i32(&self, _: i32, _: GridPos) -> Result<DynData, DataConvertError>
+	{
+		Ok(DynData::Empty)
+	}
+	
+	fn deserialize_state(&self, data: DynData) -> Result<Option<Box<dyn Any>>, DeserializeError>
+	{
+		match data
+		{
+			DynData::Empty => Ok(Some(Self::create_state(CanvasState::new(self.resolution, self.resolution)))),
+			DynData::ByteArray(arr) =>
+			{
+				let state = DeserializeError::forward(CanvasState::from_bytes(self.resolution, self.resolution, &arr))?;
+				Ok(Some(Self::create_state(state)))
+			},
+			_ => Err(DeserializeError::InvalidType{have: data.get_type(), expect: DynType::ByteArray}),
+		}
+	}
+	
+	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
+	{
+		Box::new(Self::get_state(state).clone())
+	}
+	
+	fn mirror_state(&self, state: &mut dyn Any, horizontally: bool, vertically: bool)
+	{
+		Self::get_state_mut(state).mirror(horizontally, vertically);
+	}
+	
+	fn rotate_state(&self, state: &mut dyn Any, clockwise: bool)
+	{
+		Self::get_state_mut(state).rotate(clockwise);
+	}
+	
+	fn serialize_state(&self, state: &dyn Any) -> Result<DynData, SerializeError>
+	{
+		Ok(DynData::ByteArray(Self::get_state(state).to_bytes()))
+	}
+}
+
+// the pixels of a canvas as palette indices, row by row starting at the top
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CanvasState
+{
+	width: u16,
+	height: u16,
+	pixels: Vec<u8>,
+}
+
+impl CanvasState
+{
+	pub fn new(width: u16, height: u16) -> Self
+	{
+		Self{width, height, pixels: vec![0; width as usize * height as usize]}
+	}
+	
+	// unpacks the config, which stores each pixel in CANVAS_BITS bits starting from the least significant
+	pub fn from_bytes(width: u16, height: u16, data: &[u8]) -> Result<Self, CanvasError>
+	{
+		let mut state = Self::new(width, height);
+		let expect = state.get_byte_len();
+		if data.len() != expect
+		{
+			return Err(CanvasError::DataLength{have: data.len(), expect});
+		}
+		for (i, px) in state.pixels.iter_mut().enumerate()
+		{
+			for bit in 0..CANVAS_BITS
+			{
+				let pos = i * CANVAS_BITS + bit;
+				if data[pos >> 3] & (1 << (pos & 7)) != 0 {*px |= 1 << bit;}
+			}
+		}
+		Ok(state)
+	}
+	
+	pub fn to_bytes(&self) -> Vec<u8>
+	{
+		let mut data = vec![0u8; self.get_byte_len()];
+		for (i, &px) in self.pixels.iter().enumerate()
+		{
+			for bit in 0..CANVAS_BITS
+			{
+				let pos = i * CANVAS_BITS + bit;
+				if px & (1 << bit) != 0 {data[pos >> 3] |= 1 << (pos & 7);}
+			}
+		}
+		data
+	}
+	
+	fn get_byte_len(&self) -> usize
+	{
+		(self.pixels.len() * CANVAS_BITS).div_ceil(8)
+	}
+	
+	pub fn get_width(&self) -> u16
+	{
+		self.width
+	}
+	
+	pub fn get_height(&self) -> u16
+	{
+		self.height
+	}
+	
+	pub fn get_pixels(&self) -> &[u8]
+	{
+		&self.pixels
+	}
+	
+	pub fn get_pixel(&self, x: u16, y: u16) -> Option<u8>
+	{
+		if x >= self.width || y >= self.height {return None;}
+		Some(self.pixels[x as usize + y as usize * self.width as usize])
+	}
+	
+	pub fn get_color(&self, x: u16, y: u16) -> Option<[u8; 3]>
+	{
+		self.get_pixel(x, y).map(|px| CANVAS_PALETTE[px as usize])
+	}
+	
+	pub fn set_pixel(&mut self, x: u16, y: u16, color: u8) -> Result<(), CanvasError>
+	{
+		if x >= self.width || y >= self.height
+		{
+			return Err(CanvasError::OutOfBounds{x, y, width: self.width, height: self.height});
+		}
+		if color as usize >= CANVAS_PALETTE.len()
+		{
+			return Err(CanvasError::InvalidColor(color));
+		}
+		self.pixels[x as usize + y as usize * self.width as usize] = color;
+		Ok(())
+	}
+	
+	// scales the image to the canvas and picks the closest palette color for each pixel
+	pub fn draw_image(&mut self, image: &Image)
+	{
+		let image = image.resize(self.width as u32, self.height as u32);
+		let back = CANVAS_PALETTE[0];
+		for y in 0..self.height
+		{
+			for x in 0..self.width
+			{
+				let p = image.get_pixel(x as u32, y as u32);
+				// canvases are opaque, so blend transparent pixels with the darkest color
+				let alpha = p[3] as f32 / 255.0;
+				let color = [0, 1, 2].map(|c| p[c] as f32 * alpha + back[c] as f32 * (1.0 - alpha));
+				let mut best = (0, f32::INFINITY);
+				for (i, &pal) in CANVAS_PALETTE.iter().enumerate()
+				{
+					let d = pixelart::distance(color, pal);
+					if d < best.1 {best = (i, d);}
+				}
+				self.pixels[x as usize + y as usize * self.width as usize] = best.0 as u8;
+			}
+		}
+	}
+	
+	pub fn mirror(&mut self, horizontally: bool, vertically: bool)
+	{
+		let w = self.width as usize;
+		if horizontally
+		{
+			for row in self.pixels.chunks_exact_mut(w)
+			{
+				row.reverse();
+			}
+		}
+		if vertically
+		{
+			let rows = Vec::from_iter(self.pixels.chunks_exact(w).rev().flatten().copied());
+			self.pixels = rows;
+		}
+	}
+	
+	pub fn rotate(&mut self, clockwise: bool)
+	{
+		let (w, h) = (self.width as usize, self.height as usize);
+		let mut pixels = vec![0; self.pixels.len()];
+		for y in 0..h
+		{
+			for x in 0..w
+			{
+				// the rotated canvas is h pixels wide
+				let (nx, ny) = if clockwise {(h - 1 - y, x)} else {(y, w - 1 - x)};
+				pixels[nx + ny * h] = self.pixels[x + y * w];
+			}
+		}
+		self.pixels = pixels;
+		(self.width, self.height) = (self.height, self.width);
+	}
+}
+
+// each pixel as two digits (its palette index) so that the result has about the proportions of the canvas
+impl fmt::Display for CanvasState
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		for (i, row) in self.pixels.chunks_exact(self.width as usize).enumerate()
+		{
+			if i > 0 {f.write_str("\n")?;}
+			for &px in row
+			{
+				write!(f, "{px}{px}")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CanvasError
+{
+	OutOfBounds{x: u16, y: u16, width: u16, height: u16},
+	InvalidColor(u8),
+	DataLength{have: usize, expect: usize},
+}
+
+impl fmt::Display for CanvasError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::OutOfBounds{x, y, width, height} => write!(f, "pixel {x} / {y} is outside of the canvas ({width} x {height})"),
+			Self::InvalidColor(color) => write!(f, "invalid color {color}, expected 0 to {}", CANVAS_PALETTE.len() - 1),
+			Self::DataLength{have, expect} => write!(f, "invalid canvas data length ({have} bytes, expected {expect})"),
+		}
+	}
+}
+
+impl Error for CanvasError {}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn canvas()
+	{
+		let mut state = CanvasState::new(3, 2);
+		state.set_pixel(0, 0, 7).unwrap();
+		state.set_pixel(2, 1, 5).unwrap();
+		assert_eq!(state.set_pixel(3, 0, 1), Err(CanvasError::OutOfBounds{x: 3, y: 0, width: 3, height: 2}));
+		assert_eq!(state.set_pixel(0, 1, 8), Err(CanvasError::InvalidColor(8)));
+		let data = state.to_bytes();
+		// 6 pixels of 3 bits, the first being all ones and the last 0b101 (bits 15 and 17)
+		assert_eq!(data, vec![0x07, 0x80, 0x02]);
+		assert_eq!(CanvasState::from_bytes(3, 2, &data), Ok(state.clone()));
+		assert!(CanvasState::from_bytes(3, 3, &data).is_err());
+		
+		let mut rotated = state.clone();
+		rotated.rotate(true);
+		assert_eq!((rotated.get_width(), rotated.get_height()), (2, 3));
+		assert_eq!(rotated.get_pixel(1, 0), Some(7));
+		assert_eq!(rotated.get_pixel(0, 2), Some(5));
+		rotated.rotate(false);
+		assert_eq!(rotated, state);
+		state.mirror(true, true);
+		assert_eq!(state.get_pixels(), &[5, 0, 0, 0, 0, 7]);
+		assert_eq!(state.to_string(), "550000\n000077");
+		
+		let reg = crate::block::build_registry();
+		let canvas = reg.get("canvas").unwrap();
+		let blank = canvas.deserialize_state(DynData::Empty).unwrap().unwrap();
+		assert_eq!(canvas.serialize_state(blank.as_ref()).unwrap(), DynData::ByteArray(vec![0; 54]));
+	}
+}
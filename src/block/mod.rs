@@ -22,6 +22,7 @@ pub mod power;
 pub mod simple;
 pub mod transport;
 pub mod turret;
+pub mod upgrade;
 
 pub trait BlockLogic
 {
@@ -249,7 +250,7 @@ impl RegistryEntry for Block
 	}
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Rotation
 {
 	Right, Up, Left, Down
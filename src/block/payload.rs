@@ -115,6 +115,22 @@ impl BlockLogic for AssemblerBlock
 					Err(DeserializeError::Custom(Box::new(AssemblerDeserializeError{idx, count: self.valid.len() as i32})))
 				}
 			},
+			DynData::Content(content::Type::Unit, id) =>
+			{
+				let unit = match unit::Type::try_from(id)
+				{
+					Ok(unit) => unit,
+					Err(e) => return Err(DeserializeError::Custom(Box::new(e))),
+				};
+				if self.valid.contains(&unit)
+				{
+					Ok(Some(Self::create_state(Some(unit))))
+				}
+				else
+				{
+					Err(DeserializeError::Custom(Box::new(AssemblerUnitError(unit))))
+				}
+			},
 			_ => Err(DeserializeError::InvalidType{have: data.get_type(), expect: DynType::Int}),
 		}
 	}
@@ -122,7 +138,7 @@ impl BlockLogic for AssemblerBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -183,6 +199,19 @@ impl fmt::Display for AssemblerSerializeError
 
 impl Error for AssemblerSerializeError {}
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AssemblerUnitError(pub unit::Type);
+
+impl fmt::Display for AssemblerUnitError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		write!(f, "unit {:?} cannot be assembled here", self.0)
+	}
+}
+
+impl Error for AssemblerUnitError {}
+
 pub struct ReconstructorBlock
 {
 	size: u8,
@@ -247,7 +276,7 @@ impl BlockLogic for ReconstructorBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -354,7 +383,7 @@ impl BlockLogic for PayloadBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -8,11 +8,17 @@ use crate::data::GridPos;
 use crate::data::dynamic::{DynData, DynType};
 use crate::item::storage::Storage;
 
+// named so that their link limits and ranges can be looked up (see get_connector)
+static POWER_NODE_LOGIC: ConnectorBlock = ConnectorBlock::new(1, true, cost!(Copper: 1, Lead: 3), 10, 6);
+static POWER_NODE_LARGE_LOGIC: ConnectorBlock = ConnectorBlock::new(2, true, cost!(Lead: 10, Titanium: 5, Silicon: 3), 15, 15);
+static SURGE_TOWER_LOGIC: ConnectorBlock = ConnectorBlock::new(2, true, cost!(Lead: 10, Titanium: 7, Silicon: 15, SurgeAlloy: 15), 2, 40);
+static POWER_SOURCE_LOGIC: ConnectorBlock = ConnectorBlock::new(1, true, &[], 100, 6);
+
 make_register!
 (
-	POWER_NODE: "power-node" => ConnectorBlock::new(1, true, cost!(Copper: 1, Lead: 3), 10);
-	POWER_NODE_LARGE: "power-node-large" => ConnectorBlock::new(2, true, cost!(Lead: 10, Titanium: 5, Silicon: 3), 15);
-	SURGE_TOWER: "surge-tower" => ConnectorBlock::new(2, true, cost!(Lead: 10, Titanium: 7, Silicon: 15, SurgeAlloy: 15), 2);
+	POWER_NODE: "power-node" => POWER_NODE_LOGIC;
+	POWER_NODE_LARGE: "power-node-large" => POWER_NODE_LARGE_LOGIC;
+	SURGE_TOWER: "surge-tower" => SURGE_TOWER_LOGIC;
 	DIODE: "diode" => SimpleBlock::new(1, false, cost!(Metaglass: 10, Silicon: 10, Plastanium: 5));
 	BATTERY: "battery" => SimpleBlock::new(1, true, cost!(Copper: 5, Lead: 20));
 	BATTERY_LARGE: "battery-large" => SimpleBlock::new(3, true, cost!(Lead: 50, Titanium: 20, Silicon: 30));
@@ -27,21 +33,34 @@ make_register!
 	IMPACT_REACTOR: "impact-reactor" => SimpleBlock::new(4, true,
 		cost!(Lead: 500, Metaglass: 250, Graphite: 400, Thorium: 100, Silicon: 300, SurgeAlloy: 250));
 	// sandbox only
-	POWER_SOURCE: "power-source" => ConnectorBlock::new(1, true, &[], 100);
+	POWER_SOURCE: "power-source" => POWER_SOURCE_LOGIC;
 	POWER_VOID: "power-void" => SimpleBlock::new(1, true, &[]);
 );
 
+pub fn get_connector(name: &str) -> Option<&'static ConnectorBlock>
+{
+	match name
+	{
+		"power-node" => Some(&POWER_NODE_LOGIC),
+		"power-node-large" => Some(&POWER_NODE_LARGE_LOGIC),
+		"surge-tower" => Some(&SURGE_TOWER_LOGIC),
+		"power-source" => Some(&POWER_SOURCE_LOGIC),
+		_ => None,
+	}
+}
+
 pub struct ConnectorBlock
 {
 	size: u8,
 	symmetric: bool,
 	build_cost: BuildCost,
 	max: u8,
+	range: u16,
 }
 
 impl ConnectorBlock
 {
-	pub const fn new(size: u8, symmetric: bool, build_cost: BuildCost, max: u8) -> Self
+	pub const fn new(size: u8, symmetric: bool, build_cost: BuildCost, max: u8, range: u16) -> Self
 	{
 		if size == 0
 		{
@@ -51,7 +70,7 @@ impl ConnectorBlock
 		{
 			panic!("invalid maximum link count");
 		}
-		Self{size, symmetric, build_cost, max}
+		Self{size, symmetric, build_cost, max, range}
 	}
 	
 	pub fn get_max_links(&self) -> u8
@@ -59,6 +78,12 @@ impl ConnectorBlock
 		self.max
 	}
 	
+	// how far the lasers reach (in tiles) from the center of the block
+	pub fn get_laser_range(&self) -> u16
+	{
+		self.range
+	}
+	
 	state_impl!(pub Vec<(i16, i16)>);
 }
 
@@ -10,6 +10,10 @@ use crate::data::dynamic::{DynData, DynType};
 use crate::item;
 use crate::item::storage::Storage;
 
+// named so that their ranges can be looked up (see get_bridge)
+static BRIDGE_CONVEYOR_LOGIC: BridgeBlock = BridgeBlock::new(1, false, cost!(Copper: 6, Lead: 6), 4, true);
+static PHASE_CONVEYOR_LOGIC: BridgeBlock = BridgeBlock::new(1, false, cost!(Lead: 10, Graphite: 10, Silicon: 7, PhaseFabric: 5), 12, true);
+
 make_register!
 (
 	CONVEYOR: "conveyor" => SimpleBlock::new(1, false, cost!(Copper: 1));
@@ -17,8 +21,8 @@ make_register!
 	PLASTANIUM_CONVEYOR: "plastanium-conveyor" => SimpleBlock::new(1, false, cost!(Graphite: 1, Silicon: 1, Plastanium: 1));
 	ARMORED_CONVEYOR: "armored-conveyor" => SimpleBlock::new(1, false, cost!(Metaglass: 1, Thorium: 1, Plastanium: 1));
 	JUNCTION: "junction" => SimpleBlock::new(1, true, cost!(Copper: 2));
-	BRIDGE_CONVEYOR: "bridge-conveyor" => BridgeBlock::new(1, false, cost!(Copper: 6, Lead: 6), 4, true);
-	PHASE_CONVEYOR: "phase-conveyor" => BridgeBlock::new(1, false, cost!(Lead: 10, Graphite: 10, Silicon: 7, PhaseFabric: 5), 12, true);
+	BRIDGE_CONVEYOR: "bridge-conveyor" => BRIDGE_CONVEYOR_LOGIC;
+	PHASE_CONVEYOR: "phase-conveyor" => PHASE_CONVEYOR_LOGIC;
 	SORTER: "sorter" => ItemBlock::new(1, true, cost!(Copper: 2, Lead: 2));
 	INVERTED_SORTER: "inverted-sorter" => ItemBlock::new(1, true, cost!(Copper: 2, Lead: 2));
 	ROUTER: "router" => SimpleBlock::new(1, true, cost!(Copper: 3));
@@ -31,6 +35,17 @@ make_register!
 	ITEM_VOID: "item-void" => SimpleBlock::new(1, true, &[]);
 );
 
+// the bridges that carry items from tile to tile (unlike the mass driver)
+pub fn get_bridge(name: &str) -> Option<&'static BridgeBlock>
+{
+	match name
+	{
+		"bridge-conveyor" => Some(&BRIDGE_CONVEYOR_LOGIC),
+		"phase-conveyor" => Some(&PHASE_CONVEYOR_LOGIC),
+		_ => None,
+	}
+}
+
 pub struct ItemBlock
 {
 	size: u8,
@@ -101,7 +116,7 @@ impl BlockLogic for ItemBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, _: &mut dyn Any, _: bool, _: bool)
@@ -212,6 +227,12 @@ impl BridgeBlock
 		Self{size, symmetric, build_cost, range, ortho}
 	}
 	
+	// how far (in tiles) the bridge can link
+	pub fn get_range(&self) -> u16
+	{
+		self.range
+	}
+	
 	state_impl!(pub Option<Point2>);
 }
 
@@ -296,7 +317,7 @@ impl BlockLogic for BridgeBlock
 	fn clone_state(&self, state: &dyn Any) -> Box<dyn Any>
 	{
 		let state = Self::get_state(state);
-		Box::new(Self::create_state(*state))
+		Self::create_state(*state)
 	}
 	
 	fn mirror_state(&self, state: &mut dyn Any, horizontally: bool, vertically: bool)
@@ -0,0 +1,53 @@
+// block families ordered from the lowest to the highest tier, every member can stand in for any other
+// without changing the size or the meaning of the configuration
+const CHAINS: &[&[&str]] =
+&[
+	&["copper-wall", "titanium-wall", "plastanium-wall", "thorium-wall", "phase-wall", "surge-wall"],
+	&["copper-wall-large", "titanium-wall-large", "plastanium-wall-large", "thorium-wall-large", "phase-wall-large", "surge-wall-large"],
+	&["conveyor", "titanium-conveyor", "armored-conveyor"],
+	&["conduit", "pulse-conduit", "plated-conduit"],
+	&["mechanical-drill", "pneumatic-drill"],
+];
+
+// cheaper blocks with the same size and purpose, which don't form a tier chain with the original
+const SUBSTITUTES: &[(&str, &str)] =
+&[
+	("plastanium-conveyor", "titanium-conveyor"),
+	("phase-conveyor", "bridge-conveyor"),
+	("phase-conduit", "bridge-conduit"),
+];
+
+pub fn get_chains() -> &'static [&'static [&'static str]]
+{
+	CHAINS
+}
+
+pub fn get_chain(name: &str) -> Option<&'static [&'static str]>
+{
+	CHAINS.iter().copied().find(|c| c.contains(&name))
+}
+
+pub fn get_upgrade(name: &str) -> Option<&'static str>
+{
+	let chain = get_chain(name)?;
+	let idx = chain.iter().position(|&n| n == name)?;
+	chain.get(idx + 1).copied()
+}
+
+pub fn get_downgrade(name: &str) -> Option<&'static str>
+{
+	let chain = get_chain(name)?;
+	let idx = chain.iter().position(|&n| n == name)?;
+	if idx > 0 {Some(chain[idx - 1])} else {None}
+}
+
+// the cheaper replacements for a block (all lower tiers, closest first)
+pub fn get_substitutes(name: &str) -> impl Iterator<Item = &'static str> + '_
+{
+	let lower = match get_chain(name)
+	{
+		None => &[][..],
+		Some(chain) => &chain[..chain.iter().position(|&n| n == name).unwrap()],
+	};
+	lower.iter().rev().copied().chain(SUBSTITUTES.iter().filter(move |(from, _)| *from == name).map(|&(_, to)| to))
+}
@@ -82,6 +82,15 @@ macro_rules!content_enum
 			}
 		}
 		
+		impl $tname
+		{
+			$vis fn by_name(name: &str) -> Option<Self>
+			{
+				$(if name == $vname {return Some(Self::$name);})*
+				None
+			}
+		}
+		
 		impl std::fmt::Display for $error
 		{
 			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
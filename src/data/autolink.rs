@@ -0,0 +1,243 @@
+use crate::block::power::{get_connector, ConnectorBlock};
+use crate::data::schematic::Schematic;
+use crate::registry::RegistryEntry;
+
+// blocks connected to power besides the nodes, and whether they only consume it (consumers next to each other don't connect)
+const CONSUMERS: &[&str] = &[
+	"mender", "mend-projector", "overdrive-projector", "overdrive-dome", "force-projector", "launch-pad", "illuminator",
+	"laser-drill", "blast-drill", "water-extractor", "cultivator", "oil-extractor", "multi-press", "silicon-smelter", "silicon-crucible",
+	"kiln", "plastanium-compressor", "phase-weaver", "surge-smelter", "cryofluid-mixer", "pyratite-mixer", "blast-mixer", "melter",
+	"separator", "disassembler", "spore-press", "pulverizer", "coal-centrifuge", "incinerator", "rotary-pump", "impulse-pump",
+	"phase-conduit", "phase-conveyor", "ground-factory", "air-factory", "naval-factory", "additive-reconstructor",
+	"multiplicative-reconstructor", "exponential-reconstructor", "tetrative-reconstructor", "repair-point", "repair-turret",
+	"mass-driver", "lancer", "arc", "parallax", "segment", "foreshadow", "meltdown", "power-void",
+];
+const PRODUCERS: &[&str] = &[
+	"battery", "battery-large", "combustion-generator", "thermal-generator", "steam-generator", "differential-generator",
+	"rtg-generator", "solar-panel", "solar-panel-large", "thorium-reactor", "impact-reactor",
+];
+// lasers can't pass through these
+const INSULATORS: &[&str] = &["plastanium-wall", "plastanium-wall-large"];
+
+#[derive(Clone, Copy)]
+enum Kind
+{
+	Node(&'static ConnectorBlock),
+	Consumer,
+	Producer,
+	// conducts power in one direction only, which isn't modelled, so it never joins networks
+	Diode,
+}
+
+struct Power
+{
+	pos: (u16, u16),
+	// bottom left corner and size
+	left: (i32, i32),
+	size: i32,
+	kind: Kind,
+	// the blocks this one's links point to
+	targets: Vec<usize>,
+}
+
+impl Power
+{
+	fn center(&self) -> (f32, f32)
+	{
+		(self.left.0 as f32 + self.size as f32 / 2.0, self.left.1 as f32 + self.size as f32 / 2.0)
+	}
+	
+	fn is_adjacent(&self, other: &Power) -> bool
+	{
+		let (ax, ay, bx, by) = (self.left.0, self.left.1, other.left.0, other.left.1);
+		let overlap_x = ax < bx + other.size && bx < ax + self.size;
+		let overlap_y = ay < by + other.size && by < ay + self.size;
+		(overlap_y && (ax + self.size == bx || bx + other.size == ax)) || (overlap_x && (ay + self.size == by || by + other.size == ay))
+	}
+	
+	// whether a laser from the center with this range touches the other block
+	fn reaches(&self, range: u16, other: &Power) -> bool
+	{
+		let (cx, cy) = self.center();
+		let nx = cx.clamp(other.left.0 as f32, (other.left.0 + other.size) as f32);
+		let ny = cy.clamp(other.left.1 as f32, (other.left.1 + other.size) as f32);
+		let range = range as f32;
+		(nx - cx) * (nx - cx) + (ny - cy) * (ny - cy) < range * range
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AutolinkSummary
+{
+	pub nodes: usize,
+	pub links: usize,
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize
+{
+	while parent[i] != i
+	{
+		parent[i] = parent[parent[i]];
+		i = parent[i];
+	}
+	i
+}
+
+fn is_insulated(schematic: &Schematic, from: (f32, f32), to: (f32, f32)) -> bool
+{
+	let steps = ((to.0 - from.0).abs().max((to.1 - from.1).abs()) * 4.0).ceil() as u32;
+	for i in 1..steps
+	{
+		let t = i as f32 / steps as f32;
+		let (x, y) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+		if let Ok(Some(p)) = schematic.get(x as u16, y as u16)
+		{
+			if INSULATORS.contains(&p.get_block().get_name()) {return true;}
+		}
+	}
+	false
+}
+
+// links power nodes the way the game does when they are placed in the order of the schematic: each node links to one block
+// of every network in range that it isn't part of yet, preferring other nodes and then the closest blocks, until it runs out of
+// links. adjacent blocks and existing links already count as connected, unless clear removes all links first
+pub fn autolink(schematic: &mut Schematic, clear: bool) -> AutolinkSummary
+{
+	let mut blocks = Vec::<Power>::new();
+	for p in schematic.block_iter()
+	{
+		let name = p.get_block().get_name();
+		let kind = if let Some(node) = get_connector(name) {Kind::Node(node)}
+			else if CONSUMERS.contains(&name) {Kind::Consumer}
+			else if PRODUCERS.contains(&name) {Kind::Producer}
+			else if name == "diode" {Kind::Diode}
+			else {continue;};
+		let (pos, size) = (p.get_pos(), p.get_block().get_size() as i32);
+		let left = (pos.0 as i32 - (size - 1) / 2, pos.1 as i32 - (size - 1) / 2);
+		blocks.push(Power{pos: (pos.0, pos.1), left, size, kind, targets: Vec::new()});
+	}
+	let lookup = |blocks: &[Power], x: i32, y: i32| blocks.iter().position(|b| x >= b.left.0 && y >= b.left.1 && x < b.left.0 + b.size && y < b.left.1 + b.size);
+	
+	let mut links = Vec::<Vec<(i16, i16)>>::new();
+	for b in blocks.iter()
+	{
+		let state = match b.kind
+		{
+			Kind::Node(..) => schematic.get(b.pos.0, b.pos.1).ok().flatten().and_then(|p| p.get_state()).and_then(|s| s.downcast_ref::<Vec<(i16, i16)>>()),
+			_ => None,
+		};
+		links.push(if clear {Vec::new()} else {state.cloned().unwrap_or_default()});
+	}
+	let mut parent = Vec::from_iter(0..blocks.len());
+	for i in 0..blocks.len()
+	{
+		for j in i + 1..blocks.len()
+		{
+			let conducts = !matches!((blocks[i].kind, blocks[j].kind), (Kind::Diode, _) | (_, Kind::Diode) | (Kind::Consumer, Kind::Consumer));
+			if conducts && blocks[i].is_adjacent(&blocks[j])
+			{
+				let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+				parent[ri] = rj;
+			}
+		}
+		for &(dx, dy) in links[i].iter()
+		{
+			let target = (blocks[i].pos.0 as i32 + dx as i32, blocks[i].pos.1 as i32 + dy as i32);
+			if let Some(j) = lookup(&blocks, target.0, target.1)
+			{
+				let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+				parent[ri] = rj;
+				blocks[i].targets.push(j);
+			}
+		}
+	}
+	
+	// a link counts for both nodes, but only once if both of them list it
+	let link_count = |blocks: &[Power], links: &[Vec<(i16, i16)>], i: usize|
+	{
+		links[i].len() + (0..blocks.len()).filter(|&j| blocks[j].targets.contains(&i) && !blocks[i].targets.contains(&j)).count()
+	};
+	let mut summary = AutolinkSummary::default();
+	for i in 0..blocks.len()
+	{
+		let Kind::Node(node) = blocks[i].kind
+		else {continue;};
+		summary.nodes += 1;
+		let mut candidates = Vec::from_iter((0..blocks.len()).filter(|&j|
+		{
+			let other = &blocks[j];
+			j != i && !matches!(other.kind, Kind::Diode) && blocks[i].reaches(node.get_laser_range(), other) && !blocks[i].is_adjacent(other)
+				&& !is_insulated(schematic, blocks[i].center(), other.center())
+		}));
+		let (cx, cy) = blocks[i].center();
+		candidates.sort_by(|&a, &b|
+		{
+			let dist = |j: usize| {let (x, y) = blocks[j].center(); (x - cx) * (x - cx) + (y - cy) * (y - cy)};
+			let is_node = |j: usize| matches!(blocks[j].kind, Kind::Node(..));
+			is_node(b).cmp(&is_node(a)).then(dist(a).total_cmp(&dist(b)))
+		});
+		for j in candidates
+		{
+			if link_count(&blocks, &links, i) >= node.get_max_links() as usize {break;}
+			if find(&mut parent, i) == find(&mut parent, j) {continue;}
+			if let Kind::Node(other) = blocks[j].kind
+			{
+				if link_count(&blocks, &links, j) >= other.get_max_links() as usize {continue;}
+			}
+			blocks[i].targets.push(j);
+			links[i].push(((blocks[j].pos.0 as i32 - blocks[i].pos.0 as i32) as i16, (blocks[j].pos.1 as i32 - blocks[i].pos.1 as i32) as i16));
+			let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+			parent[ri] = rj;
+			summary.links += 1;
+		}
+	}
+	
+	for (b, links) in blocks.iter().zip(links)
+	{
+		if !matches!(b.kind, Kind::Node(..)) {continue;}
+		let state = schematic.get_mut(b.pos.0, b.pos.1).ok().flatten().and_then(|p| p.get_state_mut());
+		if let Some(state) = state.and_then(|s| s.downcast_mut::<Vec<(i16, i16)>>())
+		{
+			*state = links;
+		}
+	}
+	summary
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, Rotation};
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn link()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(14, 6);
+		let mut place = |x, y, name|
+		{
+			schematic.set(x, y, reg.get(name).unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		};
+		place(2, 2, "power-node");
+		place(5, 2, "combustion-generator");
+		place(8, 2, "power-node");
+		// touches the second node, so they are connected already
+		place(10, 2, "laser-drill");
+		// phase bridges need power to move anything
+		place(13, 5, "phase-conveyor");
+		// in range, but behind an insulating wall
+		place(2, 4, "plastanium-wall");
+		place(2, 5, "mender");
+		let summary = autolink(&mut schematic, false);
+		assert_eq!(summary, AutolinkSummary{nodes: 2, links: 3});
+		let links = |s: &Schematic, x, y| s.get(x, y).unwrap().unwrap().get_state().unwrap().downcast_ref::<Vec<(i16, i16)>>().unwrap().clone();
+		// the other node comes first, then the generator
+		assert_eq!(links(&schematic, 2, 2), vec![(6, 0), (3, 0)]);
+		assert_eq!(links(&schematic, 8, 2), vec![(5, 3)]);
+		// nothing left to connect
+		assert_eq!(autolink(&mut schematic, false).links, 0);
+		assert_eq!(autolink(&mut schematic, true).links, 3);
+	}
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::block::{Block, Rotation};
+use crate::data::GridPos;
+use crate::data::dynamic::DynData;
+use crate::data::schematic::{Placement, Schematic};
+use crate::item::storage::Storage as ItemStorage;
+use crate::registry::RegistryEntry;
+
+#[derive(Debug)]
+pub enum Change<'l>
+{
+	Resized{from: (u16, u16), to: (u16, u16)},
+	Added{pos: GridPos, block: &'l Block, rot: Rotation},
+	// position in the old schematic
+	Removed{pos: GridPos, block: &'l Block},
+	// from is in the old schematic, to in the new one
+	Moved{from: GridPos, to: GridPos, block: &'l Block},
+	Rotated{pos: GridPos, block: &'l Block, from: Rotation, to: Rotation},
+	Configured{pos: GridPos, block: &'l Block, from: DynData, to: DynData},
+	Tag{key: String, from: Option<String>, to: Option<String>},
+}
+
+impl<'l> Change<'l>
+{
+	// sorts resizing first, then block changes by position (bottom to top) and tags last
+	fn sort_key(&self) -> (u8, u16, u16)
+	{
+		match self
+		{
+			Self::Resized{..} => (0, 0, 0),
+			Self::Added{pos, ..} | Self::Removed{pos, ..} | Self::Moved{to: pos, ..} | Self::Rotated{pos, ..}
+				| Self::Configured{pos, ..} => (1, pos.1, pos.0),
+			Self::Tag{..} => (2, 0, 0),
+		}
+	}
+}
+
+pub(crate) fn rot_name(rot: Rotation) -> &'static str
+{
+	match rot
+	{
+		Rotation::Right => "right",
+		Rotation::Up => "up",
+		Rotation::Left => "left",
+		Rotation::Down => "down",
+	}
+}
+
+impl<'l> fmt::Display for Change<'l>
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Resized{from, to} => write!(f, "~ size {} x {} -> {} x {}", from.0, from.1, to.0, to.1),
+			Self::Added{pos, block, rot} =>
+			{
+				write!(f, "+ {} at {} / {}", block.get_name(), pos.0, pos.1)?;
+				if !block.is_symmetric() {write!(f, " ({})", rot_name(*rot))?;}
+				Ok(())
+			},
+			Self::Removed{pos, block} => write!(f, "- {} at {} / {}", block.get_name(), pos.0, pos.1),
+			Self::Moved{from, to, block} => write!(f, "~ {} moved from {} / {} to {} / {}", block.get_name(), from.0, from.1, to.0, to.1),
+			Self::Rotated{pos, block, from, to} =>
+			{
+				write!(f, "~ {} at {} / {} rotated {} -> {}", block.get_name(), pos.0, pos.1, rot_name(*from), rot_name(*to))
+			},
+			Self::Configured{pos, block, from, to} => write!(f, "~ {} at {} / {} configured {from} -> {to}", block.get_name(), pos.0, pos.1),
+			Self::Tag{key, from, to} =>
+			{
+				write!(f, "~ tag {key:?}: ")?;
+				match from
+				{
+					None => f.write_str("<none>")?,
+					Some(v) => write!(f, "{v:?}")?,
+				}
+				f.write_str(" -> ")?;
+				match to
+				{
+					None => f.write_str("<none>"),
+					Some(v) => write!(f, "{v:?}"),
+				}
+			},
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Diff<'l>
+{
+	// translation of the whole design (if detection was requested), old positions plus this offset are new positions
+	pub offset: (i32, i32),
+	pub changes: Vec<Change<'l>>,
+	pub cost_before: ItemStorage,
+	pub cost_after: ItemStorage,
+}
+
+impl<'l> Diff<'l>
+{
+	pub fn is_empty(&self) -> bool
+	{
+		self.offset == (0, 0) && self.changes.is_empty()
+	}
+}
+
+fn get_config(p: &Placement) -> DynData
+{
+	match p.get_state()
+	{
+		None => DynData::Empty,
+		Some(state) => p.get_block().serialize_state(state).unwrap_or(DynData::Empty),
+	}
+}
+
+fn to_i32(pos: GridPos) -> (i32, i32)
+{
+	(pos.0 as i32, pos.1 as i32)
+}
+
+// finds the offset under which the most blocks of the old schematic line up with the new one
+fn find_offset(old: &Schematic, new: &Schematic) -> (i32, i32)
+{
+	let new_at: HashMap<(i32, i32), &str> = new.block_iter().map(|p| (to_i32(p.get_pos()), p.get_block().get_name())).collect();
+	let count = |(dx, dy): (i32, i32)|
+	{
+		old.block_iter().filter(|p|
+		{
+			let (x, y) = to_i32(p.get_pos());
+			new_at.get(&(x + dx, y + dy)) == Some(&p.get_block().get_name())
+		}).count()
+	};
+	// if the whole design moved, the first instance of the rarest block must be one of its instances in the new schematic
+	let mut counts = HashMap::<&str, (usize, usize)>::new();
+	for p in old.block_iter() {counts.entry(p.get_block().get_name()).or_default().0 += 1;}
+	for p in new.block_iter() {counts.entry(p.get_block().get_name()).or_default().1 += 1;}
+	let rarest = counts.into_iter().filter(|(_, (o, n))| *o > 0 && *n > 0).min_by_key(|&(name, (o, n))| (o.max(n), name));
+	let mut best = ((0, 0), count((0, 0)));
+	if let Some((name, _)) = rarest
+	{
+		let first = to_i32(old.block_iter().find(|p| p.get_block().get_name() == name).unwrap().get_pos());
+		for p in new.block_iter().filter(|p| p.get_block().get_name() == name)
+		{
+			let (x, y) = to_i32(p.get_pos());
+			let offset = (x - first.0, y - first.1);
+			let n = count(offset);
+			// only prefer an offset if it is strictly better than what we've got
+			if n > best.1 {best = (offset, n);}
+		}
+	}
+	best.0
+}
+
+pub fn diff<'l>(old: &Schematic<'l>, new: &Schematic<'l>, detect_offset: bool) -> Diff<'l>
+{
+	let mut changes = Vec::new();
+	if old.get_width() != new.get_width() || old.get_height() != new.get_height()
+	{
+		changes.push(Change::Resized{from: (old.get_width(), old.get_height()), to: (new.get_width(), new.get_height())});
+	}
+	let offset = if detect_offset {find_offset(old, new)} else {(0, 0)};
+	let new_blocks = Vec::from_iter(new.block_iter());
+	let new_at: HashMap<(i32, i32), usize> = new_blocks.iter().enumerate().map(|(i, p)| (to_i32(p.get_pos()), i)).collect();
+	let mut used = vec![false; new_blocks.len()];
+	let mut unmatched = Vec::new();
+	let compare = |changes: &mut Vec<Change<'l>>, prev: &Placement<'l>, curr: &Placement<'l>|
+	{
+		let block = curr.get_block();
+		if !block.is_symmetric() && prev.get_rotation() != curr.get_rotation()
+		{
+			changes.push(Change::Rotated{pos: curr.get_pos(), block, from: prev.get_rotation(), to: curr.get_rotation()});
+		}
+		let (from, to) = (get_config(prev), get_config(curr));
+		if from != to {changes.push(Change::Configured{pos: curr.get_pos(), block, from, to});}
+	};
+	for prev in old.block_iter()
+	{
+		let (x, y) = to_i32(prev.get_pos());
+		match new_at.get(&(x + offset.0, y + offset.1))
+		{
+			Some(&i) if new_blocks[i].get_block().get_name() == prev.get_block().get_name() =>
+			{
+				used[i] = true;
+				compare(&mut changes, prev, new_blocks[i]);
+			},
+			_ => unmatched.push(prev),
+		}
+	}
+	// blocks that disappeared but have an identical (configured) copy somewhere else are considered moved
+	for prev in unmatched
+	{
+		let (x, y) = to_i32(prev.get_pos());
+		let (x, y) = (x + offset.0, y + offset.1);
+		let config = get_config(prev);
+		let target = new_blocks.iter().enumerate()
+			.filter(|&(i, p)| !used[i] && p.get_block().get_name() == prev.get_block().get_name() && get_config(p) == config)
+			.min_by_key(|(_, p)|
+			{
+				let (nx, ny) = to_i32(p.get_pos());
+				(nx - x).abs() + (ny - y).abs()
+			});
+		match target
+		{
+			Some((i, curr)) =>
+			{
+				used[i] = true;
+				changes.push(Change::Moved{from: prev.get_pos(), to: curr.get_pos(), block: curr.get_block()});
+				compare(&mut changes, prev, curr);
+			},
+			None => changes.push(Change::Removed{pos: prev.get_pos(), block: prev.get_block()}),
+		}
+	}
+	for (i, curr) in new_blocks.iter().enumerate()
+	{
+		if !used[i] {changes.push(Change::Added{pos: curr.get_pos(), block: curr.get_block(), rot: curr.get_rotation()});}
+	}
+	let mut keys = Vec::from_iter(old.get_tags().keys().chain(new.get_tags().keys()));
+	keys.sort();
+	keys.dedup();
+	for key in keys
+	{
+		let (from, to) = (old.get_tags().get(key), new.get_tags().get(key));
+		if from != to {changes.push(Change::Tag{key: key.clone(), from: from.cloned(), to: to.cloned()});}
+	}
+	changes.sort_by_key(Change::sort_key);
+	Diff{offset, changes, cost_before: old.compute_total_cost().0, cost_after: new.compute_total_cost().0}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, BlockRegistry};
+	use crate::content;
+	
+	fn sample<'l>(reg: &'l BlockRegistry<'l>, dx: u16, dy: u16) -> Schematic<'l>
+	{
+		let mut schematic = Schematic::new(8, 8);
+		schematic.set(dx, dy, reg.get("conveyor").unwrap(), DynData::Empty, Rotation::Up).unwrap();
+		schematic.set(dx + 1, dy, reg.get("sorter").unwrap(), DynData::Content(content::Type::Item, 0), Rotation::Right).unwrap();
+		schematic.set(dx + 2, dy + 2, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		schematic
+	}
+	
+	#[test]
+	fn changes()
+	{
+		let reg = build_registry();
+		let old = sample(&reg, 0, 0);
+		assert!(diff(&old, &old, true).is_empty());
+		let mut new = sample(&reg, 0, 0);
+		new.take(2, 2).unwrap();
+		new.set(5, 5, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		new.get_mut(0, 0).unwrap().unwrap().set_rotation(Rotation::Left);
+		new.get_mut(1, 0).unwrap().unwrap().set_state(DynData::Content(content::Type::Item, 1)).unwrap();
+		new.set(3, 0, reg.get("router").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		new.get_tags_mut().insert("name".to_string(), "test".to_string());
+		let result = diff(&old, &new, false);
+		let lines = Vec::from_iter(result.changes.iter().map(|c| c.to_string()));
+		assert_eq!(lines, vec![
+			"~ conveyor at 0 / 0 rotated up -> left",
+			r#"~ sorter at 1 / 0 configured copper -> lead"#,
+			"+ router at 3 / 0",
+			"~ copper-wall moved from 2 / 2 to 5 / 5",
+			r#"~ tag "name": "" -> "test""#,
+		]);
+		assert_eq!(result.cost_after.get(crate::item::Type::Copper), result.cost_before.get(crate::item::Type::Copper) + 3);
+	}
+	
+	#[test]
+	fn translation()
+	{
+		let reg = build_registry();
+		let result = diff(&sample(&reg, 0, 0), &sample(&reg, 3, 2), true);
+		assert_eq!(result.offset, (3, 2));
+		assert!(result.changes.is_empty());
+		// without detection everything has moved
+		let result = diff(&sample(&reg, 0, 0), &sample(&reg, 3, 2), false);
+		assert_eq!(result.changes.len(), 3);
+	}
+}
@@ -70,6 +70,58 @@ impl DynData
 	}
 }
 
+impl fmt::Display for DynData
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		fn write_seq<T, F: Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result>(f: &mut fmt::Formatter<'_>, seq: &[T], write: F) -> fmt::Result
+		{
+			f.write_str("[")?;
+			for (i, v) in seq.iter().enumerate()
+			{
+				if i > 0 {f.write_str(", ")?;}
+				write(f, v)?;
+			}
+			f.write_str("]")
+		}
+		
+		match self
+		{
+			Self::Empty => f.write_str("empty"),
+			Self::Int(v) => write!(f, "{v}"),
+			Self::Long(v) => write!(f, "{v}"),
+			Self::Float(v) => write!(f, "{v}"),
+			Self::String(None) => f.write_str("null"),
+			Self::String(Some(s)) => write!(f, "{s:?}"),
+			Self::Content(t, id) | Self::TechNode(t, id) =>
+			{
+				if let Self::TechNode(..) = self {f.write_str("tech node ")?;}
+				match t.get(*id)
+				{
+					Ok(c) => f.write_str(c.get_name()),
+					Err(..) => write!(f, "{t:?} #{id}"),
+				}
+			},
+			Self::IntSeq(seq) | Self::IntArray(seq) => write_seq(f, seq, |f, v| write!(f, "{v}")),
+			Self::Point2(x, y) => write!(f, "({x}, {y})"),
+			Self::Point2Array(seq) => write_seq(f, seq, |f, (x, y)| write!(f, "({x}, {y})")),
+			Self::Boolean(v) => write!(f, "{v}"),
+			Self::Double(v) => write!(f, "{v}"),
+			Self::Building(GridPos(x, y)) => write!(f, "building at {x} / {y}"),
+			Self::LogicField(v) => write!(f, "{v:?}"),
+			Self::ByteArray(v) => write!(f, "{} bytes", v.len()),
+			Self::UnitStrategy(v) => write!(f, "{v:?}"),
+			Self::BoolArray(seq) => write_seq(f, seq, |f, v| write!(f, "{v}")),
+			Self::Unit(id) => write!(f, "unit #{id}"),
+			Self::Vec2Array(seq) => write_seq(f, seq, |f, (x, y)| write!(f, "({x}, {y})")),
+			Self::Vec2(x, y) => write!(f, "({x}, {y})"),
+			Self::Team(t) => write!(f, "{t}"),
+			Self::ObjArray(seq) => write_seq(f, seq, |f, v| write!(f, "{v}")),
+			Self::UnitCommand(v) => write!(f, "{v:?}"),
+		}
+	}
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DynType
 {
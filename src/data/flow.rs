@@ -0,0 +1,324 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::block::{Block, Rotation};
+use crate::block::transport::get_bridge;
+use crate::data::GridPos;
+use crate::data::schematic::Schematic;
+use crate::registry::RegistryEntry;
+
+pub(crate) const CONVEYORS: &[&str] = &["conveyor", "titanium-conveyor", "plastanium-conveyor", "armored-conveyor"];
+// pass items on to any side
+const SPREADERS: &[&str] = &["router", "distributor", "sorter", "inverted-sorter", "overflow-gate", "underflow-gate"];
+// blocks that don't take items, besides walls (anything else accepts at least some items)
+pub(crate) const NO_ITEMS: &[&str] = &[
+	"shock-mine", "illuminator", "door", "door-large", "thruster", "mechanical-drill", "pneumatic-drill", "laser-drill", "blast-drill",
+	"water-extractor", "cultivator", "separator", "coal-centrifuge", "heat-source", "power-node", "power-node-large", "surge-tower", "diode",
+	"battery", "battery-large", "thermal-generator", "solar-panel", "solar-panel-large", "power-source", "power-void", "mechanical-pump",
+	"rotary-pump", "impulse-pump", "conduit", "pulse-conduit", "plated-conduit", "liquid-router", "liquid-container", "liquid-tank",
+	"liquid-junction", "bridge-conduit", "phase-conduit", "liquid-source", "liquid-void", "message", "switch", "micro-processor",
+	"logic-processor", "hyper-processor", "memory-cell", "memory-bank", "logic-display", "large-logic-display", "canvas", "repair-point",
+	"repair-turret", "payload-conveyor", "payload-router", "payload-source", "payload-void", "item-source", "unloader", "wave", "lancer",
+	"arc", "parallax", "segment", "tsunami", "meltdown",
+];
+pub(crate) const DIRECTIONS: [Rotation; 4] = [Rotation::Right, Rotation::Up, Rotation::Left, Rotation::Down];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind
+{
+	Conveyor(Rotation),
+	Junction,
+	// the offset of the linked bridge, if any
+	Bridge(Option<(i32, i32)>),
+	Spreader,
+	// whether the block takes items at all
+	Other(bool),
+}
+
+impl Kind
+{
+	fn of(block: &Block, rot: Rotation, state: Option<&dyn Any>) -> Self
+	{
+		let name = block.get_name();
+		if CONVEYORS.contains(&name) {Kind::Conveyor(rot)}
+		else if name == "junction" {Kind::Junction}
+		else if get_bridge(name).is_some()
+		{
+			let link = state.and_then(|s| s.downcast_ref::<Option<(i32, i32)>>()).copied().flatten();
+			Kind::Bridge(link.filter(|&l| l != (0, 0)))
+		}
+		else if SPREADERS.contains(&name) {Kind::Spreader}
+		else {Kind::Other(!name.contains("wall") && !NO_ITEMS.contains(&name))}
+	}
+	
+	// whether items moving in this direction can enter the block
+	fn accepts(self, dir: Rotation) -> bool
+	{
+		match self
+		{
+			Kind::Conveyor(rot) => rot != dir.rotated_180(),
+			Kind::Other(accepts) => accepts,
+			_ => true,
+		}
+	}
+}
+
+// whether items moving in this direction can enter the block
+pub(crate) fn takes_items(block: &Block, rot: Rotation, dir: Rotation) -> bool
+{
+	Kind::of(block, rot, None).accepts(dir)
+}
+
+#[derive(Debug)]
+pub enum Issue<'l>
+{
+	// passes items into an empty tile, leaving the schematic doesn't count
+	DeadEnd{pos: GridPos, block: &'l Block},
+	// passes items into a block that doesn't take them from this side
+	Blocked{pos: GridPos, block: &'l Block, into: GridPos, target: &'l Block},
+	// two conveyors facing each other, only listed once
+	HeadOn{pos: GridPos, block: &'l Block, other: GridPos},
+	// items that enter go round in circles forever, in the order they travel
+	Loop(Vec<(GridPos, &'l Block)>),
+	// the end of a bridge with nowhere to put the items it receives
+	NoOutlet{pos: GridPos, block: &'l Block},
+	// linked to a tile that doesn't hold a bridge of the same type
+	BadLink{pos: GridPos, block: &'l Block, target: (i32, i32)},
+}
+
+impl<'l> fmt::Display for Issue<'l>
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::DeadEnd{pos, block} => write!(f, "{} at {} / {} leads nowhere", block.get_name(), pos.0, pos.1),
+			Self::Blocked{pos, block, into, target} =>
+			{
+				write!(f, "{} at {} / {} leads into {} at {} / {}", block.get_name(), pos.0, pos.1, target.get_name(), into.0, into.1)
+			},
+			Self::HeadOn{pos, block, other} => write!(f, "{} at {} / {} faces the one at {} / {}", block.get_name(), pos.0, pos.1, other.0, other.1),
+			Self::Loop(blocks) =>
+			{
+				write!(f, "loop of {} blocks:", blocks.len())?;
+				for (i, (pos, block)) in blocks.iter().enumerate()
+				{
+					write!(f, "{} {} at {} / {}", if i == 0 {""} else {" ->"}, block.get_name(), pos.0, pos.1)?;
+				}
+				Ok(())
+			},
+			Self::NoOutlet{pos, block} => write!(f, "{} at {} / {} has no outlet", block.get_name(), pos.0, pos.1),
+			Self::BadLink{pos, block, target} =>
+			{
+				write!(f, "{} at {} / {} is linked to {} / {}, which isn't a matching bridge", block.get_name(), pos.0, pos.1, target.0, target.1)
+			},
+		}
+	}
+}
+
+// everything that passes items in a single direction, i.e. conveyors, linked bridges and the lanes of a junction
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Node
+{
+	Block(usize),
+	Lane(usize, Rotation),
+}
+
+impl Node
+{
+	fn index(self) -> usize
+	{
+		match self
+		{
+			Node::Block(i) | Node::Lane(i, _) => i,
+		}
+	}
+}
+
+pub(crate) fn offset(rot: Rotation) -> (i32, i32)
+{
+	match rot
+	{
+		Rotation::Right => (1, 0),
+		Rotation::Up => (0, 1),
+		Rotation::Left => (-1, 0),
+		Rotation::Down => (0, -1),
+	}
+}
+
+// follows the items through conveyors, junctions and bridges, routers and other blocks are where each path ends
+pub fn analyze<'l>(schematic: &Schematic<'l>) -> Vec<Issue<'l>>
+{
+	let (width, height) = (schematic.get_width() as i32, schematic.get_height() as i32);
+	let mut blocks = Vec::<(GridPos, &'l Block, Kind)>::new();
+	let mut grid = vec![None; width as usize * height as usize];
+	for p in schematic.block_iter()
+	{
+		let (pos, size) = (p.get_pos(), p.get_block().get_size() as u16);
+		let left = (pos.0 - (size - 1) / 2, pos.1 - (size - 1) / 2);
+		for y in left.1..left.1 + size
+		{
+			for x in left.0..left.0 + size
+			{
+				grid[y as usize * width as usize + x as usize] = Some(blocks.len());
+			}
+		}
+		blocks.push((pos, p.get_block(), Kind::of(p.get_block(), p.get_rotation(), p.get_state())));
+	}
+	// None outside the schematic, Some(None) for empty tiles
+	let tile = |x: i32, y: i32| (x >= 0 && y >= 0 && x < width && y < height).then(|| grid[(y * width + x) as usize]);
+	
+	let mut issues = Vec::new();
+	let mut next = HashMap::<Node, Node>::new();
+	let mut nodes = Vec::from_iter((0..blocks.len()).filter(|&i| matches!(blocks[i].2, Kind::Conveyor(..) | Kind::Bridge(Some(..)))).map(Node::Block));
+	// bridges at the end of a chain, with the bridge linked to them
+	let mut ends = Vec::<(usize, usize)>::new();
+	let mut n = 0;
+	while n < nodes.len()
+	{
+		let node = nodes[n];
+		n += 1;
+		let (pos, block, kind) = blocks[node.index()];
+		let dir = match (node, kind)
+		{
+			(Node::Block(..), Kind::Conveyor(rot)) | (Node::Lane(_, rot), _) => rot,
+			(_, Kind::Bridge(Some((dx, dy)))) =>
+			{
+				let target = (pos.0 as i32 + dx, pos.1 as i32 + dy);
+				match tile(target.0, target.1).flatten()
+				{
+					Some(k) if blocks[k].1.get_name() == block.get_name() && (blocks[k].0.0 as i32, blocks[k].0.1 as i32) == target =>
+					{
+						if let Kind::Bridge(Some(..)) = blocks[k].2 {next.insert(node, Node::Block(k));}
+						else {ends.push((k, node.index()));}
+					},
+					_ => issues.push(Issue::BadLink{pos, block, target}),
+				}
+				continue;
+			},
+			_ => unreachable!(),
+		};
+		let (dx, dy) = offset(dir);
+		match tile(pos.0 as i32 + dx, pos.1 as i32 + dy)
+		{
+			// leaves the schematic
+			None => (),
+			Some(None) => issues.push(Issue::DeadEnd{pos, block}),
+			Some(Some(k)) =>
+			{
+				let (into, target, target_kind) = blocks[k];
+				match target_kind
+				{
+					Kind::Conveyor(rot) if rot == dir.rotated_180() && matches!(node, Node::Block(..)) =>
+					{
+						if node.index() < k {issues.push(Issue::HeadOn{pos, block, other: into});}
+					},
+					Kind::Conveyor(..) | Kind::Bridge(Some(..)) if target_kind.accepts(dir) => {next.insert(node, Node::Block(k));},
+					Kind::Junction =>
+					{
+						let lane = Node::Lane(k, dir);
+						if next.insert(node, lane).is_none() && !nodes.contains(&lane) {nodes.push(lane);}
+					},
+					_ if target_kind.accepts(dir) => (),
+					_ => issues.push(Issue::Blocked{pos, block, into, target}),
+				}
+			},
+		}
+	}
+	
+	let mut checked = HashSet::new();
+	for &(k, _) in ends.iter()
+	{
+		if !checked.insert(k) {continue;}
+		let (pos, block, _) = blocks[k];
+		// bridges don't pass items back towards the bridges linked to them
+		let inputs = Vec::from_iter(ends.iter().filter(|e| e.0 == k).map(|e|
+		{
+			let src = blocks[e.1].0;
+			(src.0 as i32 - pos.0 as i32, src.1 as i32 - pos.1 as i32)
+		}));
+		let has_outlet = DIRECTIONS.iter().any(|&dir|
+		{
+			let (dx, dy) = offset(dir);
+			if inputs.iter().any(|&(ix, iy)| ix.signum() == dx && iy.signum() == dy) {return false;}
+			match tile(pos.0 as i32 + dx, pos.1 as i32 + dy)
+			{
+				None => true,
+				Some(None) => false,
+				Some(Some(j)) => blocks[j].2.accepts(dir),
+			}
+		});
+		if !has_outlet {issues.push(Issue::NoOutlet{pos, block});}
+	}
+	
+	// every node passes items to at most one other, so a loop is found by following them until one repeats
+	let mut done = HashSet::new();
+	for &start in nodes.iter()
+	{
+		let mut path = Vec::new();
+		let mut curr = Some(start);
+		while let Some(node) = curr
+		{
+			if done.contains(&node) {break;}
+			if let Some(at) = path.iter().position(|&n| n == node)
+			{
+				issues.push(Issue::Loop(Vec::from_iter(path[at..].iter().map(|n: &Node| (blocks[n.index()].0, blocks[n.index()].1)))));
+				break;
+			}
+			path.push(node);
+			curr = next.get(&node).copied();
+		}
+		done.extend(path);
+	}
+	issues
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn issues()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(10, 6);
+		let mut place = |x, y, name, data, rot| {schematic.set(x, y, reg.get(name).unwrap(), data, rot).unwrap();};
+		place(0, 0, "conveyor", DynData::Empty, Rotation::Right);
+		place(1, 0, "conveyor", DynData::Empty, Rotation::Right);
+		place(0, 1, "conveyor", DynData::Empty, Rotation::Right);
+		place(1, 1, "copper-wall", DynData::Empty, Rotation::Right);
+		place(0, 2, "conveyor", DynData::Empty, Rotation::Right);
+		place(1, 2, "conveyor", DynData::Empty, Rotation::Left);
+		for (x, y, rot) in [(4, 0, Rotation::Right), (5, 0, Rotation::Up), (5, 1, Rotation::Left), (4, 1, Rotation::Down)]
+		{
+			place(x, y, "titanium-conveyor", DynData::Empty, rot);
+		}
+		// walled in apart from the side it is linked from
+		place(0, 4, "bridge-conveyor", DynData::Point2(3, 0), Rotation::Right);
+		place(3, 4, "bridge-conveyor", DynData::Empty, Rotation::Right);
+		for (x, y) in [(4, 4), (3, 3), (3, 5)] {place(x, y, "copper-wall", DynData::Empty, Rotation::Right);}
+		place(6, 4, "bridge-conveyor", DynData::Point2(0, -2), Rotation::Right);
+		// fine, passes through the junction and out of the schematic
+		place(7, 2, "conveyor", DynData::Empty, Rotation::Right);
+		place(8, 2, "junction", DynData::Empty, Rotation::Right);
+		place(9, 2, "conveyor", DynData::Empty, Rotation::Right);
+		// drills only put items out
+		place(7, 4, "conveyor", DynData::Empty, Rotation::Right);
+		place(8, 4, "mechanical-drill", DynData::Empty, Rotation::Right);
+		
+		let issues = Vec::from_iter(analyze(&schematic).iter().map(ToString::to_string));
+		assert_eq!(issues, [
+			"conveyor at 1 / 0 leads nowhere",
+			"conveyor at 0 / 1 leads into copper-wall at 1 / 1",
+			"conveyor at 0 / 2 faces the one at 1 / 2",
+			"bridge-conveyor at 6 / 4 is linked to 6 / 2, which isn't a matching bridge",
+			"conveyor at 7 / 4 leads into mechanical-drill at 8 / 4",
+			"bridge-conveyor at 3 / 4 has no outlet",
+			"loop of 4 blocks: titanium-conveyor at 4 / 0 -> titanium-conveyor at 5 / 0 -> titanium-conveyor at 5 / 1 -> titanium-conveyor at 4 / 1",
+		]);
+	}
+}
@@ -0,0 +1,391 @@
+use std::error::Error;
+use std::fmt;
+
+use flate2::{Decompress, DecompressError, FlushDecompress, Status};
+
+// larger images are refused, nothing built from them would fit a schematic anyway
+pub const MAX_SIZE: u32 = 8192;
+
+// RGBA pixels, top row first
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Image
+{
+	width: u32,
+	height: u32,
+	pixels: Vec<u8>,
+}
+
+impl Image
+{
+	pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self
+	{
+		assert_eq!(pixels.len(), width as usize * height as usize * 4);
+		Self{width, height, pixels}
+	}
+	
+	pub fn get_width(&self) -> u32
+	{
+		self.width
+	}
+	
+	pub fn get_height(&self) -> u32
+	{
+		self.height
+	}
+	
+	pub fn get_pixels(&self) -> &[u8]
+	{
+		&self.pixels
+	}
+	
+	pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4]
+	{
+		let i = (x as usize + y as usize * self.width as usize) * 4;
+		[self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+	}
+	
+	// scales to the given size, averaging the pixels covered when shrinking
+	pub fn resize(&self, width: u32, height: u32) -> Image
+	{
+		let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+		for y in 0..height
+		{
+			let y0 = (y as u64 * self.height as u64 / height as u64) as u32;
+			let y1 = (((y + 1) as u64 * self.height as u64 / height as u64) as u32).max(y0 + 1);
+			for x in 0..width
+			{
+				let x0 = (x as u64 * self.width as u64 / width as u64) as u32;
+				let x1 = (((x + 1) as u64 * self.width as u64 / width as u64) as u32).max(x0 + 1);
+				// weigh colors by alpha so transparent pixels don't darken the edges
+				let mut sum = [0u64; 4];
+				for sy in y0..y1
+				{
+					for sx in x0..x1
+					{
+						let p = self.get_pixel(sx, sy);
+						for c in 0..3
+						{
+							sum[c] += p[c] as u64 * p[3] as u64;
+						}
+						sum[3] += p[3] as u64;
+					}
+				}
+				let count = ((x1 - x0) * (y1 - y0)) as u64;
+				for c in 0..3
+				{
+					pixels.push(sum[c].checked_div(sum[3]).unwrap_or(0) as u8);
+				}
+				pixels.push((sum[3] / count) as u8);
+			}
+		}
+		Image{width, height, pixels}
+	}
+}
+
+// reads a PNG or PPM image, depending on its signature
+pub fn decode(data: &[u8]) -> Result<Image, ImageError>
+{
+	if data.starts_with(b"\x89PNG\r\n\x1A\n") {decode_png(data)}
+	else if data.starts_with(b"P3") || data.starts_with(b"P6") {decode_ppm(data)}
+	else {Err(ImageError::Format)}
+}
+
+fn check_size(width: u32, height: u32) -> Result<(), ImageError>
+{
+	if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {Err(ImageError::Size{width, height})}
+	else {Ok(())}
+}
+
+// the next whitespace separated number of a PPM header, skipping comments
+fn ppm_number(data: &[u8], pos: &mut usize) -> Result<u32, ImageError>
+{
+	loop
+	{
+		match data.get(*pos)
+		{
+			None => return Err(ImageError::Truncated),
+			Some(b'#') => while data.get(*pos).is_some_and(|&b| b != b'\n') {*pos += 1;},
+			Some(b) if b.is_ascii_whitespace() => *pos += 1,
+			Some(..) => break,
+		}
+	}
+	let start = *pos;
+	while data.get(*pos).is_some_and(u8::is_ascii_digit) {*pos += 1;}
+	std::str::from_utf8(&data[start..*pos]).ok().and_then(|s| s.parse::<u32>().ok()).ok_or(ImageError::Invalid("bad number in header"))
+}
+
+pub fn decode_ppm(data: &[u8]) -> Result<Image, ImageError>
+{
+	let binary = match data.get(..2)
+	{
+		Some(b"P6") => true,
+		Some(b"P3") => false,
+		_ => return Err(ImageError::Format),
+	};
+	let mut pos = 2;
+	let width = ppm_number(data, &mut pos)?;
+	let height = ppm_number(data, &mut pos)?;
+	let max = ppm_number(data, &mut pos)?;
+	check_size(width, height)?;
+	if max == 0 || max > 65535 {return Err(ImageError::Invalid("bad maximum value"));}
+	// exactly one whitespace character before binary data
+	pos += 1;
+	let count = width as usize * height as usize * 3;
+	let mut pixels = Vec::with_capacity(count / 3 * 4);
+	for i in 0..count
+	{
+		let value = if !binary {ppm_number(data, &mut pos)?}
+		else if max < 256
+		{
+			let v = *data.get(pos + i).ok_or(ImageError::Truncated)?;
+			v as u32
+		}
+		else
+		{
+			let v = data.get(pos + i * 2..pos + i * 2 + 2).ok_or(ImageError::Truncated)?;
+			u16::from_be_bytes([v[0], v[1]]) as u32
+		};
+		pixels.push((value.min(max) * 255 / max) as u8);
+		if i % 3 == 2 {pixels.push(255);}
+	}
+	Ok(Image{width, height, pixels})
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8
+{
+	let p = a as i16 + b as i16 - c as i16;
+	let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+	if pa <= pb && pa <= pc {a} else if pb <= pc {b} else {c}
+}
+
+pub fn decode_png(data: &[u8]) -> Result<Image, ImageError>
+{
+	if !data.starts_with(b"\x89PNG\r\n\x1A\n") {return Err(ImageError::Format);}
+	let mut pos = 8;
+	let mut header = None;
+	let mut palette: &[u8] = &[];
+	let mut transparency: &[u8] = &[];
+	let mut compressed = Vec::new();
+	loop
+	{
+		let len = data.get(pos..pos + 4).ok_or(ImageError::Truncated)?;
+		let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+		let kind = data.get(pos + 4..pos + 8).ok_or(ImageError::Truncated)?;
+		let body = data.get(pos + 8..pos + 8 + len).ok_or(ImageError::Truncated)?;
+		// skip the checksum, a broken image will fail to decompress anyway
+		pos += 12 + len;
+		match kind
+		{
+			b"IHDR" =>
+			{
+				if body.len() != 13 {return Err(ImageError::Invalid("bad header"));}
+				let width = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+				let height = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+				check_size(width, height)?;
+				header = Some((width, height, body[8], body[9], body[12]));
+			},
+			b"PLTE" => palette = body,
+			b"tRNS" => transparency = body,
+			b"IDAT" => compressed.extend_from_slice(body),
+			b"IEND" => break,
+			// other chunks only hold metadata
+			_ => (),
+		}
+	}
+	let Some((width, height, depth, color, interlace)) = header
+	else {return Err(ImageError::Invalid("missing header"));};
+	if interlace != 0 {return Err(ImageError::Unsupported("interlaced images"));}
+	let channels = match (color, depth)
+	{
+		(0, 1 | 2 | 4 | 8 | 16) => 1,
+		(3, 1 | 2 | 4 | 8) => 1,
+		(2, 8 | 16) => 3,
+		(4, 8 | 16) => 2,
+		(6, 8 | 16) => 4,
+		_ => return Err(ImageError::Unsupported("color type or bit depth")),
+	};
+	let bits = channels * depth as usize;
+	// bytes per complete pixel (at least one) for filtering, and per row
+	let step = bits.div_ceil(8);
+	let stride = (width as usize * bits).div_ceil(8);
+	let expected = (stride + 1) * height as usize;
+	
+	let mut raw = Vec::with_capacity(expected);
+	let mut dec = Decompress::new(true);
+	loop
+	{
+		let t_in = dec.total_in() as usize;
+		let t_out = dec.total_out();
+		let status = dec.decompress_vec(&compressed[t_in..], &mut raw, FlushDecompress::Finish)?;
+		if status == Status::StreamEnd || raw.len() >= expected {break;}
+		if dec.total_in() as usize == t_in && dec.total_out() == t_out
+		{
+			if raw.len() == raw.capacity() {raw.reserve(1024);}
+			else {return Err(ImageError::Truncated);}
+		}
+	}
+	if raw.len() < expected {return Err(ImageError::Truncated);}
+	
+	// undo the filters row by row
+	let mut rows = vec![0u8; stride * height as usize];
+	for y in 0..height as usize
+	{
+		let filter = raw[y * (stride + 1)];
+		let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+		let (done, rest) = rows.split_at_mut(y * stride);
+		let prev = if y > 0 {&done[(y - 1) * stride..]} else {&[][..]};
+		let curr = &mut rest[..stride];
+		for i in 0..stride
+		{
+			let a = if i >= step {curr[i - step]} else {0};
+			let b = prev.get(i).copied().unwrap_or(0);
+			let c = if i >= step {prev.get(i - step).copied().unwrap_or(0)} else {0};
+			curr[i] = line[i].wrapping_add(match filter
+			{
+				0 => 0,
+				1 => a,
+				2 => b,
+				3 => ((a as u16 + b as u16) / 2) as u8,
+				4 => paeth(a, b, c),
+				_ => return Err(ImageError::Invalid("bad filter")),
+			});
+		}
+	}
+	
+	// convert everything to 8-bit RGBA
+	let sample = |row: &[u8], idx: usize| -> u16
+	{
+		match depth
+		{
+			16 => u16::from_be_bytes([row[idx * 2], row[idx * 2 + 1]]),
+			8 => row[idx] as u16,
+			_ =>
+			{
+				let per_byte = 8 / depth as usize;
+				let shift = 8 - depth as usize * (idx % per_byte + 1);
+				((row[idx / per_byte] >> shift) & ((1 << depth) - 1)) as u16
+			},
+		}
+	};
+	let scale = |v: u16| -> u8
+	{
+		match depth
+		{
+			16 => (v >> 8) as u8,
+			8 => v as u8,
+			_ => (v as u32 * 255 / ((1 << depth) - 1)) as u8,
+		}
+	};
+	let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+	for row in rows.chunks_exact(stride)
+	{
+		for x in 0..width as usize
+		{
+			match color
+			{
+				0 =>
+				{
+					let v = sample(row, x);
+					let key = (transparency.len() >= 2).then(|| u16::from_be_bytes([transparency[0], transparency[1]]));
+					let g = scale(v);
+					pixels.extend_from_slice(&[g, g, g, if key == Some(v) {0} else {255}]);
+				},
+				3 =>
+				{
+					let idx = sample(row, x) as usize;
+					let rgb = palette.get(idx * 3..idx * 3 + 3).ok_or(ImageError::Invalid("color not in palette"))?;
+					pixels.extend_from_slice(rgb);
+					pixels.push(transparency.get(idx).copied().unwrap_or(255));
+				},
+				2 =>
+				{
+					let v = [sample(row, x * 3), sample(row, x * 3 + 1), sample(row, x * 3 + 2)];
+					let opaque = transparency.len() < 6 || (0..3).any(|c| u16::from_be_bytes([transparency[c * 2], transparency[c * 2 + 1]]) != v[c]);
+					pixels.extend_from_slice(&[scale(v[0]), scale(v[1]), scale(v[2]), if opaque {255} else {0}]);
+				},
+				4 =>
+				{
+					let g = scale(sample(row, x * 2));
+					pixels.extend_from_slice(&[g, g, g, scale(sample(row, x * 2 + 1))]);
+				},
+				_ =>
+				{
+					for c in 0..4
+					{
+						pixels.push(scale(sample(row, x * 4 + c)));
+					}
+				},
+			}
+		}
+	}
+	Ok(Image{width, height, pixels})
+}
+
+#[derive(Debug)]
+pub enum ImageError
+{
+	Format,
+	Truncated,
+	Size{width: u32, height: u32},
+	Invalid(&'static str),
+	Unsupported(&'static str),
+	Decompress(DecompressError),
+}
+
+impl From<DecompressError> for ImageError
+{
+	fn from(value: DecompressError) -> Self
+	{
+		Self::Decompress(value)
+	}
+}
+
+impl fmt::Display for ImageError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Format => f.write_str("not a PNG or PPM image"),
+			Self::Truncated => f.write_str("image data ends early"),
+			Self::Size{width, height} => write!(f, "unsupported image size {width} x {height} (at most {MAX_SIZE} x {MAX_SIZE})"),
+			Self::Invalid(msg) => write!(f, "invalid image ({msg})"),
+			Self::Unsupported(what) => write!(f, "unsupported image ({what})"),
+			Self::Decompress(..) => f.write_str("image data could not be decompressed"),
+		}
+	}
+}
+
+impl Error for ImageError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Decompress(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::data::render::encode_png;
+	
+	#[test]
+	fn decode_formats()
+	{
+		let rgb = [255, 0, 0, 0, 255, 0, 0, 0, 255, 10, 20, 30];
+		let rgba = Vec::from_iter(rgb.chunks(3).flat_map(|c| [c[0], c[1], c[2], 255]));
+		let png = decode(&encode_png(2, 2, &rgb)).unwrap();
+		assert_eq!(png, Image::new(2, 2, rgba.clone()));
+		let mut ppm = b"P6\n# comment\n2 2\n255\n".to_vec();
+		ppm.extend_from_slice(&rgb);
+		assert_eq!(decode(&ppm).unwrap(), png);
+		assert_eq!(decode(b"P3 2 2 15 15 0 0 0 15 0 0 0 15 0 0 0").unwrap().get_pixel(1, 0), [0, 255, 0, 255]);
+		assert!(matches!(decode(b"GIF89a"), Err(ImageError::Format)));
+		assert_eq!(png.resize(1, 1).get_pixel(0, 0), [66, 68, 71, 255]);
+	}
+}
@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::content::Content;
+use crate::data::json::{self, Value};
+use crate::data::schematic::Schematic;
+use crate::item;
+use crate::item::storage::Storage as ItemStorage;
+use crate::registry::RegistryEntry;
+
+// the searchable summary of a schematic file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry
+{
+	pub path: String,
+	pub name: String,
+	pub description: String,
+	pub labels: Vec<String>,
+	pub width: u16,
+	pub height: u16,
+	// number of blocks of each type, sorted by name
+	pub blocks: Vec<(String, u32)>,
+	pub cost: ItemStorage,
+}
+
+impl Entry
+{
+	pub fn new(path: String, schematic: &Schematic) -> Self
+	{
+		let tags = schematic.get_tags();
+		let mut blocks = Vec::<(String, u32)>::new();
+		for p in schematic.block_iter()
+		{
+			let name = p.get_block().get_name();
+			match blocks.iter_mut().find(|(n, _)| n == name)
+			{
+				Some((_, cnt)) => *cnt += 1,
+				None => blocks.push((name.to_owned(), 1)),
+			}
+		}
+		blocks.sort();
+		Self
+		{
+			path,
+			name: tags.get("name").cloned().unwrap_or_default(),
+			description: tags.get("description").cloned().unwrap_or_default(),
+			labels: schematic.get_labels().unwrap_or_default(),
+			width: schematic.get_width(),
+			height: schematic.get_height(),
+			blocks,
+			cost: schematic.compute_total_cost().0,
+		}
+	}
+	
+	pub fn get_block_count(&self, name: &str) -> u32
+	{
+		self.blocks.iter().find(|(n, _)| n == name).map_or(0, |&(_, cnt)| cnt)
+	}
+	
+	pub fn to_json(&self) -> Value
+	{
+		Value::Object(vec![
+			("path".to_owned(), Value::from(self.path.as_str())),
+			("name".to_owned(), Value::from(self.name.as_str())),
+			("description".to_owned(), Value::from(self.description.as_str())),
+			("labels".to_owned(), Value::Array(self.labels.iter().map(|l| Value::from(l.as_str())).collect())),
+			("width".to_owned(), Value::from(self.width)),
+			("height".to_owned(), Value::from(self.height)),
+			("blocks".to_owned(), Value::Object(self.blocks.iter().map(|(n, cnt)| (n.clone(), Value::from(*cnt))).collect())),
+			("cost".to_owned(), Value::Object(self.cost.iter_nonzero().map(|(ty, cnt)| (ty.get_name().to_owned(), Value::from(cnt))).collect())),
+		])
+	}
+	
+	pub fn from_json(value: &Value) -> Result<Self, IndexError>
+	{
+		fn field<'v>(value: &'v Value, key: &'static str) -> Result<&'v Value, IndexError>
+		{
+			value.get(key).ok_or(IndexError::Field(key))
+		}
+		
+		fn string(value: &Value, key: &'static str) -> Result<String, IndexError>
+		{
+			field(value, key)?.as_str().map(str::to_owned).ok_or(IndexError::Field(key))
+		}
+		
+		fn count(value: &Value, key: &'static str) -> Result<u32, IndexError>
+		{
+			value.as_i64().and_then(|v| u32::try_from(v).ok()).ok_or(IndexError::Field(key))
+		}
+		
+		let labels = field(value, "labels")?.as_array().ok_or(IndexError::Field("labels"))?.iter()
+			.map(|v| v.as_str().map(str::to_owned).ok_or(IndexError::Field("labels"))).collect::<Result<_, _>>()?;
+		let width = u16::try_from(count(field(value, "width")?, "width")?).map_err(|_| IndexError::Field("width"))?;
+		let height = u16::try_from(count(field(value, "height")?, "height")?).map_err(|_| IndexError::Field("height"))?;
+		let blocks = field(value, "blocks")?.as_object().ok_or(IndexError::Field("blocks"))?.iter()
+			.map(|(n, v)| Ok((n.clone(), count(v, "blocks")?))).collect::<Result<_, IndexError>>()?;
+		let mut cost = ItemStorage::new();
+		for (name, v) in field(value, "cost")?.as_object().ok_or(IndexError::Field("cost"))?
+		{
+			let ty = item::Type::by_name(name).ok_or_else(|| IndexError::Item(name.clone()))?;
+			cost.set(ty, count(v, "cost")?);
+		}
+		Ok(Self
+		{
+			path: string(value, "path")?,
+			name: string(value, "name")?,
+			description: string(value, "description")?,
+			labels,
+			width,
+			height,
+			blocks,
+			cost,
+		})
+	}
+}
+
+pub fn write_index(entries: &[Entry]) -> String
+{
+	let mut result = String::new();
+	// one entry per line keeps large indices readable and diffable
+	result.push_str("[\n");
+	for (i, entry) in entries.iter().enumerate()
+	{
+		result.push_str(&entry.to_json().to_string());
+		result.push_str(if i + 1 < entries.len() {",\n"} else {"\n"});
+	}
+	result.push_str("]\n");
+	result
+}
+
+pub fn read_index(text: &str) -> Result<Vec<Entry>, IndexError>
+{
+	let value = json::parse(text)?;
+	value.as_array().ok_or(IndexError::NotArray)?.iter().map(Entry::from_json).collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Query
+{
+	// case-insensitive text to find in the name or description
+	pub text: Option<String>,
+	pub labels: Vec<String>,
+	// block names with the minimum number of them
+	pub blocks: Vec<(String, u32)>,
+	pub max_width: Option<u16>,
+	pub max_height: Option<u16>,
+	// upper limit for the items in it, others are unlimited
+	pub max_cost: Vec<(item::Type, u32)>,
+}
+
+impl Query
+{
+	pub fn matches(&self, entry: &Entry) -> bool
+	{
+		if let Some(ref text) = self.text
+		{
+			let text = text.to_lowercase();
+			if !entry.name.to_lowercase().contains(&text) && !entry.description.to_lowercase().contains(&text) {return false;}
+		}
+		self.labels.iter().all(|l| entry.labels.iter().any(|e| e.eq_ignore_ascii_case(l)))
+			&& self.blocks.iter().all(|(name, min)| entry.get_block_count(name) >= (*min).max(1))
+			&& self.max_width.is_none_or(|w| entry.width <= w)
+			&& self.max_height.is_none_or(|h| entry.height <= h)
+			&& self.max_cost.iter().all(|&(ty, max)| entry.cost.get(ty) <= max)
+	}
+}
+
+#[derive(Debug)]
+pub enum IndexError
+{
+	Json(json::ParseError),
+	NotArray,
+	Field(&'static str),
+	Item(String),
+}
+
+impl From<json::ParseError> for IndexError
+{
+	fn from(value: json::ParseError) -> Self
+	{
+		Self::Json(value)
+	}
+}
+
+impl fmt::Display for IndexError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Json(..) => f.write_str("invalid json"),
+			Self::NotArray => f.write_str("index is not an array"),
+			Self::Field(key) => write!(f, "missing or invalid field {key:?}"),
+			Self::Item(name) => write!(f, "no such item {name:?}"),
+		}
+	}
+}
+
+impl Error for IndexError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Json(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, Rotation};
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn search()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(6, 3);
+		for x in [1, 4]
+		{
+			schematic.set(x, 1, reg.get("silicon-smelter").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		}
+		schematic.get_tags_mut().insert("name".to_string(), "Silicon Array".to_string());
+		schematic.set_labels(&["silicon"]);
+		let entry = Entry::new("smelters.msch".to_string(), &schematic);
+		let entries = read_index(&write_index(std::slice::from_ref(&entry))).unwrap();
+		assert_eq!(entries, vec![entry.clone()]);
+		let mut query = Query{text: Some("silicon".to_string()), labels: vec!["Silicon".to_string()], ..Query::default()};
+		query.blocks.push(("silicon-smelter".to_string(), 2));
+		query.max_width = Some(6);
+		assert!(query.matches(&entry));
+		query.max_cost.push((item::Type::Copper, entry.cost.get(item::Type::Copper) - 1));
+		assert!(!query.matches(&entry));
+		query.max_cost.clear();
+		query.blocks.push(("kiln".to_string(), 0));
+		assert!(!query.matches(&entry));
+	}
+}
@@ -0,0 +1,532 @@
+use std::error::Error;
+use std::fmt::{self, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value
+{
+	Null,
+	Boolean(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Value>),
+	// keeps insertion order so output is deterministic
+	Object(Vec<(String, Value)>),
+}
+
+impl Value
+{
+	pub fn is_null(&self) -> bool
+	{
+		matches!(self, Self::Null)
+	}
+	
+	pub fn as_bool(&self) -> Option<bool>
+	{
+		match self
+		{
+			Self::Boolean(v) => Some(*v),
+			_ => None,
+		}
+	}
+	
+	pub fn as_f64(&self) -> Option<f64>
+	{
+		match self
+		{
+			Self::Number(v) => Some(*v),
+			_ => None,
+		}
+	}
+	
+	pub fn as_i64(&self) -> Option<i64>
+	{
+		match self
+		{
+			Self::Number(v) if v.fract() == 0.0 && *v >= i64::MIN as f64 && *v <= i64::MAX as f64 => Some(*v as i64),
+			_ => None,
+		}
+	}
+	
+	pub fn as_str(&self) -> Option<&str>
+	{
+		match self
+		{
+			Self::String(v) => Some(v),
+			_ => None,
+		}
+	}
+	
+	pub fn as_array(&self) -> Option<&[Value]>
+	{
+		match self
+		{
+			Self::Array(v) => Some(v),
+			_ => None,
+		}
+	}
+	
+	pub fn as_object(&self) -> Option<&[(String, Value)]>
+	{
+		match self
+		{
+			Self::Object(v) => Some(v),
+			_ => None,
+		}
+	}
+	
+	pub fn get(&self, key: &str) -> Option<&Value>
+	{
+		match self
+		{
+			Self::Object(v) => v.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+			_ => None,
+		}
+	}
+	
+	pub fn insert(&mut self, key: &str, value: Value) -> Option<Value>
+	{
+		match self
+		{
+			Self::Object(v) =>
+			{
+				match v.iter_mut().find(|(k, _)| k == key)
+				{
+					Some((_, prev)) => Some(std::mem::replace(prev, value)),
+					None =>
+					{
+						v.push((key.to_owned(), value));
+						None
+					},
+				}
+			},
+			_ => panic!("cannot insert {key:?} into non-object"),
+		}
+	}
+}
+
+impl From<bool> for Value
+{
+	fn from(value: bool) -> Self
+	{
+		Self::Boolean(value)
+	}
+}
+
+macro_rules!from_num
+{
+	($($type:ty),+) =>
+	{
+		$(
+			impl From<$type> for Value
+			{
+				fn from(value: $type) -> Self
+				{
+					Self::Number(value as f64)
+				}
+			}
+		)+
+	};
+}
+
+from_num!(u8, i8, u16, i16, u32, i32, u64, i64, usize, f32, f64);
+
+impl From<&str> for Value
+{
+	fn from(value: &str) -> Self
+	{
+		Self::String(value.to_owned())
+	}
+}
+
+impl From<String> for Value
+{
+	fn from(value: String) -> Self
+	{
+		Self::String(value)
+	}
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value
+{
+	fn from(value: Option<T>) -> Self
+	{
+		match value
+		{
+			None => Self::Null,
+			Some(v) => v.into(),
+		}
+	}
+}
+
+pub fn write_string<W: Write>(out: &mut W, value: &str) -> fmt::Result
+{
+	out.write_char('"')?;
+	for c in value.chars()
+	{
+		match c
+		{
+			'"' => out.write_str("\\\"")?,
+			'\\' => out.write_str("\\\\")?,
+			'\n' => out.write_str("\\n")?,
+			'\r' => out.write_str("\\r")?,
+			'\t' => out.write_str("\\t")?,
+			c if c < ' ' => write!(out, "\\u{:04x}", c as u32)?,
+			c => out.write_char(c)?,
+		}
+	}
+	out.write_char('"')
+}
+
+impl fmt::Display for Value
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Null => f.write_str("null"),
+			Self::Boolean(v) => write!(f, "{v}"),
+			// json has no representation for these
+			Self::Number(v) if !v.is_finite() => f.write_str("null"),
+			Self::Number(v) if v.fract() == 0.0 && v.abs() < 1e15 => write!(f, "{}", *v as i64),
+			Self::Number(v) => write!(f, "{v}"),
+			Self::String(v) => write_string(f, v),
+			Self::Array(v) =>
+			{
+				f.write_char('[')?;
+				for (i, curr) in v.iter().enumerate()
+				{
+					if i > 0 {f.write_char(',')?;}
+					curr.fmt(f)?;
+				}
+				f.write_char(']')
+			},
+			Self::Object(v) =>
+			{
+				f.write_char('{')?;
+				for (i, (k, curr)) in v.iter().enumerate()
+				{
+					if i > 0 {f.write_char(',')?;}
+					write_string(f, k)?;
+					f.write_char(':')?;
+					curr.fmt(f)?;
+				}
+				f.write_char('}')
+			},
+		}
+	}
+}
+
+pub fn parse(text: &str) -> Result<Value, ParseError>
+{
+	Parser{text, pos: 0, minimal: false}.parse_all()
+}
+
+// the game writes some json (like schematic labels) in a "minimal" format which doesn't quote simple strings
+pub fn parse_minimal(text: &str) -> Result<Value, ParseError>
+{
+	Parser{text, pos: 0, minimal: true}.parse_all()
+}
+
+struct Parser<'l>
+{
+	text: &'l str,
+	pos: usize,
+	minimal: bool,
+}
+
+impl<'l> Parser<'l>
+{
+	fn peek(&self) -> Option<char>
+	{
+		self.text[self.pos..].chars().next()
+	}
+	
+	fn skip_ws(&mut self)
+	{
+		let rest = &self.text[self.pos..];
+		self.pos += rest.len() - rest.trim_start().len();
+	}
+	
+	fn expect(&mut self, want: char) -> Result<(), ParseError>
+	{
+		self.skip_ws();
+		match self.peek()
+		{
+			None => Err(ParseError::Eof),
+			Some(c) if c == want =>
+			{
+				self.pos += c.len_utf8();
+				Ok(())
+			},
+			Some(c) => Err(ParseError::Unexpected{at: self.pos, value: c}),
+		}
+	}
+	
+	fn parse_all(mut self) -> Result<Value, ParseError>
+	{
+		let value = self.parse_value()?;
+		self.skip_ws();
+		if self.pos < self.text.len()
+		{
+			return Err(ParseError::Trailing{at: self.pos});
+		}
+		Ok(value)
+	}
+	
+	fn parse_value(&mut self) -> Result<Value, ParseError>
+	{
+		self.skip_ws();
+		match self.peek()
+		{
+			None => Err(ParseError::Eof),
+			Some('{') =>
+			{
+				self.pos += 1;
+				let mut fields = Vec::new();
+				self.skip_ws();
+				if self.peek() == Some('}')
+				{
+					self.pos += 1;
+					return Ok(Value::Object(fields));
+				}
+				loop
+				{
+					self.skip_ws();
+					let key = match self.peek()
+					{
+						Some('"') => self.parse_string()?,
+						Some(c) if self.minimal && !Self::is_delimiter(c) => self.parse_unquoted(),
+						Some(c) => return Err(ParseError::Unexpected{at: self.pos, value: c}),
+						None => return Err(ParseError::Eof),
+					};
+					self.expect(':')?;
+					let value = self.parse_value()?;
+					fields.push((key, value));
+					self.skip_ws();
+					match self.peek()
+					{
+						Some(',') => self.pos += 1,
+						Some('}') =>
+						{
+							self.pos += 1;
+							return Ok(Value::Object(fields));
+						},
+						Some(c) => return Err(ParseError::Unexpected{at: self.pos, value: c}),
+						None => return Err(ParseError::Eof),
+					}
+				}
+			},
+			Some('[') =>
+			{
+				self.pos += 1;
+				let mut values = Vec::new();
+				self.skip_ws();
+				if self.peek() == Some(']')
+				{
+					self.pos += 1;
+					return Ok(Value::Array(values));
+				}
+				loop
+				{
+					values.push(self.parse_value()?);
+					self.skip_ws();
+					match self.peek()
+					{
+						Some(',') => self.pos += 1,
+						Some(']') =>
+						{
+							self.pos += 1;
+							return Ok(Value::Array(values));
+						},
+						Some(c) => return Err(ParseError::Unexpected{at: self.pos, value: c}),
+						None => return Err(ParseError::Eof),
+					}
+				}
+			},
+			Some('"') => Ok(Value::String(self.parse_string()?)),
+			Some(c) if c == '-' || c.is_ascii_digit() =>
+			{
+				let start = self.pos;
+				let len = self.text[start..].find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')).unwrap_or(self.text.len() - start);
+				match self.text[start..start + len].parse::<f64>()
+				{
+					Ok(v) if !self.minimal || self.at_delimiter(start + len) =>
+					{
+						self.pos = start + len;
+						Ok(Value::Number(v))
+					},
+					_ if self.minimal => Ok(Value::String(self.parse_unquoted())),
+					_ => Err(ParseError::Number{at: start}),
+				}
+			},
+			Some(c) =>
+			{
+				for (name, value) in [("true", Value::Boolean(true)), ("false", Value::Boolean(false)), ("null", Value::Null)]
+				{
+					if self.text[self.pos..].starts_with(name) && (!self.minimal || self.at_delimiter(self.pos + name.len()))
+					{
+						self.pos += name.len();
+						return Ok(value);
+					}
+				}
+				if self.minimal && !Self::is_delimiter(c) {Ok(Value::String(self.parse_unquoted()))}
+				else {Err(ParseError::Unexpected{at: self.pos, value: c})}
+			},
+		}
+	}
+	
+	fn is_delimiter(c: char) -> bool
+	{
+		matches!(c, ',' | ':' | '[' | ']' | '{' | '}' | '"') || c.is_whitespace()
+	}
+	
+	fn at_delimiter(&self, pos: usize) -> bool
+	{
+		match self.text[pos..].chars().next()
+		{
+			None => true,
+			Some(c) => Self::is_delimiter(c),
+		}
+	}
+	
+	fn parse_unquoted(&mut self) -> String
+	{
+		// unquoted strings may contain spaces, but no structural characters
+		let rest = &self.text[self.pos..];
+		let len = rest.find([',', ':', ']', '}', '\n', '\r']).unwrap_or(rest.len());
+		self.pos += len;
+		rest[..len].trim().to_owned()
+	}
+	
+	fn parse_hex(&mut self) -> Result<u32, ParseError>
+	{
+		let start = self.pos;
+		match self.text.get(start..start + 4).and_then(|s| u32::from_str_radix(s, 16).ok())
+		{
+			Some(v) =>
+			{
+				self.pos += 4;
+				Ok(v)
+			},
+			None => Err(ParseError::Escape{at: start}),
+		}
+	}
+	
+	fn parse_string(&mut self) -> Result<String, ParseError>
+	{
+		self.expect('"')?;
+		let mut result = String::new();
+		loop
+		{
+			let rest = &self.text[self.pos..];
+			match rest.find(['"', '\\'])
+			{
+				None => return Err(ParseError::Eof),
+				Some(end) =>
+				{
+					result.push_str(&rest[..end]);
+					self.pos += end + 1;
+					if rest.as_bytes()[end] == b'"' {return Ok(result);}
+					let at = self.pos - 1;
+					match self.peek()
+					{
+						None => return Err(ParseError::Eof),
+						Some(c) =>
+						{
+							self.pos += c.len_utf8();
+							match c
+							{
+								'"' | '\\' | '/' => result.push(c),
+								'b' => result.push('\x08'),
+								'f' => result.push('\x0C'),
+								'n' => result.push('\n'),
+								'r' => result.push('\r'),
+								't' => result.push('\t'),
+								'u' =>
+								{
+									let mut code = self.parse_hex()?;
+									if (0xD800..0xDC00).contains(&code) && self.text[self.pos..].starts_with("\\u")
+									{
+										// surrogate pair
+										self.pos += 2;
+										let low = self.parse_hex()?;
+										if !(0xDC00..0xE000).contains(&low) {return Err(ParseError::Escape{at});}
+										code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+									}
+									match char::from_u32(code)
+									{
+										None => return Err(ParseError::Escape{at}),
+										Some(c) => result.push(c),
+									}
+								},
+								_ => return Err(ParseError::Escape{at}),
+							}
+						},
+					}
+				},
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseError
+{
+	Eof,
+	Unexpected{at: usize, value: char},
+	Number{at: usize},
+	Escape{at: usize},
+	Trailing{at: usize},
+}
+
+impl fmt::Display for ParseError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Eof => f.write_str("unexpected end of input"),
+			Self::Unexpected{at, value} => write!(f, "unexpected character {value:?} (at {at})"),
+			Self::Number{at} => write!(f, "malformed number (at {at})"),
+			Self::Escape{at} => write!(f, "invalid escape sequence (at {at})"),
+			Self::Trailing{at} => write!(f, "trailing data (at {at})"),
+		}
+	}
+}
+
+impl Error for ParseError {}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn reparse()
+	{
+		let value = Value::Object(vec![
+			("name".to_string(), Value::from("qu\"ote\\ \u{1F998}\n")),
+			("size".to_string(), Value::from(12)),
+			("ratio".to_string(), Value::from(-0.25)),
+			("list".to_string(), Value::Array(vec![Value::Null, Value::from(true), Value::Array(Vec::new()), Value::Object(Vec::new())])),
+		]);
+		let text = value.to_string();
+		assert_eq!(text, r#"{"name":"qu\"ote\\ 🦘\n","size":12,"ratio":-0.25,"list":[null,true,[],{}]}"#);
+		assert_eq!(parse(&text), Ok(value));
+	}
+	
+	#[test]
+	fn escapes()
+	{
+		assert_eq!(parse(r#""é🦘\/""#), Ok(Value::from("é🦘/")));
+		assert_eq!(parse(r#""\x""#), Err(ParseError::Escape{at: 1}));
+	}
+	
+	#[test]
+	fn minimal()
+	{
+		let expect = Value::Array(vec![Value::from("power plant"), Value::from("1st"), Value::from(2), Value::from("quoted")]);
+		assert_eq!(parse_minimal(r#"[power plant, 1st, 2, "quoted"]"#), Ok(expect));
+		assert!(parse("[power plant]").is_err());
+	}
+}
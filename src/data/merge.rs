@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+
+use crate::data::GridPos;
+use crate::data::dynamic::DynData;
+use crate::data::schematic::{PlaceError, Placement, Schematic};
+use crate::registry::RegistryEntry;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side
+{
+	Ours,
+	Theirs,
+}
+
+// a set of overlapping blocks both sides changed differently, the merged schematic keeps the base version until resolved
+#[derive(Clone)]
+pub struct Conflict<'l>
+{
+	pub base: Vec<Placement<'l>>,
+	pub ours: Vec<Placement<'l>>,
+	pub theirs: Vec<Placement<'l>>,
+}
+
+impl<'l> Conflict<'l>
+{
+	// the lowest position (bottom to top, then left to right) of any block involved
+	pub fn get_pos(&self) -> GridPos
+	{
+		self.base.iter().chain(self.ours.iter()).chain(self.theirs.iter()).map(|p| p.get_pos())
+			.min_by_key(|p| (p.1, p.0)).unwrap()
+	}
+	
+	pub fn get_side(&self, side: Side) -> &[Placement<'l>]
+	{
+		match side
+		{
+			Side::Ours => &self.ours,
+			Side::Theirs => &self.theirs,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TagConflict
+{
+	pub key: String,
+	pub base: Option<String>,
+	pub ours: Option<String>,
+	pub theirs: Option<String>,
+}
+
+impl TagConflict
+{
+	pub fn get_side(&self, side: Side) -> Option<&str>
+	{
+		match side
+		{
+			Side::Ours => self.ours.as_deref(),
+			Side::Theirs => self.theirs.as_deref(),
+		}
+	}
+}
+
+pub struct Merge<'l>
+{
+	schematic: Schematic<'l>,
+	conflicts: Vec<Conflict<'l>>,
+	tag_conflicts: Vec<TagConflict>,
+}
+
+impl<'l> Merge<'l>
+{
+	pub fn get_schematic(&self) -> &Schematic<'l>
+	{
+		&self.schematic
+	}
+	
+	pub fn into_schematic(self) -> Schematic<'l>
+	{
+		self.schematic
+	}
+	
+	pub fn get_conflicts(&self) -> &[Conflict<'l>]
+	{
+		&self.conflicts
+	}
+	
+	pub fn get_tag_conflicts(&self) -> &[TagConflict]
+	{
+		&self.tag_conflicts
+	}
+	
+	pub fn is_clean(&self) -> bool
+	{
+		self.conflicts.is_empty() && self.tag_conflicts.is_empty()
+	}
+	
+	// replaces the base version of a conflict with one side, removing the conflict (and shifting the later ones)
+	pub fn resolve(&mut self, idx: usize, side: Side) -> Result<(), PlaceError>
+	{
+		let conflict = self.conflicts.remove(idx);
+		for p in conflict.base.iter()
+		{
+			let pos = p.get_pos();
+			self.schematic.take(pos.0, pos.1).unwrap();
+		}
+		for p in conflict.get_side(side)
+		{
+			put(&mut self.schematic, p)?;
+		}
+		Ok(())
+	}
+	
+	pub fn resolve_tag(&mut self, idx: usize, side: Side)
+	{
+		let conflict = self.tag_conflicts.remove(idx);
+		let value = match side
+		{
+			Side::Ours => conflict.ours,
+			Side::Theirs => conflict.theirs,
+		};
+		match value
+		{
+			None => self.schematic.get_tags_mut().remove(&conflict.key),
+			Some(v) => self.schematic.get_tags_mut().insert(conflict.key, v),
+		};
+	}
+	
+	pub fn resolve_all(&mut self, side: Side) -> Result<(), PlaceError>
+	{
+		while !self.conflicts.is_empty()
+		{
+			self.resolve(self.conflicts.len() - 1, side)?;
+		}
+		while !self.tag_conflicts.is_empty()
+		{
+			self.resolve_tag(self.tag_conflicts.len() - 1, side);
+		}
+		Ok(())
+	}
+}
+
+fn get_config(p: &Placement) -> DynData
+{
+	match p.get_state()
+	{
+		None => DynData::Empty,
+		Some(state) => p.get_block().serialize_state(state).unwrap_or(DynData::Empty),
+	}
+}
+
+fn same(a: &Placement, b: &Placement) -> bool
+{
+	(a.get_rotation() == b.get_rotation() || a.get_block().is_symmetric()) && get_config(a) == get_config(b)
+}
+
+fn put<'l>(schematic: &mut Schematic<'l>, p: &Placement<'l>) -> Result<(), PlaceError>
+{
+	let pos = p.get_pos();
+	schematic.set(pos.0, pos.1, p.get_block(), get_config(p), p.get_rotation())?;
+	Ok(())
+}
+
+// the tiles covered by a placement
+fn footprint(p: &Placement) -> impl Iterator<Item = (u16, u16)>
+{
+	let sz = p.get_block().get_size() as u16;
+	let pos = p.get_pos();
+	let (x0, y0) = (pos.0 - (sz - 1) / 2, pos.1 - (sz - 1) / 2);
+	(0..sz * sz).map(move |i| (x0 + i % sz, y0 + i / sz))
+}
+
+// blocks are identified by their position and type, anything else (rotation, configuration) is a modification
+fn index<'s, 'l>(schematic: &'s Schematic<'l>) -> HashMap<(u16, u16, &'s str), &'s Placement<'l>>
+{
+	schematic.block_iter().map(|p| ((p.get_pos().0, p.get_pos().1, p.get_block().get_name()), p)).collect()
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize
+{
+	let mut root = i;
+	while parent[root] != root {root = parent[root];}
+	parent[i] = root;
+	root
+}
+
+fn pick_dimension(base: u16, ours: u16, theirs: u16) -> u16
+{
+	if ours == base {theirs} else if theirs == base {ours} else {ours.max(theirs)}
+}
+
+// combines the changes made in ours and theirs relative to their common base, changes on both sides to the same block
+// or overlapping tiles conflict unless they are identical
+pub fn merge<'l>(base: &Schematic<'l>, ours: &Schematic<'l>, theirs: &Schematic<'l>) -> Result<Merge<'l>, PlaceError>
+{
+	let (base_at, ours_at, theirs_at) = (index(base), index(ours), index(theirs));
+	let mut accepted = Vec::<&Placement<'l>>::new();
+	// candidates that may conflict with the other side, and whether they already do
+	let mut items = Vec::<(Side, &Placement<'l>, bool)>::new();
+	let mut links = Vec::<(usize, usize)>::new();
+	// base versions of conflicting blocks, tied to one of the items they conflict with
+	let mut originals = Vec::<(usize, &Placement<'l>)>::new();
+	for (key, &b) in base_at.iter()
+	{
+		let (o, t) = (ours_at.get(key).copied(), theirs_at.get(key).copied());
+		let o_same = o.is_some_and(|o| same(o, b));
+		let t_same = t.is_some_and(|t| same(t, b));
+		let agree = match (o, t)
+		{
+			(None, None) => true,
+			(Some(o), Some(t)) => same(o, t),
+			_ => false,
+		};
+		if t_same || agree
+		{
+			if let Some(o) = o {accepted.push(o);}
+		}
+		else if o_same
+		{
+			if let Some(t) = t {accepted.push(t);}
+		}
+		else
+		{
+			let first = o.map(|o|
+			{
+				items.push((Side::Ours, o, true));
+				items.len() - 1
+			});
+			let second = t.map(|t|
+			{
+				items.push((Side::Theirs, t, true));
+				items.len() - 1
+			});
+			if let (Some(i), Some(j)) = (first, second) {links.push((i, j));}
+			originals.push((first.or(second).unwrap(), b));
+		}
+	}
+	for (key, &o) in ours_at.iter()
+	{
+		if base_at.contains_key(key) {continue;}
+		match theirs_at.get(key)
+		{
+			// added identically on both sides
+			Some(&t) if same(o, t) => accepted.push(o),
+			_ => items.push((Side::Ours, o, false)),
+		}
+	}
+	for (key, &t) in theirs_at.iter()
+	{
+		if base_at.contains_key(key) || ours_at.get(key).is_some_and(|&o| same(o, t)) {continue;}
+		items.push((Side::Theirs, t, false));
+	}
+	let mut parent = Vec::from_iter(0..items.len());
+	let mut flagged = Vec::from_iter(items.iter().map(|&(.., conflict)| conflict));
+	for (i, j) in links {parent[j] = i;}
+	// anything overlapping a change from the other side conflicts with it
+	let mut occupied = HashMap::<(u16, u16), usize>::new();
+	for (i, &(_, p, _)) in items.iter().enumerate()
+	{
+		for tile in footprint(p)
+		{
+			match occupied.get(&tile)
+			{
+				None => {occupied.insert(tile, i);},
+				Some(&j) =>
+				{
+					let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+					parent[ri] = rj;
+					flagged[i] = true;
+					flagged[j] = true;
+				},
+			}
+		}
+	}
+	let mut groups = HashMap::<usize, usize>::new();
+	let mut conflicts = Vec::<Conflict<'l>>::new();
+	for i in 0..items.len()
+	{
+		let root = find(&mut parent, i);
+		if flagged[i] {flagged[root] = true;}
+	}
+	for (i, &(side, p, _)) in items.iter().enumerate()
+	{
+		let root = find(&mut parent, i);
+		if !flagged[root]
+		{
+			accepted.push(p);
+			continue;
+		}
+		let idx = *groups.entry(root).or_insert_with(||
+		{
+			conflicts.push(Conflict{base: Vec::new(), ours: Vec::new(), theirs: Vec::new()});
+			conflicts.len() - 1
+		});
+		match side
+		{
+			Side::Ours => conflicts[idx].ours.push(p.clone()),
+			Side::Theirs => conflicts[idx].theirs.push(p.clone()),
+		}
+	}
+	for &(i, b) in originals.iter()
+	{
+		let root = find(&mut parent, i);
+		conflicts[groups[&root]].base.push(b.clone());
+	}
+	// grow the result if needed so that either side of every conflict can be placed
+	let mut width = pick_dimension(base.get_width(), ours.get_width(), theirs.get_width());
+	let mut height = pick_dimension(base.get_height(), ours.get_height(), theirs.get_height());
+	for p in accepted.iter().copied().chain(items.iter().map(|&(_, p, _)| p)).chain(originals.iter().map(|&(_, p)| p))
+	{
+		for (x, y) in footprint(p)
+		{
+			width = width.max(x + 1);
+			height = height.max(y + 1);
+		}
+	}
+	let mut schematic = Schematic::new(width, height);
+	for p in accepted.into_iter().chain(originals.into_iter().map(|(_, p)| p))
+	{
+		put(&mut schematic, p)?;
+	}
+	let mut tag_conflicts = Vec::new();
+	let tags = schematic.get_tags_mut();
+	tags.clear();
+	let mut keys = Vec::from_iter(base.get_tags().keys().chain(ours.get_tags().keys()).chain(theirs.get_tags().keys()));
+	keys.sort();
+	keys.dedup();
+	for key in keys
+	{
+		let (b, o, t) = (base.get_tags().get(key), ours.get_tags().get(key), theirs.get_tags().get(key));
+		let value = if o == t || t == b {o} else if o == b {t}
+		else
+		{
+			tag_conflicts.push(TagConflict{key: key.clone(), base: b.cloned(), ours: o.cloned(), theirs: t.cloned()});
+			b
+		};
+		if let Some(v) = value {tags.insert(key.clone(), v.clone());}
+	}
+	for c in conflicts.iter_mut()
+	{
+		for list in [&mut c.base, &mut c.ours, &mut c.theirs]
+		{
+			list.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+		}
+	}
+	conflicts.sort_by_key(|c| (c.get_pos().1, c.get_pos().0));
+	Ok(Merge{schematic, conflicts, tag_conflicts})
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, BlockRegistry, Rotation};
+	
+	fn sample<'l>(reg: &'l BlockRegistry<'l>) -> Schematic<'l>
+	{
+		let mut schematic = Schematic::new(8, 8);
+		schematic.set(0, 0, reg.get("conveyor").unwrap(), DynData::Empty, Rotation::Up).unwrap();
+		schematic.set(4, 4, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		schematic
+	}
+	
+	#[test]
+	fn combine()
+	{
+		let reg = build_registry();
+		let base = sample(&reg);
+		let mut ours = sample(&reg);
+		ours.get_mut(0, 0).unwrap().unwrap().set_rotation(Rotation::Left);
+		ours.set(2, 2, reg.get("router").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let mut theirs = sample(&reg);
+		theirs.take(4, 4).unwrap();
+		theirs.set(6, 6, reg.get("router").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		theirs.get_tags_mut().insert("name".to_string(), "test".to_string());
+		let result = merge(&base, &ours, &theirs).unwrap();
+		assert!(result.is_clean());
+		let schematic = result.get_schematic();
+		assert_eq!(schematic.get_block_count(), 3);
+		assert_eq!(schematic.get(0, 0).unwrap().unwrap().get_rotation(), Rotation::Left);
+		assert!(schematic.get(4, 4).unwrap().is_none());
+		assert!(schematic.get(2, 2).unwrap().is_some() && schematic.get(6, 6).unwrap().is_some());
+		assert_eq!(schematic.get_tags()["name"], "test");
+	}
+	
+	#[test]
+	fn conflicts()
+	{
+		let reg = build_registry();
+		let base = sample(&reg);
+		let mut ours = sample(&reg);
+		ours.get_mut(0, 0).unwrap().unwrap().set_rotation(Rotation::Left);
+		ours.set(3, 3, reg.get("router").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let mut theirs = sample(&reg);
+		theirs.get_mut(0, 0).unwrap().unwrap().set_rotation(Rotation::Down);
+		theirs.set(3, 3, reg.get("mender").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let mut result = merge(&base, &ours, &theirs).unwrap();
+		assert_eq!(result.get_conflicts().len(), 2);
+		assert_eq!(result.get_conflicts()[1].get_pos(), GridPos(3, 3));
+		// unresolved conflicts keep the base version
+		assert_eq!(result.get_schematic().get(0, 0).unwrap().unwrap().get_rotation(), Rotation::Up);
+		assert!(result.get_schematic().get(3, 3).unwrap().is_none());
+		result.resolve(1, Side::Theirs).unwrap();
+		result.resolve(0, Side::Ours).unwrap();
+		assert!(result.is_clean());
+		assert_eq!(result.get_schematic().get(0, 0).unwrap().unwrap().get_rotation(), Rotation::Left);
+		assert_eq!(result.get_schematic().get(3, 3).unwrap().unwrap().get_block().get_name(), "mender");
+	}
+}
@@ -2,10 +2,24 @@ use std::error::Error;
 use std::fmt;
 use std::str::Utf8Error;
 
+pub mod autolink;
 pub mod base64;
 pub mod command;
+pub mod diff;
 pub mod dynamic;
+pub mod flow;
+pub mod image;
+pub mod index;
+pub mod json;
+pub mod merge;
+pub mod pixelart;
+pub mod processors;
+pub mod render;
+pub mod route;
 pub mod schematic;
+pub mod simulate;
+pub mod substitute;
+pub mod text;
 
 pub struct DataRead<'d>
 {
@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::block::{BlockRegistry, Rotation};
+use crate::content::{self, Content};
+use crate::data::dynamic::DynData;
+use crate::data::image::Image;
+use crate::data::schematic::{PlaceError, Schematic, MAX_DIMENSION};
+use crate::item;
+
+// pixels less opaque than this are left empty
+const MIN_ALPHA: u8 = 128;
+
+// walls that can be built outside of the sandbox, with the item they look like
+const WALLS: &[(&str, item::Type)] = &[
+	("copper-wall", item::Type::Copper),
+	("titanium-wall", item::Type::Titanium),
+	("plastanium-wall", item::Type::Plastanium),
+	("thorium-wall", item::Type::Thorium),
+	("phase-wall", item::Type::PhaseFabric),
+	("surge-wall", item::Type::SurgeAlloy),
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Style
+{
+	Sorter,
+	InvertedSorter,
+	Wall,
+	Illuminator,
+}
+
+impl Style
+{
+	pub fn by_name(name: &str) -> Option<Self>
+	{
+		match name
+		{
+			"sorter" => Some(Self::Sorter),
+			"inverted-sorter" => Some(Self::InvertedSorter),
+			"wall" => Some(Self::Wall),
+			"illuminator" => Some(Self::Illuminator),
+			_ => None,
+		}
+	}
+}
+
+// a block (configured with an item for sorters) and the color it shows
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Swatch
+{
+	pub block: &'static str,
+	pub item: Option<item::Type>,
+	pub color: [u8; 3],
+}
+
+// every block of the style, illuminators can show any color so they have no palette
+pub fn get_palette(style: Style) -> Vec<Swatch>
+{
+	match style
+	{
+		Style::Sorter | Style::InvertedSorter =>
+		{
+			let block = if style == Style::Sorter {"sorter"} else {"inverted-sorter"};
+			Vec::from_iter((0..).map_while(|id| item::Type::try_from(id).ok()).map(|item| Swatch{block, item: Some(item), color: item.get_color()}))
+		},
+		Style::Wall => Vec::from_iter(WALLS.iter().map(|&(block, item)| Swatch{block, item: None, color: item.get_color()})),
+		Style::Illuminator => Vec::new(),
+	}
+}
+
+// weighs the channels by how sensitive the eye is to them ("redmean")
+pub(crate) fn distance(a: [f32; 3], b: [u8; 3]) -> f32
+{
+	let r = (a[0] + b[0] as f32) / 2.0;
+	let (dr, dg, db) = (a[0] - b[0] as f32, a[1] - b[1] as f32, a[2] - b[2] as f32);
+	(2.0 + r / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - r) / 256.0) * db * db
+}
+
+fn nearest(palette: &[Swatch], color: [f32; 3]) -> usize
+{
+	let mut best = (0, f32::INFINITY);
+	for (i, s) in palette.iter().enumerate()
+	{
+		let d = distance(color, s.color);
+		if d < best.1 {best = (i, d);}
+	}
+	best.0
+}
+
+// builds the image one block per pixel, either from the palette (optionally dithered) or as illuminators in the exact color
+pub fn generate<'l>(reg: &'l BlockRegistry<'l>, image: &Image, style: Style, palette: &[Swatch], dither: bool) -> Result<Schematic<'l>, PixelError>
+{
+	let (width, height) = (image.get_width(), image.get_height());
+	if width > MAX_DIMENSION as u32 || height > MAX_DIMENSION as u32 {return Err(PixelError::TooLarge{width, height});}
+	let lamp = if style == Style::Illuminator {Some(reg.get("illuminator").ok_or(PixelError::UnknownBlock("illuminator"))?)} else {None};
+	if lamp.is_none() && palette.is_empty() {return Err(PixelError::EmptyPalette);}
+	let blocks = palette.iter().map(|s| reg.get(s.block).ok_or(PixelError::UnknownBlock(s.block))).collect::<Result<Vec<_>, _>>()?;
+	
+	let mut schematic = Schematic::new(width as u16, height as u16);
+	// quantization error carried over to the following pixels
+	let mut error = vec![[0f32; 3]; (width * height) as usize];
+	for y in 0..height
+	{
+		for x in 0..width
+		{
+			let p = image.get_pixel(x, y);
+			if p[3] < MIN_ALPHA {continue;}
+			// the image's top row is the schematic's highest
+			let (sx, sy) = (x as u16, (height - 1 - y) as u16);
+			if let Some(lamp) = lamp
+			{
+				let rgba = u32::from_be_bytes([p[0], p[1], p[2], 255]);
+				schematic.set(sx, sy, lamp, DynData::Int(rgba as i32), Rotation::Right)?;
+				continue;
+			}
+			let e = error[(x + y * width) as usize];
+			let color = [0, 1, 2].map(|c| (p[c] as f32 + e[c]).clamp(0.0, 255.0));
+			let idx = nearest(palette, color);
+			let swatch = palette[idx];
+			let data = match swatch.item
+			{
+				Some(item) => DynData::Content(content::Type::Item, item.get_id()),
+				None => DynData::Empty,
+			};
+			schematic.set(sx, sy, blocks[idx], data, Rotation::Right)?;
+			if dither
+			{
+				// floyd-steinberg
+				let diff = [0, 1, 2].map(|c| color[c] - swatch.color[c] as f32);
+				for (dx, dy, weight) in [(1i32, 0u32, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)]
+				{
+					let (nx, ny) = (x as i32 + dx, y + dy);
+					if nx < 0 || nx >= width as i32 || ny >= height {continue;}
+					let target = &mut error[(nx as u32 + ny * width) as usize];
+					for c in 0..3
+					{
+						target[c] += diff[c] * weight / 16.0;
+					}
+				}
+			}
+		}
+	}
+	Ok(schematic)
+}
+
+#[derive(Debug)]
+pub enum PixelError
+{
+	TooLarge{width: u32, height: u32},
+	EmptyPalette,
+	UnknownBlock(&'static str),
+	Place(PlaceError),
+}
+
+impl From<PlaceError> for PixelError
+{
+	fn from(value: PlaceError) -> Self
+	{
+		Self::Place(value)
+	}
+}
+
+impl fmt::Display for PixelError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::TooLarge{width, height} => write!(f, "image too large for a schematic ({width} x {height})"),
+			Self::EmptyPalette => f.write_str("no blocks to choose from"),
+			Self::UnknownBlock(name) => write!(f, "no block named {name:?}"),
+			Self::Place(..) => f.write_str("could not place block"),
+		}
+	}
+}
+
+impl Error for PixelError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Place(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	use crate::block::base::RGBA;
+	
+	#[test]
+	fn pixels()
+	{
+		let reg = build_registry();
+		let copper = item::Type::Copper.get_color();
+		let coal = item::Type::Coal.get_color();
+		// copper and (nearly) coal on top, a transparent pixel and pure blue below
+		let image = Image::new(2, 2, vec![copper[0], copper[1], copper[2], 255, 40, 40, 40, 255, 0, 0, 0, 0, 0, 0, 255, 255]);
+		let schematic = generate(&reg, &image, Style::Sorter, &get_palette(Style::Sorter), false).unwrap();
+		assert_eq!(schematic.block_iter().count(), 3);
+		let item_at = |x, y| *schematic.get(x, y).unwrap().unwrap().get_state().unwrap().downcast_ref::<Option<item::Type>>().unwrap();
+		assert_eq!(item_at(0, 1), Some(item::Type::Copper));
+		assert_eq!(coal, [0x27, 0x27, 0x27]);
+		assert_eq!(item_at(1, 1), Some(item::Type::Coal));
+		assert!(schematic.get(0, 0).unwrap().is_none());
+		let (cost, sandbox) = schematic.compute_total_cost();
+		assert_eq!((cost.get_total(), sandbox), (12, false));
+		
+		let lamps = generate(&reg, &image, Style::Illuminator, &[], true).unwrap();
+		let color = *lamps.get(1, 0).unwrap().unwrap().get_state().unwrap().downcast_ref::<RGBA>().unwrap();
+		assert_eq!(color, RGBA::from(0x0000FFFFu32));
+		assert!(matches!(generate(&reg, &image, Style::Wall, &[], false), Err(PixelError::EmptyPalette)));
+		let wide = Image::new(200, 10, vec![255; 200 * 10 * 4]);
+		assert!(matches!(generate(&reg, &wide, Style::Sorter, &get_palette(Style::Sorter), false), Err(PixelError::TooLarge{width: 200, height: 10})));
+	}
+}
@@ -0,0 +1,287 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use crate::block::logic::{CodeError, CreateError, ProcessorState};
+use crate::data::GridPos;
+use crate::data::json::{self, Value};
+use crate::data::schematic::Schematic;
+use crate::logic::format;
+use crate::registry::RegistryEntry;
+
+// where the code of a processor is stored when exported, with its links (relative to the processor)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManifestEntry
+{
+	pub x: u16,
+	pub y: u16,
+	pub block: String,
+	pub file: String,
+	pub links: Vec<(String, i16, i16)>,
+}
+
+impl ManifestEntry
+{
+	pub fn to_json(&self) -> Value
+	{
+		let links = self.links.iter().map(|(name, x, y)| Value::Object(vec![
+			("name".to_owned(), Value::from(name.as_str())),
+			("x".to_owned(), Value::from(*x)),
+			("y".to_owned(), Value::from(*y)),
+		])).collect();
+		Value::Object(vec![
+			("x".to_owned(), Value::from(self.x)),
+			("y".to_owned(), Value::from(self.y)),
+			("block".to_owned(), Value::from(self.block.as_str())),
+			("file".to_owned(), Value::from(self.file.as_str())),
+			("links".to_owned(), Value::Array(links)),
+		])
+	}
+	
+	pub fn from_json(value: &Value) -> Result<Self, ManifestError>
+	{
+		fn int<T: TryFrom<i64>>(value: &Value, key: &'static str) -> Result<T, ManifestError>
+		{
+			value.get(key).and_then(Value::as_i64).and_then(|v| T::try_from(v).ok()).ok_or(ManifestError::Field(key))
+		}
+		
+		fn string(value: &Value, key: &'static str) -> Result<String, ManifestError>
+		{
+			value.get(key).and_then(Value::as_str).map(str::to_owned).ok_or(ManifestError::Field(key))
+		}
+		
+		let links = value.get("links").and_then(Value::as_array).ok_or(ManifestError::Field("links"))?.iter()
+			.map(|l| Ok((string(l, "name")?, int(l, "x")?, int(l, "y")?))).collect::<Result<_, ManifestError>>()?;
+		Ok(Self{x: int(value, "x")?, y: int(value, "y")?, block: string(value, "block")?, file: string(value, "file")?, links})
+	}
+}
+
+// the manifest entries and code of every processor, ordered by position
+pub fn export(schematic: &Schematic) -> Vec<(ManifestEntry, String)>
+{
+	let mut result = Vec::new();
+	for p in schematic.block_iter()
+	{
+		let Some(proc) = p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>())
+		else {continue;};
+		let GridPos(x, y) = p.get_pos();
+		let block = p.get_block().get_name().to_owned();
+		let links = proc.get_links().iter().map(|l| (l.get_name().to_owned(), l.get_pos().0, l.get_pos().1)).collect();
+		let file = format!("{block}-{x}-{y}.mlog");
+		result.push((ManifestEntry{x, y, block, file, links}, proc.get_code().to_owned()));
+	}
+	result.sort_by_key(|(e, _)| (e.y, e.x));
+	result
+}
+
+pub fn write_manifest(entries: &[ManifestEntry]) -> String
+{
+	let mut result = String::from("[\n");
+	for (i, entry) in entries.iter().enumerate()
+	{
+		result.push_str(&entry.to_json().to_string());
+		result.push_str(if i + 1 < entries.len() {",\n"} else {"\n"});
+	}
+	result.push_str("]\n");
+	result
+}
+
+pub fn read_manifest(text: &str) -> Result<Vec<ManifestEntry>, ManifestError>
+{
+	let value = json::parse(text)?;
+	value.as_array().ok_or(ManifestError::NotArray)?.iter().map(ManifestEntry::from_json).collect()
+}
+
+fn get_processor<'s>(schematic: &'s mut Schematic, x: u16, y: u16, block: &str) -> Result<&'s mut ProcessorState, ImportError>
+{
+	let Ok(Some(p)) = schematic.get_mut(x, y)
+	else {return Err(ImportError::NotFound{x, y});};
+	let name = p.get_block().get_name();
+	if name != block || p.get_pos() != GridPos(x, y)
+	{
+		return Err(ImportError::WrongBlock{x, y, expected: block.to_owned(), found: name.to_owned()});
+	}
+	p.get_state_mut().and_then(|s| s.downcast_mut::<ProcessorState>()).ok_or(ImportError::NotFound{x, y})
+}
+
+fn replace_state(proc: &mut ProcessorState, code: &str, links: &[(String, i16, i16)]) -> Result<(), ImportError>
+{
+	let mut state = ProcessorState::new();
+	state.set_code(Cow::Borrowed(code))?;
+	for (name, x, y) in links
+	{
+		state.create_link(name.clone(), *x, *y)?;
+	}
+	// only change anything once everything is known to be valid
+	*proc = state;
+	Ok(())
+}
+
+// replaces the code and links of the processor described by the entry, which must still be in the same place
+pub fn import(schematic: &mut Schematic, entry: &ManifestEntry, code: &str) -> Result<(), ImportError>
+{
+	replace_state(get_processor(schematic, entry.x, entry.y, &entry.block)?, code, &entry.links)
+}
+
+// replaces the code and links of whatever processor is at the position
+pub fn set_program(schematic: &mut Schematic, x: u16, y: u16, code: &str, links: &[(String, i16, i16)]) -> Result<(), ImportError>
+{
+	let Ok(Some(p)) = schematic.get_mut(x, y)
+	else {return Err(ImportError::NotFound{x, y});};
+	let Some(proc) = p.get_state_mut().and_then(|s| s.downcast_mut::<ProcessorState>())
+	else {return Err(ImportError::NotFound{x, y});};
+	replace_state(proc, code, links)
+}
+
+// the same program with insignificant differences (spacing, comments, labels and empty lines) removed
+fn normalize(code: &str) -> String
+{
+	if let Ok(numeric) = format::remove_labels(code) {return numeric;}
+	let lines = Vec::from_iter(code.lines().map(str::trim_end));
+	let end = lines.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+	lines[..end].join("\n")
+}
+
+// sets the code of every processor running the old program, returns their positions
+pub fn replace_code(schematic: &mut Schematic, old: &str, new: &str) -> Result<Vec<GridPos>, CodeError>
+{
+	let old = normalize(old);
+	// fail before changing anything
+	ProcessorState::new().set_code(Cow::Borrowed(new))?;
+	let matching = Vec::from_iter(schematic.block_iter().filter(|p|
+	{
+		p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>()).is_some_and(|s| normalize(s.get_code()) == old)
+	}).map(|p| p.get_pos()));
+	for &GridPos(x, y) in matching.iter()
+	{
+		let proc = schematic.get_mut(x, y).unwrap().unwrap().get_state_mut().unwrap().downcast_mut::<ProcessorState>().unwrap();
+		proc.set_code(Cow::Borrowed(new))?;
+	}
+	Ok(matching)
+}
+
+#[derive(Debug)]
+pub enum ManifestError
+{
+	Json(json::ParseError),
+	NotArray,
+	Field(&'static str),
+}
+
+impl From<json::ParseError> for ManifestError
+{
+	fn from(value: json::ParseError) -> Self
+	{
+		Self::Json(value)
+	}
+}
+
+impl fmt::Display for ManifestError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Json(..) => f.write_str("invalid json"),
+			Self::NotArray => f.write_str("manifest is not an array"),
+			Self::Field(key) => write!(f, "missing or invalid field {key:?}"),
+		}
+	}
+}
+
+impl Error for ManifestError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Json(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum ImportError
+{
+	NotFound{x: u16, y: u16},
+	WrongBlock{x: u16, y: u16, expected: String, found: String},
+	Code(CodeError),
+	Link(CreateError),
+}
+
+impl From<CodeError> for ImportError
+{
+	fn from(value: CodeError) -> Self
+	{
+		Self::Code(value)
+	}
+}
+
+impl From<CreateError> for ImportError
+{
+	fn from(value: CreateError) -> Self
+	{
+		Self::Link(value)
+	}
+}
+
+impl fmt::Display for ImportError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::NotFound{x, y} => write!(f, "no processor at {x} / {y}"),
+			Self::WrongBlock{x, y, expected, found} => write!(f, "expected {expected} at {x} / {y} but found {found}"),
+			Self::Code(..) => f.write_str("invalid code"),
+			Self::Link(..) => f.write_str("invalid link"),
+		}
+	}
+}
+
+impl Error for ImportError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Code(e) => Some(e),
+			Self::Link(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, Rotation};
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn roundtrip()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(6, 3);
+		for x in [0, 2, 4]
+		{
+			schematic.set(x, 0, reg.get("micro-processor").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		}
+		let exported = export(&schematic);
+		assert_eq!(exported.len(), 3);
+		let mut entry = exported[1].0.clone();
+		assert_eq!(entry.file, "micro-processor-2-0.mlog");
+		entry.links.push(("cell1".to_string(), 1, 0));
+		let entries = read_manifest(&write_manifest(std::slice::from_ref(&entry))).unwrap();
+		assert_eq!(entries, vec![entry.clone()]);
+		import(&mut schematic, &entry, "sensor x cell1 @totalItems\n").unwrap();
+		assert_eq!(export(&schematic)[1].0.links, entry.links);
+		entry.block = "logic-processor".to_string();
+		assert!(matches!(import(&mut schematic, &entry, ""), Err(ImportError::WrongBlock{..})));
+		let replaced = replace_code(&mut schematic, "sensor x cell1 @totalItems  \n\n", "end").unwrap();
+		assert_eq!(replaced, vec![GridPos(2, 0)]);
+		assert_eq!(replace_code(&mut schematic, "", "noop").unwrap().len(), 2);
+	}
+}
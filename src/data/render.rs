@@ -0,0 +1,246 @@
+use std::fmt::Write;
+
+use flate2::{Compress, Compression, FlushCompress, Status};
+
+use crate::block::Rotation;
+use crate::block::logic::CanvasState;
+use crate::data::schematic::{Placement, Schematic};
+use crate::registry::RegistryEntry;
+
+const BACKGROUND: [u8; 3] = [0x25, 0x25, 0x2B];
+
+// a stable color for each block type, so the same blocks look alike across renders
+fn block_color(name: &str) -> [u8; 3]
+{
+	let hash = name.bytes().fold(0x811C9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193));
+	// hue from the hash, with fixed saturation and value to keep everything readable on the background
+	let hue = (hash % 360) as f32 / 60.0;
+	let (s, v) = (0.55f32, 0.85f32);
+	let c = v * s;
+	let x = c * (1.0 - (hue % 2.0 - 1.0).abs());
+	let (r, g, b) = match hue as u32
+	{
+		0 => (c, x, 0.0),
+		1 => (x, c, 0.0),
+		2 => (0.0, c, x),
+		3 => (0.0, x, c),
+		4 => (x, 0.0, c),
+		_ => (c, 0.0, x),
+	};
+	let m = v - c;
+	[((r + m) * 255.0) as u8, ((g + m) * 255.0) as u8, ((b + m) * 255.0) as u8]
+}
+
+fn darken(color: [u8; 3]) -> [u8; 3]
+{
+	color.map(|c| c / 2)
+}
+
+// the covered tiles as (left, bottom, size)
+fn bounds(p: &Placement) -> (u32, u32, u32)
+{
+	let sz = p.get_block().get_size() as u32;
+	let pos = p.get_pos();
+	(pos.0 as u32 - (sz - 1) / 2, pos.1 as u32 - (sz - 1) / 2, sz)
+}
+
+// renders every block as a colored square (with a marker pointing in its direction if rotation matters) into RGB
+// pixels, top row first, with each tile taking scale * scale pixels
+pub fn render_rgb(schematic: &Schematic, scale: u32) -> (u32, u32, Vec<u8>)
+{
+	let scale = scale.max(1);
+	let (w, h) = (schematic.get_width() as u32 * scale, schematic.get_height() as u32 * scale);
+	let mut pixels = Vec::from_iter(std::iter::repeat_n(BACKGROUND, (w * h) as usize).flatten());
+	let mut fill = |x0: u32, y0: u32, x1: u32, y1: u32, color: [u8; 3]|
+	{
+		for y in y0..y1
+		{
+			// image rows go down while schematic rows go up
+			let row = (h - 1 - y) * w;
+			for x in x0..x1
+			{
+				let i = ((row + x) * 3) as usize;
+				pixels[i..i + 3].copy_from_slice(&color);
+			}
+		}
+	};
+	for p in schematic.block_iter()
+	{
+		let (x, y, sz) = bounds(p);
+		let color = block_color(p.get_block().get_name());
+		let (x0, y0, len) = (x * scale, y * scale, sz * scale);
+		let border = if scale >= 4 {1} else {0};
+		fill(x0, y0, x0 + len, y0 + len, darken(color));
+		fill(x0 + border, y0 + border, x0 + len - border, y0 + len - border, color);
+		if let Some(canvas) = p.get_state().and_then(|s| s.downcast_ref::<CanvasState>())
+		{
+			// the pixels cover the inside of the border, the first canvas row being the top one
+			let inner = len - 2 * border;
+			let (cw, ch) = (canvas.get_width() as u32, canvas.get_height() as u32);
+			let top = y0 + len - border;
+			for cy in 0..ch
+			{
+				for cx in 0..cw
+				{
+					let color = canvas.get_color(cx as u16, cy as u16).unwrap();
+					fill(x0 + border + cx * inner / cw, top - (cy + 1) * inner / ch, x0 + border + (cx + 1) * inner / cw, top - cy * inner / ch, color);
+				}
+			}
+		}
+		if !p.get_block().is_symmetric() && scale >= 4
+		{
+			let mark = (len / 4).max(1);
+			let mid = len / 2 - mark / 2;
+			let (mx, my) = match p.get_rotation()
+			{
+				Rotation::Right => (len - border - mark, mid),
+				Rotation::Up => (mid, len - border - mark),
+				Rotation::Left => (border, mid),
+				Rotation::Down => (mid, border),
+			};
+			fill(x0 + mx, y0 + my, x0 + mx + mark, y0 + my + mark, darken(color));
+		}
+	}
+	(w, h, pixels)
+}
+
+fn crc32(data: &[u8]) -> u32
+{
+	let mut crc = !0u32;
+	for &b in data
+	{
+		crc ^= b as u32;
+		for _ in 0..8
+		{
+			crc = if crc & 1 != 0 {(crc >> 1) ^ 0xEDB88320} else {crc >> 1};
+		}
+	}
+	!crc
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8])
+{
+	out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+	let start = out.len();
+	out.extend_from_slice(kind);
+	out.extend_from_slice(data);
+	let crc = crc32(&out[start..]);
+	out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// encodes RGB pixels (top row first) as a PNG image
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Vec<u8>
+{
+	assert_eq!(pixels.len(), (width * height * 3) as usize);
+	let mut raw = Vec::with_capacity(((width * 3 + 1) * height) as usize);
+	for row in pixels.chunks_exact((width * 3) as usize)
+	{
+		// no filter
+		raw.push(0);
+		raw.extend_from_slice(row);
+	}
+	let mut comp = Compress::new(Compression::new(6), true);
+	let mut data = Vec::with_capacity(raw.len() / 4 + 64);
+	loop
+	{
+		let t_in = comp.total_in() as usize;
+		let status = comp.compress_vec(&raw[t_in..], &mut data, FlushCompress::Finish).expect("compressing into a vec cannot fail");
+		if status == Status::StreamEnd {break;}
+		data.reserve(data.capacity().max(1024));
+	}
+	let mut out = Vec::new();
+	out.extend_from_slice(b"\x89PNG\r\n\x1A\n");
+	let mut header = Vec::with_capacity(13);
+	header.extend_from_slice(&width.to_be_bytes());
+	header.extend_from_slice(&height.to_be_bytes());
+	// 8-bit RGB, default compression and filtering, no interlacing
+	header.extend_from_slice(&[8, 2, 0, 0, 0]);
+	write_chunk(&mut out, b"IHDR", &header);
+	write_chunk(&mut out, b"IDAT", &data);
+	write_chunk(&mut out, b"IEND", &[]);
+	out
+}
+
+pub fn render_png(schematic: &Schematic, scale: u32) -> Vec<u8>
+{
+	let (w, h, pixels) = render_rgb(schematic, scale);
+	encode_png(w, h, &pixels)
+}
+
+// the pixels of every canvas as text (see CanvasState's Display), which the block grid has no room for
+pub fn render_canvases(schematic: &Schematic) -> String
+{
+	let mut out = String::new();
+	let mut blocks = Vec::from_iter(schematic.block_iter());
+	blocks.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+	for p in blocks
+	{
+		if let Some(canvas) = p.get_state().and_then(|s| s.downcast_ref::<CanvasState>())
+		{
+			let pos = p.get_pos();
+			writeln!(out, "{} at {} / {}:\n{canvas}", p.get_block().get_name(), pos.0, pos.1).unwrap();
+		}
+	}
+	out
+}
+
+// the same picture as render_rgb as an SVG document, where each block is labelled with its name
+pub fn render_svg(schematic: &Schematic, scale: u32) -> String
+{
+	let scale = scale.max(1);
+	let (w, h) = (schematic.get_width() as u32 * scale, schematic.get_height() as u32 * scale);
+	let hex = |c: [u8; 3]| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]);
+	let mut out = String::new();
+	writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#).unwrap();
+	writeln!(out, r#"<rect width="{w}" height="{h}" fill="{}"/>"#, hex(BACKGROUND)).unwrap();
+	let mut blocks = Vec::from_iter(schematic.block_iter());
+	blocks.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+	for p in blocks
+	{
+		let (x, y, sz) = bounds(p);
+		let color = block_color(p.get_block().get_name());
+		let (left, top, len) = (x * scale, h - (y + sz) * scale, sz * scale);
+		let pos = p.get_pos();
+		write!(out, r#"<rect x="{left}" y="{top}" width="{len}" height="{len}" fill="{}" stroke="{}">"#, hex(color), hex(darken(color))).unwrap();
+		writeln!(out, "<title>{} at {} / {}</title></rect>", p.get_block().get_name(), pos.0, pos.1).unwrap();
+		if !p.get_block().is_symmetric()
+		{
+			let (cx, cy, r) = (left as f32 + len as f32 / 2.0, top as f32 + len as f32 / 2.0, len as f32 * 0.35);
+			let (dx, dy) = match p.get_rotation()
+			{
+				Rotation::Right => (r, 0.0),
+				Rotation::Up => (0.0, -r),
+				Rotation::Left => (-r, 0.0),
+				Rotation::Down => (0.0, r),
+			};
+			writeln!(out, r#"<line x1="{cx}" y1="{cy}" x2="{}" y2="{}" stroke="{}" stroke-width="{}"/>"#, cx + dx, cy + dy,
+				hex(darken(color)), (scale as f32 / 8.0).max(1.0)).unwrap();
+		}
+	}
+	out.push_str("</svg>\n");
+	out
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn png()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(3, 2);
+		schematic.set(0, 0, reg.get("conveyor").unwrap(), DynData::Empty, Rotation::Up).unwrap();
+		let (w, h, pixels) = render_rgb(&schematic, 4);
+		assert_eq!((w, h), (12, 8));
+		// the conveyor is in the bottom left corner of the image, the top right is empty
+		assert_eq!(pixels[((h - 2) * w * 3 + 3) as usize..][..3], block_color("conveyor"));
+		assert_eq!(pixels[((w - 1) * 3) as usize..][..3], BACKGROUND);
+		let png = encode_png(w, h, &pixels);
+		assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+		assert_eq!(crc32(b"IEND"), 0xAE426082);
+	}
+}
@@ -0,0 +1,380 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+
+use crate::block::{Block, Rotation};
+use crate::block::transport::get_bridge;
+use crate::data::dynamic::DynData;
+use crate::data::flow::takes_items;
+use crate::data::schematic::{PlaceError, Schematic};
+use crate::registry::RegistryEntry;
+
+// conveyors a junction can take the place of where the route crosses them
+const CROSSABLE: &[&str] = &["conveyor", "titanium-conveyor", "armored-conveyor"];
+const DIRECTIONS: [Rotation; 4] = [Rotation::Right, Rotation::Up, Rotation::Left, Rotation::Down];
+// costs per tile, turns and junctions are slightly worse than going straight
+const STEP_COST: u32 = 10;
+const TURN_COST: u32 = 1;
+const JUNCTION_COST: u32 = 12;
+// on top of the tiles a bridge spans, so that bridges are only used where they make the route shorter
+const BRIDGE_COST: u32 = 15;
+
+pub struct RouteOptions<'l>
+{
+	pub conveyor: &'l Block,
+	// used to cross conveyors that are in the way
+	pub junction: Option<&'l Block>,
+	// used to hop over obstacles, must be one of the item bridges
+	pub bridge: Option<&'l Block>,
+	// the direction of the last conveyor, the direction of travel by default
+	pub end: Option<Rotation>,
+}
+
+impl<'l> RouteOptions<'l>
+{
+	pub fn new(conveyor: &'l Block) -> Self
+	{
+		Self{conveyor, junction: None, bridge: None, end: None}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RouteSummary
+{
+	pub conveyors: usize,
+	pub junctions: usize,
+	pub bridges: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Tile
+{
+	Free,
+	Conveyor(Rotation),
+	Junction,
+	Blocked,
+}
+
+// what the route puts into a tile
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Piece
+{
+	Conveyor(Rotation),
+	Junction,
+	// an existing junction that the route passes through
+	Through,
+	// the input end of a bridge, linked to the output this many tiles ahead
+	Bridge(Rotation, u16),
+}
+
+fn offset(rot: Rotation) -> (i32, i32)
+{
+	match rot
+	{
+		Rotation::Right => (1, 0),
+		Rotation::Up => (0, 1),
+		Rotation::Left => (-1, 0),
+		Rotation::Down => (0, -1),
+	}
+}
+
+fn is_horizontal(rot: Rotation) -> bool
+{
+	matches!(rot, Rotation::Right | Rotation::Left)
+}
+
+// places the shortest chain of conveyors that carries items from one tile to the other, both of which must be empty
+// the route may cross conveyors with junctions and hop over anything with bridges
+pub fn route<'l>(schematic: &mut Schematic<'l>, from: (u16, u16), to: (u16, u16), options: &RouteOptions<'l>) -> Result<RouteSummary, RouteError>
+{
+	for block in [Some(options.conveyor), options.junction, options.bridge].into_iter().flatten()
+	{
+		if block.get_size() != 1 {return Err(RouteError::BlockSize(block.get_name().to_owned()));}
+	}
+	let range = match options.bridge
+	{
+		None => 0,
+		Some(b) => get_bridge(b.get_name()).ok_or_else(|| RouteError::NotBridge(b.get_name().to_owned()))?.get_range(),
+	};
+	for (x, y) in [from, to]
+	{
+		match schematic.get(x, y)
+		{
+			Err(..) => return Err(RouteError::OutOfBounds{x, y}),
+			Ok(Some(..)) => return Err(RouteError::Occupied{x, y}),
+			Ok(None) => (),
+		}
+	}
+	
+	let (w, h) = (schematic.get_width() as i32, schematic.get_height() as i32);
+	let mut tiles = Vec::with_capacity((w * h) as usize);
+	for y in 0..h
+	{
+		for x in 0..w
+		{
+			tiles.push(match schematic.get(x as u16, y as u16).unwrap()
+			{
+				None => Tile::Free,
+				Some(p) if CROSSABLE.contains(&p.get_block().get_name()) => Tile::Conveyor(p.get_rotation()),
+				Some(p) if p.get_block().get_name() == "junction" => Tile::Junction,
+				Some(..) => Tile::Blocked,
+			});
+		}
+	}
+	
+	// dijkstra over (tile, direction items arrive from), with a fifth direction for the start where they come from anywhere
+	let len = tiles.len() * 5;
+	let mut dist = vec![u32::MAX; len];
+	let mut prev = vec![None::<(usize, Piece)>; len];
+	let mut heap = BinaryHeap::new();
+	let start = (from.0 as usize + from.1 as usize * w as usize) * 5 + 4;
+	dist[start] = 0;
+	heap.push(Reverse((0u32, start)));
+	let mut goal = None;
+	while let Some(Reverse((cost, node))) = heap.pop()
+	{
+		if cost > dist[node] {continue;}
+		let (tile, incoming) = (node / 5, DIRECTIONS.get(node % 5).copied());
+		let (x, y) = ((tile % w as usize) as i32, (tile / w as usize) as i32);
+		if (x, y) == (to.0 as i32, to.1 as i32)
+		{
+			let rot = options.end.or(incoming).unwrap_or(Rotation::Right);
+			// the last conveyor can't face back into the route
+			if incoming.is_some_and(|r| r.rotated_180() == rot) {continue;}
+			goal = Some((node, rot));
+			break;
+		}
+		let mut visit = |nx: i32, ny: i32, dir: usize, add: u32, piece: Piece|
+		{
+			if nx < 0 || ny < 0 || nx >= w || ny >= h {return;}
+			let next = (nx + ny * w) as usize * 5 + dir;
+			if cost + add < dist[next]
+			{
+				dist[next] = cost + add;
+				prev[next] = Some((node, piece));
+				heap.push(Reverse((cost + add, next)));
+			}
+		};
+		match tiles[tile]
+		{
+			Tile::Free =>
+			{
+				for (dir, &rot) in DIRECTIONS.iter().enumerate()
+				{
+					if incoming.is_some_and(|r| r.rotated_180() == rot) {continue;}
+					let (dx, dy) = offset(rot);
+					let turn = if incoming.is_some_and(|r| r != rot) {TURN_COST} else {0};
+					visit(x + dx, y + dy, dir, STEP_COST + turn, Piece::Conveyor(rot));
+					for span in 2..=range as i32
+					{
+						let (ex, ey) = (x + dx * span, y + dy * span);
+						if ex < 0 || ey < 0 || ex >= w || ey >= h {break;}
+						// the output end needs space and items leave it towards the next tile
+						if tiles[(ex + ey * w) as usize] != Tile::Free || (ex, ey) == (to.0 as i32, to.1 as i32) {continue;}
+						// it also passes items to both sides, where they must not leak into anything that takes them
+						let leaks = DIRECTIONS.iter().filter(|&&side| is_horizontal(side) != is_horizontal(rot)).any(|&side|
+						{
+							let (sx, sy) = offset(side);
+							let (sx, sy) = (ex + sx, ey + sy);
+							sx >= 0 && sy >= 0 && sx < w && sy < h && schematic.get(sx as u16, sy as u16).ok().flatten()
+								.is_some_and(|p| takes_items(p.get_block(), p.get_rotation(), side))
+						});
+						if leaks {continue;}
+						visit(ex + dx, ey + dy, dir, STEP_COST * (span as u32 + 1) + BRIDGE_COST + turn, Piece::Bridge(rot, span as u16));
+					}
+				}
+			},
+			Tile::Conveyor(rot) =>
+			{
+				if let Some(r) = incoming
+				{
+					if options.junction.is_some() && is_horizontal(r) != is_horizontal(rot)
+					{
+						let (dx, dy) = offset(r);
+						visit(x + dx, y + dy, node % 5, JUNCTION_COST, Piece::Junction);
+					}
+				}
+			},
+			Tile::Junction =>
+			{
+				if let Some(r) = incoming
+				{
+					let (dx, dy) = offset(r);
+					visit(x + dx, y + dy, node % 5, STEP_COST, Piece::Through);
+				}
+			},
+			Tile::Blocked => (),
+		}
+	}
+	
+	let Some((mut node, last)) = goal
+	else {return Err(RouteError::NoPath);};
+	let pos = |node: usize| ((node / 5) as i32 % w, (node / 5) as i32 / w);
+	let mut pieces = vec![(pos(node), Piece::Conveyor(last))];
+	while let Some((p, piece)) = prev[node]
+	{
+		pieces.push((pos(p), piece));
+		node = p;
+	}
+	pieces.reverse();
+	// the search doesn't know which tiles the route itself takes, so it could cross itself
+	let mut used = vec![false; tiles.len()];
+	for &((x, y), piece) in pieces.iter()
+	{
+		let mut claim = |x: i32, y: i32| !std::mem::replace(&mut used[(x + y * w) as usize], true);
+		let free = match piece
+		{
+			Piece::Bridge(rot, span) =>
+			{
+				let (dx, dy) = offset(rot);
+				claim(x, y) && claim(x + dx * span as i32, y + dy * span as i32)
+			},
+			_ => claim(x, y),
+		};
+		if !free {return Err(RouteError::NoPath);}
+	}
+	
+	let mut summary = RouteSummary::default();
+	for ((x, y), piece) in pieces
+	{
+		let (x, y) = (x as u16, y as u16);
+		match piece
+		{
+			Piece::Conveyor(rot) =>
+			{
+				schematic.set(x, y, options.conveyor, DynData::Empty, rot)?;
+				summary.conveyors += 1;
+			},
+			Piece::Junction =>
+			{
+				let _ = schematic.take(x, y);
+				schematic.set(x, y, options.junction.unwrap(), DynData::Empty, Rotation::Right)?;
+				summary.junctions += 1;
+			},
+			Piece::Through => (),
+			Piece::Bridge(rot, span) =>
+			{
+				let bridge = options.bridge.unwrap();
+				let (dx, dy) = offset(rot);
+				let (dx, dy) = (dx * span as i32, dy * span as i32);
+				schematic.set(x, y, bridge, DynData::Point2(dx, dy), rot)?;
+				schematic.set((x as i32 + dx) as u16, (y as i32 + dy) as u16, bridge, DynData::Empty, rot)?;
+				summary.bridges += 1;
+			},
+		}
+	}
+	Ok(summary)
+}
+
+#[derive(Debug)]
+pub enum RouteError
+{
+	OutOfBounds{x: u16, y: u16},
+	Occupied{x: u16, y: u16},
+	BlockSize(String),
+	NotBridge(String),
+	NoPath,
+	Place(PlaceError),
+}
+
+impl From<PlaceError> for RouteError
+{
+	fn from(value: PlaceError) -> Self
+	{
+		Self::Place(value)
+	}
+}
+
+impl fmt::Display for RouteError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::OutOfBounds{x, y} => write!(f, "position {x} / {y} is out of bounds"),
+			Self::Occupied{x, y} => write!(f, "position {x} / {y} is not empty"),
+			Self::BlockSize(name) => write!(f, "{name} is larger than a single tile"),
+			Self::NotBridge(name) => write!(f, "{name} is not an item bridge"),
+			Self::NoPath => f.write_str("no free path between the positions"),
+			Self::Place(..) => f.write_str("could not place block"),
+		}
+	}
+}
+
+impl Error for RouteError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Place(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	
+	#[test]
+	fn obstacles()
+	{
+		let reg = build_registry();
+		let conveyor = reg.get("conveyor").unwrap();
+		let mut schematic = Schematic::new(9, 3);
+		// a wall two tiles thick across the whole height and a conveyor going up in front of it
+		for y in 0..3
+		{
+			schematic.set(4, y, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+			schematic.set(5, y, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+			schematic.set(2, y, conveyor, DynData::Empty, Rotation::Up).unwrap();
+		}
+		let mut options = RouteOptions::new(conveyor);
+		assert!(matches!(route(&mut schematic.clone(), (0, 1), (8, 1), &options), Err(RouteError::NoPath)));
+		options.junction = reg.get("junction");
+		options.bridge = reg.get("bridge-conveyor");
+		let summary = route(&mut schematic, (0, 1), (8, 1), &options).unwrap();
+		assert_eq!(summary, RouteSummary{conveyors: 3, junctions: 1, bridges: 1});
+		assert_eq!(schematic.get(2, 1).unwrap().unwrap().get_block().get_name(), "junction");
+		// the bridge spans its full range, right up to the last conveyor
+		let input = schematic.get(3, 1).unwrap().unwrap();
+		assert_eq!(input.get_block().get_name(), "bridge-conveyor");
+		assert_eq!(input.get_block().serialize_state(input.get_state().unwrap()).unwrap(), DynData::Point2(4, 0));
+		assert_eq!(schematic.get(8, 1).unwrap().unwrap().get_rotation(), Rotation::Right);
+		
+		let mut open = Schematic::new(4, 4);
+		options.end = Some(Rotation::Up);
+		assert_eq!(route(&mut open, (0, 0), (3, 3), &options).unwrap().conveyors, 7);
+		assert_eq!(open.get(3, 3).unwrap().unwrap().get_rotation(), Rotation::Up);
+		assert!(matches!(route(&mut open, (0, 0), (1, 1), &options), Err(RouteError::Occupied{x: 0, y: 0})));
+		// conduit bridges have a range, but carry no items
+		options.bridge = reg.get("bridge-conduit");
+		assert!(matches!(route(&mut open, (1, 0), (2, 0), &options), Err(RouteError::NotBridge(..))));
+	}
+	
+	#[test]
+	fn bridge_sides()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(8, 3);
+		// a wall two tiles thick, with a container beside (5, 0) where the bridge could land
+		for x in 2..4
+		{
+			for y in 0..3
+			{
+				schematic.set(x, y, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+			}
+		}
+		schematic.set(5, 1, reg.get("container").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let mut options = RouteOptions::new(reg.get("conveyor").unwrap());
+		options.bridge = reg.get("bridge-conveyor");
+		route(&mut schematic, (0, 0), (7, 0), &options).unwrap();
+		// items would also go into the container if the bridge ended there
+		assert_eq!(schematic.get(4, 0).unwrap().unwrap().get_block().get_name(), "bridge-conveyor");
+		assert_eq!(schematic.get(5, 0).unwrap().unwrap().get_block().get_name(), "conveyor");
+	}
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::error::Error;
 use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
 use std::iter::FusedIterator;
 use std::slice::Iter;
 
@@ -12,6 +13,7 @@ use crate::block::{self, Block, BlockRegistry, Rotation};
 use crate::data::{self, DataRead, DataWrite, GridPos, Serializer};
 use crate::data::base64;
 use crate::data::dynamic::{self, DynData, DynSerializer};
+use crate::data::json;
 use crate::item::storage::Storage as ItemStorage;
 use crate::registry::RegistryEntry;
 
@@ -85,13 +87,94 @@ impl<'l> Clone for Placement<'l>
 			state: match self.state
 			{
 				None => None,
-				Some(ref s) => Some(self.block.clone_state(s)),
+				Some(ref s) => Some(self.block.clone_state(s.as_ref())),
 			},
 			rot: self.rot,
 		}
 	}
 }
 
+impl<'l> Placement<'l>
+{
+	// states don't implement comparison or hashing, so both use their serialized form
+	fn get_config_bytes(&self) -> Vec<u8>
+	{
+		let data = match self.state
+		{
+			None => DynData::Empty,
+			Some(ref s) => self.block.serialize_state(s.as_ref()).unwrap_or(DynData::Empty),
+		};
+		let mut buff = DataWrite::new();
+		match DynSerializer.serialize(&mut buff, &data)
+		{
+			Ok(()) => buff.get_written().to_vec(),
+			Err(..) => Vec::new(),
+		}
+	}
+	
+	fn hash_at<H: Hasher>(&self, state: &mut H, pos: GridPos, with_rot: bool)
+	{
+		hash_bytes(state, self.block.get_name().as_bytes());
+		state.write(&u32::from(pos).to_le_bytes());
+		state.write(&[if with_rot {self.rot.into()} else {0}]);
+		hash_bytes(state, &self.get_config_bytes());
+	}
+}
+
+impl<'l> PartialEq for Placement<'l>
+{
+	fn eq(&self, other: &Self) -> bool
+	{
+		self.pos == other.pos && self.block.get_name() == other.block.get_name() && self.rot == other.rot
+			&& self.get_config_bytes() == other.get_config_bytes()
+	}
+}
+
+impl<'l> Eq for Placement<'l> {}
+
+impl<'l> Hash for Placement<'l>
+{
+	fn hash<H: Hasher>(&self, state: &mut H)
+	{
+		self.hash_at(state, self.pos, true);
+	}
+}
+
+// only writes explicit bytes so that the result is the same on every platform
+fn hash_bytes<H: Hasher>(state: &mut H, bytes: &[u8])
+{
+	state.write(&(bytes.len() as u32).to_le_bytes());
+	state.write(bytes);
+}
+
+// FNV-1a, unlike the standard library's hasher its output is specified and won't change between versions
+struct Fnv(u64);
+
+impl Fnv
+{
+	fn new() -> Self
+	{
+		Self(0xCBF29CE484222325)
+	}
+}
+
+impl Hasher for Fnv
+{
+	fn write(&mut self, bytes: &[u8])
+	{
+		for &b in bytes
+		{
+			self.0 ^= b as u64;
+			self.0 = self.0.wrapping_mul(0x100000001B3);
+		}
+	}
+	
+	fn finish(&self) -> u64
+	{
+		self.0
+	}
+}
+
 #[derive(Clone)]
 pub struct Schematic<'l>
 {
@@ -151,6 +234,41 @@ impl<'l> Schematic<'l>
 		&mut self.tags
 	}
 	
+	pub fn get_labels(&self) -> Result<Vec<String>, LabelError>
+	{
+		let labels = match self.tags.get("labels")
+		{
+			None => return Ok(Vec::new()),
+			Some(s) if s.trim().is_empty() => return Ok(Vec::new()),
+			Some(s) => s,
+		};
+		match json::parse_minimal(labels)?
+		{
+			json::Value::Array(values) =>
+			{
+				let mut result = Vec::with_capacity(values.len());
+				for (i, v) in values.into_iter().enumerate()
+				{
+					match v
+					{
+						json::Value::String(s) => result.push(s),
+						// unquoted labels that happen to look like other values
+						json::Value::Number(..) | json::Value::Boolean(..) => result.push(v.to_string()),
+						_ => return Err(LabelError::Element(i)),
+					}
+				}
+				Ok(result)
+			},
+			_ => Err(LabelError::NotArray),
+		}
+	}
+	
+	pub fn set_labels<S: AsRef<str>>(&mut self, labels: &[S])
+	{
+		let value = json::Value::Array(labels.iter().map(|s| json::Value::from(s.as_ref())).collect());
+		self.tags.insert("labels".to_string(), value.to_string());
+	}
+	
 	pub fn is_empty(&self) -> bool
 	{
 		self.blocks.is_empty()
@@ -289,11 +407,13 @@ impl<'l> Schematic<'l>
 		{
 			let mut result = if collect {Some(Vec::new())} else {None};
 			// remove all blocks in the region
+			let (x0, y0) = ((x - off) as usize, (y - off) as usize);
 			for dy in 0..(sz as usize)
 			{
 				for dx in 0..(sz as usize)
 				{
-					if let Some(idx) = self.lookup[(x as usize + dx) + (y as usize + dy) * (self.width as usize)]
+					if self.blocks.is_empty() {break;}
+					if let Some(idx) = self.lookup[(x0 + dx) + (y0 + dy) * (self.width as usize)]
 					{
 						let prev = self.swap_remove(idx);
 						if let Some(ref mut v) = result {v.push(prev);}
@@ -309,14 +429,15 @@ impl<'l> Schematic<'l>
 		else
 		{
 			let pos = (x as usize) + (y as usize) * (self.width as usize);
-			match self.lookup[pos]
+			// the lookup is only allocated once there are blocks
+			match if self.blocks.is_empty() {None} else {self.lookup[pos]}
 			{
 				None =>
 				{
 					let idx = self.blocks.len();
 					let state = block.deserialize_state(data)?;
 					self.blocks.push(Placement{pos: GridPos(x, y), block, state, rot});
-					self.lookup[pos] = Some(idx);
+					self.fill_lookup(x as usize, y as usize, 1, Some(idx));
 					Ok(if collect {Some(Vec::new())} else {None})
 				},
 				Some(idx) =>
@@ -439,11 +560,12 @@ impl<'l> Schematic<'l>
 		{
 			return Err(ResizeError::TargetHeight(h));
 		}
-		if dx <= -(w as i16) || dx >= self.width as i16
+		// blocks are moved by the offset, so it must keep at least one column / row in bounds
+		if dx <= -(self.width as i16) || dx >= w as i16
 		{
 			return Err(ResizeError::XOffset{dx, old_w: self.width, new_w: w});
 		}
-		if dy <= -(h as i16) || dy >= self.height as i16
+		if dy <= -(self.height as i16) || dy >= h as i16
 		{
 			return Err(ResizeError::YOffset{dy, old_h: self.height, new_h: h});
 		}
@@ -452,20 +574,21 @@ impl<'l> Schematic<'l>
 		let mut top = 0u16;
 		let mut left = 0u16;
 		let mut bottom = 0u16;
-		let right_bound = dx + w as i16 - 1;
-		let top_bound = dy + h as i16 - 1;
-		let left_bound = dx;
-		let bottom_bound = dy;
+		let right_bound = w as i32 - 1 - dx as i32;
+		let top_bound = h as i32 - 1 - dy as i32;
+		let left_bound = -(dx as i32);
+		let bottom_bound = -(dy as i32);
 		for Placement{pos, block, ..} in self.blocks.iter()
 		{
 			let sz = block.get_size() as u16;
-			let (x0, y0, x1, y1) = (pos.0 - (sz - 1) / 2, pos.1 - (sz - 1) / 2, pos.0 + sz / 2, pos.1 + sz / 2);
-			if (x1 as i16) > right_bound && x1 - right_bound as u16 > right {right = x1 - right_bound as u16;}
-			if (y1 as i16) > top_bound && y1 - top_bound as u16 > top {top = y1 - top_bound as u16;}
-			if (x0 as i16) < left_bound && left_bound as u16 - x0 > left {left = left_bound as u16 - x0;}
-			if (y0 as i16) < bottom_bound && bottom_bound as u16 - y0 > bottom {bottom = bottom_bound as u16 - y0;}
+			let (x0, y0) = ((pos.0 - (sz - 1) / 2) as i32, (pos.1 - (sz - 1) / 2) as i32);
+			let (x1, y1) = ((pos.0 + sz / 2) as i32, (pos.1 + sz / 2) as i32);
+			if x1 > right_bound {right = right.max((x1 - right_bound) as u16);}
+			if y1 > top_bound {top = top.max((y1 - top_bound) as u16);}
+			if x0 < left_bound {left = left.max((left_bound - x0) as u16);}
+			if y0 < bottom_bound {bottom = bottom.max((bottom_bound - y0) as u16);}
 		}
-		if left > 0 || top > 0 || left > 0 || bottom > 0
+		if right > 0 || top > 0 || left > 0 || bottom > 0
 		{
 			return Err(ResizeError::Truncated{right, top, left, bottom});
 		}
@@ -476,6 +599,7 @@ impl<'l> Schematic<'l>
 			pos.0 = (pos.0 as i16 + dx) as u16;
 			pos.1 = (pos.1 as i16 + dy) as u16;
 		}
+		self.rebuild_lookup();
 		Ok(())
 	}
 	
@@ -484,6 +608,48 @@ impl<'l> Schematic<'l>
 		self.mirror(true, true);
 	}
 	
+	// blocks sorted by position relative to the origin, rotation is skipped for symmetric blocks unless exact
+	fn hash_blocks<H: Hasher>(&self, state: &mut H, origin: (u16, u16), exact: bool)
+	{
+		let mut blocks = Vec::from_iter(self.blocks.iter());
+		blocks.sort_by_key(|p| (p.pos.1, p.pos.0));
+		state.write(&(blocks.len() as u32).to_le_bytes());
+		for p in blocks
+		{
+			p.hash_at(state, GridPos(p.pos.0 - origin.0, p.pos.1 - origin.1), exact || !p.block.is_symmetric());
+		}
+	}
+	
+	// a hash of the size, tags and blocks which is stable across platforms and versions
+	pub fn content_hash(&self) -> u64
+	{
+		let mut hasher = Fnv::new();
+		self.hash(&mut hasher);
+		hasher.finish()
+	}
+	
+	// a hash of only the blocks which is the same no matter where in the schematic they are and how they're rotated or
+	// mirrored, to find duplicate designs
+	pub fn shape_hash(&self) -> u64
+	{
+		let mut curr = self.clone();
+		let mut best = u64::MAX;
+		for i in 0..8
+		{
+			if i == 4 {curr.mirror(true, false);}
+			let origin = curr.blocks.iter().fold((u16::MAX, u16::MAX), |(x, y), p|
+			{
+				let off = (p.block.get_size() as u16 - 1) / 2;
+				(x.min(p.pos.0 - off), y.min(p.pos.1 - off))
+			});
+			let mut hasher = Fnv::new();
+			curr.hash_blocks(&mut hasher, origin, false);
+			best = best.min(hasher.finish());
+			curr.rotate(true);
+		}
+		best
+	}
+	
 	pub fn pos_iter(&self) -> PosIter
 	{
 		PosIter{x: 0, y: 0, w: self.width, h: self.height}
@@ -508,6 +674,102 @@ impl<'l> Schematic<'l>
 		}
 		(cost, sandbox)
 	}
+	
+	// swaps every placement of one block for another, keeping position, rotation and (if possible) configuration
+	pub fn replace_all(&mut self, from: &Block, to: &'l Block, region: Option<(u16, u16, u16, u16)>) -> ReplaceSummary
+	{
+		let cost_before = self.compute_total_cost().0;
+		let mut replaced = 0usize;
+		let mut reset = Vec::new();
+		let mut skipped = Vec::new();
+		if from.get_name() != to.get_name()
+		{
+			for idx in 0..self.blocks.len()
+			{
+				let curr = &self.blocks[idx];
+				if curr.block.get_name() != from.get_name() {continue;}
+				let GridPos(x, y) = curr.pos;
+				if let Some((rx, ry, rw, rh)) = region
+				{
+					if x < rx || y < ry || x - rx >= rw || y - ry >= rh {continue;}
+				}
+				match self.replace_idx(idx, to)
+				{
+					None => skipped.push(GridPos(x, y)),
+					Some(was_reset) =>
+					{
+						if was_reset {reset.push(GridPos(x, y));}
+						replaced += 1;
+					},
+				}
+			}
+		}
+		let cost_after = if replaced > 0 {self.compute_total_cost().0} else {cost_before.clone()};
+		ReplaceSummary{replaced, reset, skipped, cost_before, cost_after}
+	}
+	
+	// swaps the block at a position for another like replace_all, returning whether its configuration had to be reset
+	// or None if the new block doesn't fit there (or there is no block)
+	pub fn replace_at(&mut self, x: u16, y: u16, to: &'l Block) -> Result<Option<bool>, PosError>
+	{
+		if x >= self.width || y >= self.height
+		{
+			return Err(PosError{x, y, w: self.width, h: self.height});
+		}
+		if self.blocks.is_empty() {return Ok(None);}
+		match self.lookup[(x as usize) + (y as usize) * (self.width as usize)]
+		{
+			Some(idx) if self.blocks[idx].block.get_name() != to.get_name() => Ok(self.replace_idx(idx, to)),
+			_ => Ok(None),
+		}
+	}
+	
+	fn replace_idx(&mut self, idx: usize, to: &'l Block) -> Option<bool>
+	{
+		let sz = to.get_size() as usize;
+		let off = (sz - 1) / 2;
+		let curr = &self.blocks[idx];
+		let GridPos(x, y) = curr.pos;
+		// the new footprint must be within bounds and may only overlap the block it replaces
+		let (x, y) = (x as usize, y as usize);
+		let fits = x >= off && y >= off && x - off + sz <= self.width as usize && y - off + sz <= self.height as usize
+			&& (0..sz).all(|dy| (0..sz).all(|dx|
+			{
+				match self.lookup[(x - off + dx) + (y - off + dy) * (self.width as usize)]
+				{
+					None => true,
+					Some(other) => other == idx,
+				}
+			}));
+		if !fits {return None;}
+		let state = match curr.state
+		{
+			None => None,
+			Some(ref state) => curr.block.serialize_state(state.as_ref()).ok().and_then(|data| to.deserialize_state(data).ok()),
+		};
+		let (state, was_reset) = match state
+		{
+			Some(state) => (state, false),
+			None => (to.deserialize_state(DynData::Empty).ok()?, curr.state.is_some()),
+		};
+		let rot = curr.rot;
+		let prev = std::mem::replace(&mut self.blocks[idx], Placement{pos: GridPos(x as u16, y as u16), block: to, state, rot});
+		self.fill_lookup(x, y, prev.block.get_size() as usize, None);
+		self.fill_lookup(x, y, sz, Some(idx));
+		Some(was_reset)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplaceSummary
+{
+	pub replaced: usize,
+	// placements whose configuration was not compatible with the new block
+	pub reset: Vec<GridPos>,
+	// placements that could not be replaced because the new block doesn't fit
+	pub skipped: Vec<GridPos>,
+	pub cost_before: ItemStorage,
+	pub cost_after: ItemStorage,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -531,6 +793,47 @@ impl fmt::Display for NewError
 
 impl Error for NewError {}
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LabelError
+{
+	Json(json::ParseError),
+	NotArray,
+	Element(usize),
+}
+
+impl From<json::ParseError> for LabelError
+{
+	fn from(value: json::ParseError) -> Self
+	{
+		Self::Json(value)
+	}
+}
+
+impl fmt::Display for LabelError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Json(..) => f.write_str("malformed label list"),
+			Self::NotArray => f.write_str("label list is not an array"),
+			Self::Element(idx) => write!(f, "label #{idx} is not a string"),
+		}
+	}
+}
+
+impl Error for LabelError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Json(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PosError
 {
@@ -609,8 +912,8 @@ impl fmt::Display for ResizeError
 		{
 			Self::TargetWidth(w) => write!(f, "invalid target width ({w})"),
 			Self::TargetHeight(w) => write!(f, "invalid target height ({w})"),
-			Self::XOffset{dx, old_w, new_w} => write!(f, "horizontal offset {dx} not in [-{new_w}, {old_w}]"),
-			Self::YOffset{dy, old_h, new_h} => write!(f, "vertical offset {dy} not in [-{new_h}, {old_h}]"),
+			Self::XOffset{dx, old_w, new_w} => write!(f, "horizontal offset {dx} not in ]-{old_w}, {new_w}["),
+			Self::YOffset{dy, old_h, new_h} => write!(f, "vertical offset {dy} not in ]-{old_h}, {new_h}["),
 			Self::Truncated{right, top, left, bottom} =>
 			{
 				macro_rules!fmt_dir
@@ -645,6 +948,35 @@ impl fmt::Display for ResizeError
 
 impl Error for ResizeError {}
 
+impl<'l> PartialEq for Schematic<'l>
+{
+	fn eq(&self, other: &Self) -> bool
+	{
+		self.width == other.width && self.height == other.height && self.tags == other.tags && self.blocks.len() == other.blocks.len()
+			&& self.blocks.iter().all(|p| other.get(p.pos.0, p.pos.1).ok().flatten().is_some_and(|q| p == q))
+	}
+}
+
+impl<'l> Eq for Schematic<'l> {}
+
+impl<'l> Hash for Schematic<'l>
+{
+	fn hash<H: Hasher>(&self, state: &mut H)
+	{
+		state.write(&self.width.to_le_bytes());
+		state.write(&self.height.to_le_bytes());
+		let mut tags = Vec::from_iter(self.tags.iter());
+		tags.sort();
+		state.write(&(tags.len() as u32).to_le_bytes());
+		for (k, v) in tags
+		{
+			hash_bytes(state, k.as_bytes());
+			hash_bytes(state, v.as_bytes());
+		}
+		self.hash_blocks(state, (0, 0), true);
+	}
+}
+
 impl<'l> fmt::Display for Schematic<'l>
 {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
@@ -951,7 +1283,10 @@ impl<'l> Serializer<Schematic<'l>> for SchematicSerializer<'l>
 			return Err(WriteError::TagCount(data.tags.len()));
 		}
 		rbuff.write_u8(data.tags.len() as u8)?;
-		for (k, v) in data.tags.iter()
+		// tags and blocks are sorted so that equal schematics always produce the same output
+		let mut tags = Vec::from_iter(data.tags.iter());
+		tags.sort();
+		for (k, v) in tags
 		{
 			rbuff.write_utf(k)?;
 			rbuff.write_utf(v)?;
@@ -959,7 +1294,9 @@ impl<'l> Serializer<Schematic<'l>> for SchematicSerializer<'l>
 		// use string keys here to avoid issues with different block refs with the same name
 		let mut block_map = HashMap::<&str, u32>::new();
 		let mut block_table = Vec::<&str>::new();
-		for curr in data.blocks.iter()
+		let mut blocks = Vec::from_iter(data.blocks.iter());
+		blocks.sort_by_key(|p| (p.pos.1, p.pos.0));
+		for curr in blocks.iter()
 		{
 			match block_map.entry(curr.block.get_name())
 			{
@@ -984,7 +1321,7 @@ impl<'l> Serializer<Schematic<'l>> for SchematicSerializer<'l>
 		// don't have to check data.blocks.len() because dimensions don't allow exceeding MAX_BLOCKS
 		rbuff.write_i32(data.blocks.len() as i32)?;
 		let mut num = 0;
-		for curr in data.blocks.iter()
+		for curr in blocks
 		{
 			rbuff.write_i8(block_map[curr.block.get_name()] as i8)?;
 			rbuff.write_u32(u32::from(curr.pos))?;
@@ -1005,7 +1342,8 @@ impl<'l> Serializer<Schematic<'l>> for SchematicSerializer<'l>
 			data::WriteBuff::Vec(v) => v,
 			_ => unreachable!("write buffer not owned"),
 		};
-		let mut comp = Compress::new(Compression::default(), true);
+		// fixed level instead of the default, which could change between versions of the compression library
+		let mut comp = Compress::new(Compression::new(6), true);
 		// compress the immediate buffer into a temp buffer to copy it to buff? no thanks
 		match buff.data
 		{
@@ -1407,6 +1745,8 @@ impl FusedIterator for PosIter {}
 mod test
 {
 	use super::*;
+	use crate::content;
+	use crate::item;
 	
 	macro_rules!test_iter
 	{
@@ -1434,4 +1774,143 @@ mod test
 	
 	test_iter!(block_iter, Schematic::new(3, 4).pos_iter(), Some(GridPos(0, 0)), Some(GridPos(1, 0)), Some(GridPos(2, 0)),
 		Some(GridPos(0, 1)), 7, Some(GridPos(2, 3)), None);
+	
+	#[test]
+	fn labels()
+	{
+		let mut schematic = Schematic::new(1, 1);
+		assert_eq!(schematic.get_labels(), Ok(Vec::new()));
+		schematic.set_labels(&["power", "say \"hi\""]);
+		assert_eq!(schematic.get_tags()["labels"], r#"["power","say \"hi\""]"#);
+		assert_eq!(schematic.get_labels(), Ok(vec!["power".to_string(), "say \"hi\"".to_string()]));
+		// the game may write labels without quotes
+		schematic.get_tags_mut().insert("labels".to_string(), "[defense, silicon smelter]".to_string());
+		assert_eq!(schematic.get_labels(), Ok(vec!["defense".to_string(), "silicon smelter".to_string()]));
+		schematic.get_tags_mut().insert("labels".to_string(), "{}".to_string());
+		assert_eq!(schematic.get_labels(), Err(LabelError::NotArray));
+	}
+	
+	#[test]
+	fn replace()
+	{
+		let mut schematic = Schematic::new(4, 4);
+		let prev = schematic.replace(1, 1, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right, true).unwrap();
+		assert_eq!(prev.map(|v| v.len()), Some(0));
+		schematic.set(3, 3, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		// the 3x3 core centered on (2, 2) covers both walls, but not (0, 0)
+		schematic.set(0, 3, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		let prev = schematic.replace(2, 2, &block::base::CORE_SHARD, DynData::Empty, Rotation::Right, true).unwrap();
+		assert_eq!(prev.map(|v| v.len()), Some(2));
+		assert_eq!(schematic.get_block_count(), 2);
+		assert!(schematic.get(1, 1).unwrap().is_some());
+		assert!(schematic.get(0, 3).unwrap().is_some());
+		assert!(schematic.get(0, 0).unwrap().is_none());
+	}
+	
+	#[test]
+	fn replace_at()
+	{
+		let mut schematic = Schematic::new(3, 3);
+		schematic.set(1, 1, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(2, 2, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		// the large wall would cover the other one
+		assert_eq!(schematic.replace_at(1, 1, &block::defense::COPPER_WALL_LARGE), Ok(None));
+		assert_eq!(schematic.replace_at(2, 2, &block::defense::TITANIUM_WALL), Ok(Some(false)));
+		assert_eq!(schematic.replace_at(0, 0, &block::defense::TITANIUM_WALL), Ok(None));
+		assert_eq!(schematic.get(2, 2).unwrap().unwrap().get_block().get_name(), "titanium-wall");
+		assert_eq!(schematic.get(1, 1).unwrap().unwrap().get_block().get_name(), "copper-wall");
+	}
+	
+	#[test]
+	fn replace_all()
+	{
+		let mut schematic = Schematic::new(4, 4);
+		schematic.set(0, 0, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(3, 3, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(1, 0, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(2, 2, &block::transport::SORTER, DynData::Content(content::Type::Item, 0), Rotation::Up).unwrap();
+		let summary = schematic.replace_all(&block::defense::COPPER_WALL, &block::defense::TITANIUM_WALL, Some((0, 0, 2, 2)));
+		assert_eq!(summary.replaced, 2);
+		assert_eq!(summary.cost_before.get(item::Type::Copper), 3 * 6 + 2);
+		assert_eq!(summary.cost_after.get(item::Type::Copper), 6 + 2);
+		assert_eq!(summary.cost_after.get(item::Type::Titanium), 12);
+		assert_eq!(schematic.get(3, 3).unwrap().unwrap().get_block().get_name(), "copper-wall");
+		// the large wall at (0, 0) would overlap the one at (1, 0), which has enough space
+		let summary = schematic.replace_all(&block::defense::TITANIUM_WALL, &block::defense::COPPER_WALL_LARGE, None);
+		assert_eq!((summary.replaced, summary.skipped), (1, vec![GridPos(0, 0)]));
+		assert_eq!(schematic.get(2, 1).unwrap().unwrap().get_block().get_name(), "copper-wall-large");
+		let summary = schematic.replace_all(&block::transport::SORTER, &block::transport::INVERTED_SORTER, None);
+		assert_eq!((summary.replaced, summary.reset.len()), (1, 0));
+		let sorter = schematic.get(2, 2).unwrap().unwrap();
+		assert_eq!(sorter.get_rotation(), Rotation::Up);
+		assert_eq!(sorter.get_block().serialize_state(sorter.get_state().unwrap()).unwrap(), DynData::Content(content::Type::Item, 0));
+	}
+	
+	#[test]
+	fn resize()
+	{
+		let mut schematic = Schematic::new(4, 4);
+		schematic.set(1, 1, &block::defense::COPPER_WALL_LARGE, DynData::Empty, Rotation::Right).unwrap();
+		assert_eq!(schematic.resize(1, 1, 4, 4), Ok(()));
+		assert!(schematic.get(2, 2).unwrap().is_some());
+		assert!(schematic.get(1, 1).unwrap().is_none());
+		assert_eq!(schematic.resize(1, 0, 4, 4), Err(ResizeError::Truncated{right: 1, top: 0, left: 0, bottom: 0}));
+		assert_eq!(schematic.resize(-2, -2, 2, 2), Ok(()));
+		assert!(schematic.get(1, 1).unwrap().is_some());
+		assert_eq!(schematic.resize(2, 0, 2, 2), Err(ResizeError::XOffset{dx: 2, old_w: 2, new_w: 2}));
+	}
+	
+	#[test]
+	fn clone_state()
+	{
+		let mut schematic = Schematic::new(1, 1);
+		schematic.set(0, 0, &block::transport::SORTER, DynData::Content(content::Type::Item, 1), Rotation::Right).unwrap();
+		let copy = schematic.clone();
+		let sorter = copy.get(0, 0).unwrap().unwrap();
+		assert_eq!(sorter.get_block().serialize_state(sorter.get_state().unwrap()).unwrap(), DynData::Content(content::Type::Item, 1));
+	}
+	
+	#[test]
+	fn canonical()
+	{
+		let reg = block::build_registry();
+		let mut first = Schematic::new(4, 4);
+		first.set(0, 0, &block::defense::COPPER_WALL, DynData::Empty, Rotation::Right).unwrap();
+		first.set(2, 2, &block::defense::COPPER_WALL_LARGE, DynData::Empty, Rotation::Right).unwrap();
+		first.set(3, 0, &block::defense::TITANIUM_WALL, DynData::Empty, Rotation::Right).unwrap();
+		// removing a block reorders the remaining ones internally
+		first.take(0, 0).unwrap();
+		let mut second = Schematic::new(4, 4);
+		second.set(2, 2, &block::defense::COPPER_WALL_LARGE, DynData::Empty, Rotation::Right).unwrap();
+		second.set(3, 0, &block::defense::TITANIUM_WALL, DynData::Empty, Rotation::Right).unwrap();
+		second.get_tags_mut().insert("zzz".to_string(), "last".to_string());
+		first.get_tags_mut().insert("zzz".to_string(), "last".to_string());
+		let mut ss = SchematicSerializer(&reg);
+		assert_eq!(ss.serialize_base64(&first).unwrap(), ss.serialize_base64(&second).unwrap());
+	}
+	
+	#[test]
+	fn equality()
+	{
+		let mut first = Schematic::new(5, 5);
+		first.set(0, 0, &block::transport::CONVEYOR, DynData::Empty, Rotation::Right).unwrap();
+		first.set(1, 0, &block::defense::COPPER_WALL_LARGE, DynData::Empty, Rotation::Right).unwrap();
+		let mut second = Schematic::new(5, 5);
+		second.set(1, 0, &block::defense::COPPER_WALL_LARGE, DynData::Empty, Rotation::Right).unwrap();
+		second.set(0, 0, &block::transport::CONVEYOR, DynData::Empty, Rotation::Right).unwrap();
+		assert!(first == second);
+		assert_eq!(first.content_hash(), second.content_hash());
+		// the same design moved and turned around
+		second.resize(2, 1, 5, 5).unwrap();
+		second.rotate(false);
+		second.mirror(false, true);
+		assert!(first != second);
+		assert_ne!(first.content_hash(), second.content_hash());
+		assert_eq!(first.shape_hash(), second.shape_hash());
+		// but not if the conveyor turns away from the wall
+		let pos = second.block_iter().find(|p| !p.get_block().is_symmetric()).unwrap().get_pos();
+		let conveyor = second.get_mut(pos.0, pos.1).unwrap().unwrap();
+		conveyor.set_rotation(conveyor.get_rotation().rotated(true).rotated(true));
+		assert_ne!(first.shape_hash(), second.shape_hash());
+	}
 }
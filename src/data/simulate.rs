@@ -0,0 +1,630 @@
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use crate::block::{Block, Rotation};
+use crate::data::GridPos;
+use crate::data::flow::{offset, CONVEYORS, DIRECTIONS, NO_ITEMS};
+use crate::data::schematic::Schematic;
+use crate::item;
+use crate::registry::RegistryEntry;
+
+const TICKS_PER_SECOND: f32 = 60.0;
+// how often a mass driver fires, and how many items it waits for
+const DRIVER_RELOAD: u32 = 200;
+const DRIVER_MIN_ITEMS: usize = 10;
+const ITEM_SOURCE_RATE: f32 = 100.0;
+const UNLOADER_RATE: f32 = 11.0;
+// how many items a source holds on to while it can't pass them on, like the output of a factory
+const SOURCE_BUFFER: f32 = 10.0;
+// in items per second, a source can't hand out more than its buffer every tick
+pub const MAX_SOURCE_RATE: f32 = SOURCE_BUFFER * TICKS_PER_SECOND;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Source
+{
+	pub pos: (u16, u16),
+	pub item: item::Type,
+	// in items per second
+	pub rate: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationOptions
+{
+	// in addition to the item sources and unloaders that have an item configured
+	pub sources: Vec<Source>,
+	// ticks to run before counting, so that the conveyors fill up
+	pub warmup: u32,
+	// ticks to count the delivered items for
+	pub ticks: u32,
+}
+
+impl Default for SimulationOptions
+{
+	fn default() -> Self
+	{
+		Self{sources: Vec::new(), warmup: 10 * TICKS_PER_SECOND as u32, ticks: 60 * TICKS_PER_SECOND as u32}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Sink<'l>
+{
+	Block{pos: GridPos, block: &'l Block},
+	// items leaving the schematic, the position is that of the last block they passed
+	Edge{pos: GridPos, block: &'l Block},
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Delivery<'l>
+{
+	pub sink: Sink<'l>,
+	pub item: item::Type,
+	// in items per second
+	pub rate: f32,
+}
+
+impl<'l> fmt::Display for Delivery<'l>
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self.sink
+		{
+			Sink::Block{pos, block} => write!(f, "{} into {} at {} / {}", self.item, block.get_name(), pos.0, pos.1)?,
+			Sink::Edge{pos, block} => write!(f, "{} out of the schematic from {} at {} / {}", self.item, block.get_name(), pos.0, pos.1)?,
+		}
+		write!(f, ": {:.2} / s", self.rate)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SimulateError
+{
+	OutOfBounds{x: u16, y: u16},
+	Empty{x: u16, y: u16},
+	// a junction, sorter or gate, which need to know which way the items are moving
+	NotSource{x: u16, y: u16},
+	// not a number, not positive or above MAX_SOURCE_RATE
+	InvalidRate{x: u16, y: u16},
+}
+
+impl fmt::Display for SimulateError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::OutOfBounds{x, y} => write!(f, "source {x} / {y} is outside of the schematic"),
+			Self::Empty{x, y} => write!(f, "no block at {x} / {y} to produce items"),
+			Self::NotSource{x, y} => write!(f, "the block at {x} / {y} can't produce items, it only passes them on"),
+			Self::InvalidRate{x, y} => write!(f, "the rate of source {x} / {y} must be above 0 and at most {MAX_SOURCE_RATE} items per second"),
+		}
+	}
+}
+
+impl Error for SimulateError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Logic
+{
+	Conveyor(Rotation),
+	Junction,
+	Router,
+	Sorter{item: Option<item::Type>, invert: bool},
+	// overflow gates pass items forward if they can, underflow gates only if they can't to either side
+	Gate{invert: bool},
+	// the index of the linked bridge or mass driver, if any
+	Bridge(Option<usize>),
+	Driver(Option<usize>),
+	// takes any amount of items
+	Sink,
+	// takes no items at all
+	Wall,
+}
+
+impl Logic
+{
+	// sorters and gates pass items on right away, but not to one another
+	fn is_instant(self) -> bool
+	{
+		matches!(self, Logic::Sorter{..} | Logic::Gate{..})
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Packet
+{
+	item: item::Type,
+	from: Option<usize>,
+	// the direction the item was moving in when it entered
+	dir: Rotation,
+}
+
+struct Unit<'l>
+{
+	pos: GridPos,
+	block: &'l Block,
+	logic: Logic,
+	// items per tick, junctions move this many per lane
+	speed: f32,
+	capacity: usize,
+	// per lane for junctions
+	progress: [f32; 4],
+	items: VecDeque<Packet>,
+	// the next neighbor to try for routers, the next side for sorters and gates
+	turn: usize,
+	reload: u32,
+	// neighbors in every direction that the items can be passed to
+	adjacent: Vec<(usize, Rotation)>,
+	// directions bridge ends don't output to, because bridges are linked to them from there
+	closed: [bool; 4],
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Target
+{
+	Unit(usize, Rotation),
+	Edge,
+}
+
+struct Emitter
+{
+	unit: usize,
+	item: item::Type,
+	// items per tick
+	rate: f32,
+	progress: f32,
+	turn: usize,
+	// put the items into the block instead of next to it
+	inject: bool,
+	// unloaders don't put items back into containers
+	skip_sinks: bool,
+}
+
+struct Simulation<'l>
+{
+	width: i32,
+	height: i32,
+	grid: Vec<Option<usize>>,
+	units: Vec<Unit<'l>>,
+	counting: bool,
+	// by unit, whether the items left the schematic and item
+	counts: BTreeMap<(usize, bool, item::Type), u32>,
+}
+
+fn dir_index(dir: Rotation) -> usize
+{
+	DIRECTIONS.iter().position(|&d| d == dir).unwrap()
+}
+
+impl<'l> Simulation<'l>
+{
+	// None outside the schematic, Some(None) for empty tiles
+	fn tile(&self, x: i32, y: i32) -> Option<Option<usize>>
+	{
+		(x >= 0 && y >= 0 && x < self.width && y < self.height).then(|| self.grid[(y * self.width + x) as usize])
+	}
+	
+	fn neighbor(&self, i: usize, dir: Rotation) -> Option<Option<usize>>
+	{
+		let (pos, (dx, dy)) = (self.units[i].pos, offset(dir));
+		self.tile(pos.0 as i32 + dx, pos.1 as i32 + dy)
+	}
+	
+	fn accepts(&self, j: usize, dir: Rotation, from: Option<usize>) -> bool
+	{
+		let unit = &self.units[j];
+		match unit.logic
+		{
+			Logic::Conveyor(rot) => rot != dir.rotated_180() && unit.items.len() < unit.capacity,
+			Logic::Junction => unit.items.iter().filter(|p| p.dir == dir).count() < unit.capacity,
+			Logic::Sorter{..} | Logic::Gate{..} => unit.items.len() < unit.capacity && !from.is_some_and(|f| self.units[f].logic.is_instant()),
+			Logic::Bridge(link) =>
+			{
+				// bridges take items from the bridges linked to them, linked ones also from any side but the one facing their target
+				let linked = from.is_some_and(|f| self.units[f].logic == Logic::Bridge(Some(j)));
+				let open = link.is_some_and(|k|
+				{
+					let (a, b) = (unit.pos, self.units[k].pos);
+					((b.0 as i32 - a.0 as i32).signum(), (b.1 as i32 - a.1 as i32).signum()) != offset(dir.rotated_180())
+				});
+				unit.items.len() < unit.capacity && (linked || open)
+			},
+			// unlinked mass drivers only receive shots
+			Logic::Router | Logic::Driver(Some(..)) => unit.items.len() < unit.capacity,
+			Logic::Driver(None) | Logic::Wall => false,
+			Logic::Sink => true,
+		}
+	}
+	
+	fn ahead(&self, i: usize, dir: Rotation) -> Option<Target>
+	{
+		match self.neighbor(i, dir)
+		{
+			Some(Some(j)) if self.accepts(j, dir, Some(i)) => Some(Target::Unit(j, dir)),
+			_ => None,
+		}
+	}
+	
+	// tries the left and right side in turns
+	fn sides(&self, i: usize, dir: Rotation, turn: usize) -> Option<(Target, usize)>
+	{
+		let index = dir_index(dir);
+		let sides = [DIRECTIONS[(index + 1) % 4], DIRECTIONS[(index + 3) % 4]];
+		(0..2).find_map(|k| self.ahead(i, sides[(turn + k) % 2]).map(|t| (t, turn + k + 1)))
+	}
+	
+	// passes items to the neighbors in turns, other than the one they came from
+	fn dump(&self, i: usize, from: Option<usize>, turn: usize, skip_sinks: bool) -> Option<(Target, usize)>
+	{
+		let unit = &self.units[i];
+		let count = unit.adjacent.len();
+		(0..count).find_map(|k|
+		{
+			let (j, dir) = unit.adjacent[(turn + k) % count];
+			if Some(j) == from || unit.closed[dir_index(dir)] || (skip_sinks && self.units[j].logic == Logic::Sink) {return None;}
+			self.accepts(j, dir, Some(i)).then_some((Target::Unit(j, dir), turn + k + 1))
+		})
+	}
+	
+	// where the next item of the unit (or one of its lanes) goes, with the item's index and the unit's next turn
+	fn pick(&self, i: usize, lane: Option<Rotation>) -> Option<(usize, Target, usize)>
+	{
+		let unit = &self.units[i];
+		let index = match lane
+		{
+			Some(dir) => unit.items.iter().position(|p| p.dir == dir)?,
+			None if unit.items.is_empty() => return None,
+			None => 0,
+		};
+		let packet = unit.items[index];
+		let (target, turn) = match unit.logic
+		{
+			Logic::Conveyor(..) | Logic::Junction =>
+			{
+				let dir = if let Logic::Conveyor(rot) = unit.logic {rot} else {packet.dir};
+				let target = match self.neighbor(i, dir)
+				{
+					None => Target::Edge,
+					_ => self.ahead(i, dir)?,
+				};
+				(target, unit.turn)
+			},
+			Logic::Sorter{item, invert} =>
+			{
+				if (Some(packet.item) == item) != invert {(self.ahead(i, packet.dir)?, unit.turn)}
+				else {self.sides(i, packet.dir, unit.turn)?}
+			},
+			Logic::Gate{invert} =>
+			{
+				let ahead = self.ahead(i, packet.dir);
+				match ahead
+				{
+					Some(t) if !invert => (t, unit.turn),
+					_ => self.sides(i, packet.dir, unit.turn).or_else(|| ahead.map(|t| (t, unit.turn)))?,
+				}
+			},
+			Logic::Bridge(Some(j)) =>
+			{
+				if !self.accepts(j, packet.dir, Some(i)) {return None;}
+				(Target::Unit(j, packet.dir), unit.turn)
+			},
+			Logic::Router | Logic::Bridge(None) | Logic::Driver(None) => self.dump(i, packet.from, unit.turn, false)?,
+			Logic::Driver(Some(..)) | Logic::Sink | Logic::Wall => return None,
+		};
+		Some((index, target, turn))
+	}
+	
+	fn count(&mut self, i: usize, edge: bool, item: item::Type)
+	{
+		if self.counting {*self.counts.entry((i, edge, item)).or_default() += 1;}
+	}
+	
+	fn receive(&mut self, j: usize, packet: Packet)
+	{
+		if self.units[j].logic == Logic::Sink {self.count(j, false, packet.item);}
+		else {self.units[j].items.push_back(packet);}
+	}
+	
+	fn step(&mut self, i: usize, lane: usize)
+	{
+		let unit = &mut self.units[i];
+		unit.progress[lane] += unit.speed;
+		while self.units[i].progress[lane] >= 1.0
+		{
+			let dir = (self.units[i].logic == Logic::Junction).then_some(DIRECTIONS[lane]);
+			let Some((index, target, turn)) = self.pick(i, dir)
+			else
+			{
+				// blocks that are stuck don't save up moves for later
+				let unit = &mut self.units[i];
+				unit.progress[lane] = unit.progress[lane].min(unit.speed.max(1.0));
+				break;
+			};
+			let packet = self.units[i].items.remove(index).unwrap();
+			self.units[i].turn = turn;
+			self.units[i].progress[lane] -= 1.0;
+			match target
+			{
+				Target::Edge => self.count(i, true, packet.item),
+				Target::Unit(j, dir) => self.receive(j, Packet{item: packet.item, from: Some(i), dir}),
+			}
+		}
+	}
+	
+	fn fire(&mut self, i: usize, j: usize)
+	{
+		let unit = &mut self.units[i];
+		if unit.reload > 0
+		{
+			unit.reload -= 1;
+			return;
+		}
+		if unit.items.len() < DRIVER_MIN_ITEMS {return;}
+		let space = self.units[j].capacity.saturating_sub(self.units[j].items.len());
+		let count = space.min(self.units[i].items.len());
+		if count == 0 {return;}
+		let shot = Vec::from_iter(self.units[i].items.drain(..count));
+		self.units[i].reload = DRIVER_RELOAD;
+		for packet in shot
+		{
+			self.units[j].items.push_back(Packet{item: packet.item, from: Some(i), dir: packet.dir});
+		}
+	}
+	
+	fn emit(&mut self, emitter: &mut Emitter)
+	{
+		emitter.progress = (emitter.progress + emitter.rate).min(SOURCE_BUFFER);
+		let mut sent = 0;
+		for _ in 0..emitter.progress.floor() as u32
+		{
+			let i = emitter.unit;
+			if emitter.inject
+			{
+				let dir = if let Logic::Conveyor(rot) = self.units[i].logic {rot} else {Rotation::Right};
+				if self.units[i].items.len() >= self.units[i].capacity {break;}
+				self.receive(i, Packet{item: emitter.item, from: None, dir});
+			}
+			else
+			{
+				let Some((Target::Unit(j, dir), turn)) = self.dump(i, None, emitter.turn, emitter.skip_sinks)
+				else {break;};
+				emitter.turn = turn;
+				self.receive(j, Packet{item: emitter.item, from: Some(i), dir});
+			}
+			sent += 1;
+		}
+		emitter.progress -= sent as f32;
+	}
+}
+
+fn get_item(state: Option<&dyn Any>) -> Option<item::Type>
+{
+	state.and_then(|s| s.downcast_ref::<Option<item::Type>>()).copied().flatten()
+}
+
+// moves items tick by tick through conveyors, junctions, routers, sorters, gates, bridges and mass drivers, and counts
+// what arrives in every other block that takes items, or leaves the schematic on a conveyor
+pub fn simulate<'l>(schematic: &Schematic<'l>, options: &SimulationOptions) -> Result<Vec<Delivery<'l>>, SimulateError>
+{
+	let (width, height) = (schematic.get_width() as i32, schematic.get_height() as i32);
+	let mut sim = Simulation{width, height, grid: vec![None; (width * height) as usize], units: Vec::new(), counting: false, counts: BTreeMap::new()};
+	let mut links = Vec::new();
+	let mut emitters = Vec::new();
+	for p in schematic.block_iter()
+	{
+		let (pos, block, size) = (p.get_pos(), p.get_block(), p.get_block().get_size() as u16);
+		let left = (pos.0 - (size - 1) / 2, pos.1 - (size - 1) / 2);
+		for y in left.1..left.1 + size
+		{
+			for x in left.0..left.0 + size
+			{
+				sim.grid[(y as i32 * width + x as i32) as usize] = Some(sim.units.len());
+			}
+		}
+		let name = block.get_name();
+		let (logic, speed, capacity) = match name
+		{
+			"conveyor" => (Logic::Conveyor(p.get_rotation()), 8.0, 4),
+			"plastanium-conveyor" => (Logic::Conveyor(p.get_rotation()), 40.0, 10),
+			_ if CONVEYORS.contains(&name) => (Logic::Conveyor(p.get_rotation()), 11.0, 4),
+			"junction" => (Logic::Junction, 14.0, 6),
+			"router" | "distributor" => (Logic::Router, 7.5, 1),
+			"sorter" | "inverted-sorter" => (Logic::Sorter{item: get_item(p.get_state()), invert: name == "inverted-sorter"}, TICKS_PER_SECOND, 1),
+			"overflow-gate" | "underflow-gate" => (Logic::Gate{invert: name == "underflow-gate"}, TICKS_PER_SECOND, 1),
+			"bridge-conveyor" => (Logic::Bridge(None), 13.0, 10),
+			"phase-conveyor" => (Logic::Bridge(None), 30.0, 10),
+			"mass-driver" => (Logic::Driver(None), TICKS_PER_SECOND, 120),
+			_ if name.contains("wall") || NO_ITEMS.contains(&name) => (Logic::Wall, 0.0, 0),
+			_ => (Logic::Sink, 0.0, 0),
+		};
+		if matches!(logic, Logic::Bridge(..) | Logic::Driver(..))
+		{
+			if let Some(&(dx, dy)) = p.get_state().and_then(|s| s.downcast_ref::<Option<(i32, i32)>>()).and_then(Option::as_ref)
+			{
+				if (dx, dy) != (0, 0) {links.push((sim.units.len(), (pos.0 as i32 + dx, pos.1 as i32 + dy)));}
+			}
+		}
+		let rate = match name
+		{
+			"item-source" => ITEM_SOURCE_RATE,
+			"unloader" => UNLOADER_RATE,
+			_ => 0.0,
+		};
+		if let Some(item) = get_item(p.get_state()).filter(|_| rate > 0.0)
+		{
+			emitters.push(Emitter{unit: sim.units.len(), item, rate: rate / TICKS_PER_SECOND, progress: 0.0, turn: 0, inject: false, skip_sinks: name == "unloader"});
+		}
+		sim.units.push(Unit{pos, block, logic, speed: speed / TICKS_PER_SECOND, capacity, progress: [0.0; 4], items: VecDeque::new(), turn: 0,
+			reload: 0, adjacent: Vec::new(), closed: [false; 4]});
+	}
+	
+	for i in 0..sim.units.len()
+	{
+		let (pos, size) = (sim.units[i].pos, sim.units[i].block.get_size() as i32);
+		let (lx, ly) = (pos.0 as i32 - (size - 1) / 2, pos.1 as i32 - (size - 1) / 2);
+		let mut adjacent = Vec::new();
+		for dir in DIRECTIONS
+		{
+			for k in 0..size
+			{
+				let (x, y) = match dir
+				{
+					Rotation::Right => (lx + size, ly + k),
+					Rotation::Up => (lx + k, ly + size),
+					Rotation::Left => (lx - 1, ly + k),
+					Rotation::Down => (lx + k, ly - 1),
+				};
+				if let Some(Some(j)) = sim.tile(x, y)
+				{
+					if !adjacent.iter().any(|&(a, _)| a == j) {adjacent.push((j, dir));}
+				}
+			}
+		}
+		sim.units[i].adjacent = adjacent;
+	}
+	for (i, target) in links
+	{
+		let Some(Some(j)) = sim.tile(target.0, target.1)
+		else {continue;};
+		let (from, to) = (sim.units[i].pos, sim.units[j].pos);
+		if sim.units[j].block.get_name() != sim.units[i].block.get_name() || (to.0 as i32, to.1 as i32) != target {continue;}
+		sim.units[i].logic = match sim.units[i].logic
+		{
+			Logic::Bridge(..) => Logic::Bridge(Some(j)),
+			_ => Logic::Driver(Some(j)),
+		};
+		let back = ((from.0 as i32 - to.0 as i32).signum(), (from.1 as i32 - to.1 as i32).signum());
+		if let Some(dir) = DIRECTIONS.iter().position(|&d| offset(d) == back) {sim.units[j].closed[dir] = true;}
+	}
+	
+	// the given sources come first, so that they aren't crowded out by item sources
+	let mut given = Vec::new();
+	for source in options.sources.iter()
+	{
+		let (x, y) = source.pos;
+		if !(source.rate > 0.0 && source.rate <= MAX_SOURCE_RATE) {return Err(SimulateError::InvalidRate{x, y});}
+		let Some(tile) = sim.tile(x as i32, y as i32)
+		else {return Err(SimulateError::OutOfBounds{x, y});};
+		let Some(unit) = tile
+		else {return Err(SimulateError::Empty{x, y});};
+		let inject = match sim.units[unit].logic
+		{
+			Logic::Junction | Logic::Sorter{..} | Logic::Gate{..} => return Err(SimulateError::NotSource{x, y}),
+			Logic::Sink | Logic::Wall => false,
+			_ => true,
+		};
+		given.push(Emitter{unit, item: source.item, rate: source.rate / TICKS_PER_SECOND, progress: 0.0, turn: 0, inject, skip_sinks: false});
+	}
+	given.append(&mut emitters);
+	let mut emitters = given;
+	
+	for tick in 0..options.warmup + options.ticks
+	{
+		sim.counting = tick >= options.warmup;
+		for emitter in emitters.iter_mut()
+		{
+			sim.emit(emitter);
+		}
+		for i in 0..sim.units.len()
+		{
+			match sim.units[i].logic
+			{
+				Logic::Driver(Some(j)) => sim.fire(i, j),
+				Logic::Junction => (0..4).for_each(|lane| sim.step(i, lane)),
+				Logic::Sink | Logic::Wall => (),
+				_ => sim.step(i, 0),
+			}
+		}
+	}
+	
+	let seconds = options.ticks as f32 / TICKS_PER_SECOND;
+	Ok(Vec::from_iter(sim.counts.iter().map(|(&(i, edge, item), &count)|
+	{
+		let (pos, block) = (sim.units[i].pos, sim.units[i].block);
+		let sink = if edge {Sink::Edge{pos, block}} else {Sink::Block{pos, block}};
+		Delivery{sink, item, rate: count as f32 / seconds}
+	})))
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	use crate::data::dynamic::DynData;
+	
+	#[test]
+	fn sorting()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(6, 2);
+		let copper = DynData::Content(crate::content::Type::Item, item::Type::Copper.into());
+		let mut place = |x, y, name, data, rot| {schematic.set(x, y, reg.get(name).unwrap(), data, rot).unwrap();};
+		place(0, 0, "item-source", copper.clone(), Rotation::Right);
+		place(1, 0, "conveyor", DynData::Empty, Rotation::Right);
+		place(2, 0, "sorter", copper, Rotation::Right);
+		place(3, 0, "conveyor", DynData::Empty, Rotation::Right);
+		place(4, 0, "container", DynData::Empty, Rotation::Right);
+		// everything else goes up and out of the schematic
+		place(2, 1, "conveyor", DynData::Empty, Rotation::Up);
+		
+		let options = SimulationOptions{sources: vec![Source{pos: (1, 0), item: item::Type::Lead, rate: 3.0}], ..Default::default()};
+		let deliveries = simulate(&schematic, &options).unwrap();
+		assert_eq!(deliveries.len(), 2);
+		// the conveyor moves 8 items per second, the item source fills what the lead leaves
+		assert!(matches!(deliveries[0].sink, Sink::Block{pos: GridPos(4, 0), ..}));
+		assert_eq!(deliveries[0].item, item::Type::Copper);
+		assert!((deliveries[0].rate - 5.0).abs() < 0.2, "{}", deliveries[0]);
+		assert!(matches!(deliveries[1].sink, Sink::Edge{pos: GridPos(2, 1), ..}));
+		assert_eq!(deliveries[1].item, item::Type::Lead);
+		assert!((deliveries[1].rate - 3.0).abs() < 0.2, "{}", deliveries[1]);
+		let options = SimulationOptions{sources: vec![Source{pos: (2, 0), item: item::Type::Lead, rate: 1.0}], ..Default::default()};
+		assert_eq!(simulate(&schematic, &options).unwrap_err(), SimulateError::NotSource{x: 2, y: 0});
+	}
+	
+	#[test]
+	fn bridges()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(8, 3);
+		let copper = DynData::Content(crate::content::Type::Item, item::Type::Copper.into());
+		let mut place = |x, y, name, data: &DynData| {schematic.set(x, y, reg.get(name).unwrap(), data.clone(), Rotation::Right).unwrap();};
+		place(0, 0, "item-source", &copper);
+		place(1, 0, "conveyor", &DynData::Empty);
+		place(2, 0, "bridge-conveyor", &DynData::Point2(3, 0));
+		place(5, 0, "bridge-conveyor", &DynData::Empty);
+		place(6, 0, "item-void", &DynData::Empty);
+		// an unlinked bridge only takes items from bridges linked to it
+		place(0, 2, "item-source", &copper);
+		place(1, 2, "conveyor", &DynData::Empty);
+		place(2, 2, "bridge-conveyor", &DynData::Empty);
+		place(3, 2, "item-void", &DynData::Empty);
+		
+		let deliveries = simulate(&schematic, &SimulationOptions::default()).unwrap();
+		assert_eq!(deliveries.len(), 1);
+		assert!(matches!(deliveries[0].sink, Sink::Block{pos: GridPos(6, 0), ..}));
+		assert!((deliveries[0].rate - 8.0).abs() < 0.2, "{}", deliveries[0]);
+	}
+	
+	#[test]
+	fn rates()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(6, 6);
+		schematic.set(1, 1, reg.get("container").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(3, 1, reg.get("container").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		for rate in [f32::NAN, -1.0, 0.0, MAX_SOURCE_RATE * 2.0, 2e9]
+		{
+			let options = SimulationOptions{sources: vec![Source{pos: (1, 1), item: item::Type::Copper, rate}], ..Default::default()};
+			assert_eq!(simulate(&schematic, &options).unwrap_err(), SimulateError::InvalidRate{x: 1, y: 1});
+		}
+		// a container takes any amount, so the source hands out all it can
+		let options = SimulationOptions{sources: vec![Source{pos: (1, 1), item: item::Type::Copper, rate: MAX_SOURCE_RATE}], ..Default::default()};
+		let deliveries = simulate(&schematic, &options).unwrap();
+		assert_eq!(deliveries.len(), 1);
+		assert!((deliveries[0].rate - MAX_SOURCE_RATE).abs() < 1.0, "{}", deliveries[0]);
+	}
+}
@@ -0,0 +1,168 @@
+use crate::block::{Block, BlockRegistry, upgrade};
+use crate::data::GridPos;
+use crate::data::schematic::Schematic;
+use crate::item::storage::Storage as ItemStorage;
+use crate::registry::RegistryEntry;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Substitution<'l>
+{
+	pub pos: GridPos,
+	pub from: &'l Block,
+	pub to: &'l Block,
+}
+
+#[derive(Clone, Debug)]
+pub struct BudgetSummary<'l>
+{
+	pub changed: Vec<Substitution<'l>>,
+	// the items still missing after all substitutions, empty if the schematic fits the budget
+	pub shortfall: ItemStorage,
+}
+
+fn count_missing(cost: &ItemStorage, budget: &ItemStorage) -> u64
+{
+	cost.iter_nonzero().map(|(ty, cnt)| cnt.saturating_sub(budget.get(ty)) as u64).sum()
+}
+
+// the missing items after swapping one block for another, only looking at the items either of them costs
+fn missing_after(missing: u64, cost: &ItemStorage, budget: &ItemStorage, from: &ItemStorage, to: &ItemStorage) -> u64
+{
+	let lack = |cnt: u64, ty| cnt.saturating_sub(budget.get(ty) as u64);
+	let mut seen = Vec::new();
+	let mut result = missing;
+	for (ty, _) in from.iter_nonzero().chain(to.iter_nonzero())
+	{
+		if seen.contains(&ty) {continue;}
+		seen.push(ty);
+		let curr = cost.get(ty) as u64;
+		let next = curr.saturating_sub(from.get(ty) as u64) + to.get(ty) as u64;
+		result = result - lack(curr, ty) + lack(next, ty);
+	}
+	result
+}
+
+// swaps blocks for cheaper substitutes (one at a time, always picking the one that reduces the missing items the
+// most) until the build cost fits the budget or no substitute helps anymore
+pub fn fit_budget<'l>(schematic: &mut Schematic<'l>, reg: &'l BlockRegistry<'l>, budget: &ItemStorage) -> BudgetSummary<'l>
+{
+	let mut changed = Vec::new();
+	let (mut cost, _) = schematic.compute_total_cost();
+	let mut missing = count_missing(&cost, budget);
+	// how many there are of each block, so that every step only has to look at the kinds of blocks
+	let mut counts = Vec::<(&'l Block, usize)>::new();
+	for p in schematic.block_iter()
+	{
+		match counts.iter_mut().find(|(b, _)| b.get_name() == p.get_block().get_name())
+		{
+			Some((_, n)) => *n += 1,
+			None => counts.push((p.get_block(), 1)),
+		}
+	}
+	// per substitution, the positions left to try (in reverse), those where it didn't fit are dropped for good
+	let mut queues = Vec::<((&str, &str), Vec<(u16, u16)>)>::new();
+	while missing > 0
+	{
+		let mut best: Option<(u64, &'l Block, &'l Block)> = None;
+		for &(from, n) in counts.iter()
+		{
+			if n == 0 {continue;}
+			let Some(from_cost) = from.get_build_cost()
+			else {continue;};
+			for name in upgrade::get_substitutes(from.get_name())
+			{
+				if queues.iter().any(|(k, q)| *k == (from.get_name(), name) && q.is_empty()) {continue;}
+				let Some(to) = reg.get(name)
+				else {continue;};
+				let Some(to_cost) = to.get_build_cost()
+				else {continue;};
+				let next = missing_after(missing, &cost, budget, from_cost, to_cost);
+				if next < best.map_or(missing, |(n, ..)| n)
+				{
+					best = Some((next, from, to));
+				}
+			}
+		}
+		let Some((next, from, to)) = best
+		else {break;};
+		let key = (from.get_name(), to.get_name());
+		let queue = match queues.iter().position(|(k, _)| *k == key)
+		{
+			Some(i) => &mut queues[i].1,
+			None =>
+			{
+				let mut positions = Vec::from_iter(schematic.block_iter().filter(|p| p.get_block().get_name() == from.get_name())
+					.map(|p| (p.get_pos().0, p.get_pos().1)));
+				positions.reverse();
+				queues.push((key, positions));
+				&mut queues.last_mut().unwrap().1
+			},
+		};
+		let mut done = None;
+		while let Some((x, y)) = queue.pop()
+		{
+			// it may have been swapped for something else since
+			let Some(curr) = schematic.get(x, y).ok().flatten()
+			else {continue;};
+			if curr.get_block().get_name() != from.get_name() || curr.get_pos() != GridPos(x, y) {continue;}
+			if let Ok(Some(..)) = schematic.replace_at(x, y, to)
+			{
+				done = Some(GridPos(x, y));
+				break;
+			}
+		}
+		// an empty queue excludes this substitution, until there are new blocks to try it on
+		let Some(pos) = done
+		else {continue;};
+		for (k, q) in queues.iter_mut()
+		{
+			if k.0 == to.get_name() {q.push((pos.0, pos.1));}
+		}
+		counts.iter_mut().find(|(b, _)| b.get_name() == from.get_name()).unwrap().1 -= 1;
+		match counts.iter_mut().find(|(b, _)| b.get_name() == to.get_name())
+		{
+			Some((_, n)) => *n += 1,
+			None => counts.push((to, 1)),
+		}
+		changed.push(Substitution{pos, from, to});
+		// both have a build cost, or they wouldn't have been picked
+		cost.sub_all(from.get_build_cost().unwrap(), 0);
+		cost.add_all(to.get_build_cost().unwrap(), u32::MAX);
+		missing = next;
+	}
+	let mut rest = budget.clone();
+	cost.diff_all(&mut rest, 0);
+	BudgetSummary{changed, shortfall: cost}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, Rotation};
+	use crate::data::dynamic::DynData;
+	use crate::item;
+	
+	#[test]
+	fn fit_walls()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(4, 1);
+		for x in 0..4
+		{
+			schematic.set(x, 0, reg.get("titanium-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		}
+		let mut budget = ItemStorage::new();
+		budget.add(item::Type::Copper, 12, u32::MAX);
+		budget.add(item::Type::Titanium, 12, u32::MAX);
+		let summary = fit_budget(&mut schematic, &reg, &budget);
+		assert_eq!(summary.changed.len(), 2);
+		assert!(summary.shortfall.is_empty());
+		// trading titanium for copper doesn't help without any resources
+		budget.clear();
+		let summary = fit_budget(&mut schematic, &reg, &budget);
+		assert_eq!(summary.changed.len(), 0);
+		assert_eq!(summary.shortfall.get(item::Type::Copper), 12);
+		assert_eq!(summary.shortfall.get_total(), 24);
+	}
+}
@@ -0,0 +1,522 @@
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use crate::block::{self, Block, BlockRegistry, Rotation};
+use crate::block::base::RGBA;
+use crate::block::logic::ProcessorState;
+use crate::content;
+use crate::data::command::UnitCommand;
+use crate::data::diff::rot_name;
+use crate::data::dynamic::DynData;
+use crate::data::json::Value;
+use crate::data::schematic::{NewError, PlaceError, Placement, Schematic};
+use crate::fluid;
+use crate::item;
+use crate::registry::RegistryEntry;
+use crate::unit;
+
+// the configuration of a block in the text forms, None if it has none (or is a processor, which is written separately)
+fn format_config(p: &Placement) -> Option<String>
+{
+	let state = p.get_state()?;
+	if state.is::<ProcessorState>() {return None;}
+	if let Some(rgba) = state.downcast_ref::<RGBA>() {return Some(rgba.to_string());}
+	match p.get_block().serialize_state(state).ok()?
+	{
+		DynData::Empty => None,
+		DynData::UnitCommand(cmd) => Some(format!("{cmd:?}").to_lowercase()),
+		data => Some(data.to_string()),
+	}
+}
+
+fn write_block<W: Write>(out: &mut W, p: &Placement) -> fmt::Result
+{
+	let pos = p.get_pos();
+	write!(out, "block {} {} {} {}", pos.0, pos.1, p.get_block().get_name(), rot_name(p.get_rotation()))?;
+	match format_config(p)
+	{
+		None => writeln!(out)?,
+		Some(config) => writeln!(out, " {config}")?,
+	}
+	// processors get their code on separate lines so that changes to it are easy to follow
+	if let Some(proc) = p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>())
+	{
+		for link in proc.get_links()
+		{
+			let (dx, dy) = link.get_pos();
+			writeln!(out, "\tlink {:?} {dx} {dy}", link.get_name())?;
+		}
+		// split on every newline so that a trailing one (which the game always writes) turns into an empty last line
+		let code = proc.get_code();
+		if !code.is_empty()
+		{
+			for line in code.split('\n')
+			{
+				if line.is_empty() {writeln!(out, "\tcode")?;}
+				else {writeln!(out, "\tcode {line}")?;}
+			}
+		}
+	}
+	Ok(())
+}
+
+// writes a line-oriented description of the schematic, which is identical for equal schematics regardless of the
+// order of their tags and blocks (for diffing and version control)
+pub fn write_text<W: Write>(schematic: &Schematic, out: &mut W) -> fmt::Result
+{
+	writeln!(out, "size {} {}", schematic.get_width(), schematic.get_height())?;
+	let mut tags = Vec::from_iter(schematic.get_tags().iter());
+	tags.sort();
+	for (key, value) in tags
+	{
+		writeln!(out, "tag {key:?} {value:?}")?;
+	}
+	for p in sorted_blocks(schematic)
+	{
+		write_block(out, p)?;
+	}
+	Ok(())
+}
+
+pub fn to_text(schematic: &Schematic) -> String
+{
+	let mut result = String::new();
+	write_text(schematic, &mut result).unwrap();
+	result
+}
+
+fn sorted_blocks<'s, 'l>(schematic: &'s Schematic<'l>) -> Vec<&'s Placement<'l>>
+{
+	let mut blocks = Vec::from_iter(schematic.block_iter());
+	blocks.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+	blocks
+}
+
+// the same content as the text form, with processor code and links as separate fields
+pub fn to_json(schematic: &Schematic) -> Value
+{
+	let mut tags = Vec::from_iter(schematic.get_tags().iter().map(|(k, v)| (k.clone(), Value::from(v.as_str()))));
+	tags.sort_by(|(l, _), (r, _)| l.cmp(r));
+	let blocks = sorted_blocks(schematic).into_iter().map(|p|
+	{
+		let mut value = Value::Object(vec![
+			("x".to_owned(), Value::from(p.get_pos().0)),
+			("y".to_owned(), Value::from(p.get_pos().1)),
+			("block".to_owned(), Value::from(p.get_block().get_name())),
+			("rotation".to_owned(), Value::from(rot_name(p.get_rotation()))),
+			("config".to_owned(), Value::from(format_config(p))),
+		]);
+		if let Some(proc) = p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>())
+		{
+			let links = proc.get_links().iter().map(|l| Value::Object(vec![
+				("name".to_owned(), Value::from(l.get_name())),
+				("x".to_owned(), Value::from(l.get_pos().0)),
+				("y".to_owned(), Value::from(l.get_pos().1)),
+			])).collect();
+			value.insert("links", Value::Array(links));
+			value.insert("code", Value::from(proc.get_code()));
+		}
+		value
+	}).collect();
+	Value::Object(vec![
+		("width".to_owned(), Value::from(schematic.get_width())),
+		("height".to_owned(), Value::from(schematic.get_height())),
+		("tags".to_owned(), Value::Object(tags)),
+		("blocks".to_owned(), Value::Array(blocks)),
+	])
+}
+
+fn parse_rotation(value: &str) -> Option<Rotation>
+{
+	match value
+	{
+		"right" => Some(Rotation::Right),
+		"up" => Some(Rotation::Up),
+		"left" => Some(Rotation::Left),
+		"down" => Some(Rotation::Down),
+		_ => None,
+	}
+}
+
+// reads a string as written by Debug, returning it and the remaining text
+fn parse_quoted(text: &str) -> Option<(String, &str)>
+{
+	let mut chars = text.strip_prefix('"')?.char_indices();
+	let mut result = String::new();
+	while let Some((i, c)) = chars.next()
+	{
+		match c
+		{
+			'"' => return Some((result, &text[i + 2..])),
+			'\\' =>
+			{
+				let c = match chars.next()?.1
+				{
+					'n' => '\n',
+					'r' => '\r',
+					't' => '\t',
+					'0' => '\0',
+					'u' =>
+					{
+						if chars.next()?.1 != '{' {return None;}
+						let mut code = 0u32;
+						loop
+						{
+							match chars.next()?.1
+							{
+								'}' => break,
+								c => code = code.checked_mul(16)?.checked_add(c.to_digit(16)?)?,
+							}
+						}
+						char::from_u32(code)?
+					},
+					c @ ('\\' | '"' | '\'') => c,
+					_ => return None,
+				};
+				result.push(c);
+			},
+			c => result.push(c),
+		}
+	}
+	None
+}
+
+fn parse_pair<T: std::str::FromStr>(text: &str) -> Option<(T, T)>
+{
+	let (x, y) = text.strip_prefix('(')?.strip_suffix(')')?.split_once(',')?;
+	Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+// the configuration values the text could stand for, the first one the block accepts is used
+fn config_candidates(value: &str) -> Vec<DynData>
+{
+	if let Some(hex) = value.strip_prefix('#')
+	{
+		return match u32::from_str_radix(hex, 16)
+		{
+			Ok(rgba) if hex.len() == 8 => vec![DynData::Int(rgba as i32)],
+			_ => Vec::new(),
+		};
+	}
+	if let Ok(v) = value.parse::<i32>() {return vec![DynData::Int(v)];}
+	if let Ok(v) = value.parse::<bool>() {return vec![DynData::Boolean(v)];}
+	if value == "null" {return vec![DynData::String(None)];}
+	if value.starts_with('"')
+	{
+		return match parse_quoted(value)
+		{
+			Some((s, "")) => vec![DynData::String(Some(s))],
+			_ => Vec::new(),
+		};
+	}
+	if let Some((x, y)) = parse_pair(value) {return vec![DynData::Point2(x, y)];}
+	if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']'))
+	{
+		let mut points = Vec::new();
+		let mut rest = list.trim();
+		while !rest.is_empty()
+		{
+			let Some(end) = rest.find(')')
+			else {return Vec::new();};
+			let Some(point) = parse_pair(&rest[..=end])
+			else {return Vec::new();};
+			points.push(point);
+			rest = rest[end + 1..].trim_start().trim_start_matches(',').trim_start();
+		}
+		return vec![DynData::Point2Array(points)];
+	}
+	let mut result = Vec::new();
+	if let Some(v) = item::Type::by_name(value) {result.push(DynData::Content(content::Type::Item, v.into()));}
+	if let Some(v) = fluid::Type::by_name(value) {result.push(DynData::Content(content::Type::Fluid, v.into()));}
+	if let Some(v) = unit::Type::by_name(value) {result.push(DynData::Content(content::Type::Unit, v.into()));}
+	if let Some(v) = block::content::Type::by_name(value) {result.push(DynData::Content(content::Type::Block, v.into()));}
+	for cmd in [UnitCommand::Move, UnitCommand::Repair, UnitCommand::Rebuild, UnitCommand::Assist, UnitCommand::Mine, UnitCommand::Boost]
+	{
+		if format!("{cmd:?}").eq_ignore_ascii_case(value) {result.push(DynData::UnitCommand(cmd));}
+	}
+	result
+}
+
+pub fn parse_config(block: &Block, value: &str) -> Option<DynData>
+{
+	config_candidates(value).into_iter().find(|c| block.deserialize_state(c.clone()).is_ok())
+}
+
+struct BlockDef<'l>
+{
+	x: u16,
+	y: u16,
+	block: &'l Block,
+	rot: Rotation,
+	config: DynData,
+	links: Vec<(String, i16, i16)>,
+	code: Option<String>,
+}
+
+fn place<'l>(schematic: &mut Schematic<'l>, def: BlockDef<'l>, line: usize) -> Result<(), TextError>
+{
+	let mut config = def.config;
+	if def.code.is_some() || !def.links.is_empty()
+	{
+		let state = def.block.deserialize_state(DynData::Empty).ok().flatten();
+		let Some(mut state) = state.filter(|s| s.is::<ProcessorState>())
+		else {return Err(TextError::NotProcessor{line, name: def.block.get_name().to_owned()});};
+		let proc = state.downcast_mut::<ProcessorState>().unwrap();
+		if let Some(code) = def.code
+		{
+			if proc.set_code(code.into()).is_err() {return Err(TextError::Processor{line});}
+		}
+		for (name, x, y) in def.links
+		{
+			if proc.create_link(name, x, y).is_err() {return Err(TextError::Processor{line});}
+		}
+		config = def.block.serialize_state(state.as_ref()).map_err(|_| TextError::Processor{line})?;
+	}
+	match schematic.set(def.x, def.y, def.block, config, def.rot)
+	{
+		Ok(..) => Ok(()),
+		Err(e) => Err(TextError::Place{line, source: e}),
+	}
+}
+
+// reads the text form written by write_text
+pub fn read_text<'l>(reg: &'l BlockRegistry<'l>, text: &str) -> Result<Schematic<'l>, TextError>
+{
+	let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l)).filter(|(_, l)| !l.trim().is_empty());
+	let Some((line, first)) = lines.next()
+	else {return Err(TextError::Empty);};
+	let size = first.strip_prefix("size ").and_then(|s| s.split_once(' '))
+		.and_then(|(w, h)| Some((w.parse::<u16>().ok()?, h.parse::<u16>().ok()?)));
+	let Some((w, h)) = size
+	else {return Err(TextError::Syntax{line, expect: "size <width> <height>"});};
+	let mut schematic = Schematic::try_new(w, h).map_err(|e| TextError::Size{line, source: e})?;
+	schematic.get_tags_mut().clear();
+	let mut pending: Option<(usize, BlockDef<'l>)> = None;
+	for (line, curr) in lines
+	{
+		if let Some(rest) = curr.strip_prefix('\t')
+		{
+			let Some((_, def)) = pending.as_mut()
+			else {return Err(TextError::Syntax{line, expect: "block before link or code"});};
+			if let Some(code) = rest.strip_prefix("code")
+			{
+				let code = code.strip_prefix(' ').unwrap_or(code);
+				match def.code
+				{
+					None => def.code = Some(code.to_owned()),
+					Some(ref mut prev) =>
+					{
+						prev.push('\n');
+						prev.push_str(code);
+					},
+				}
+			}
+			else if let Some(link) = rest.strip_prefix("link ")
+			{
+				let parsed = parse_quoted(link).and_then(|(name, pos)|
+				{
+					let (x, y) = pos.trim().split_once(' ')?;
+					Some((name, x.parse().ok()?, y.parse().ok()?))
+				});
+				match parsed
+				{
+					Some(link) => def.links.push(link),
+					None => return Err(TextError::Syntax{line, expect: "link \"<name>\" <x> <y>"}),
+				}
+			}
+			else {return Err(TextError::Syntax{line, expect: "link or code"});}
+			continue;
+		}
+		if let Some((line, def)) = pending.take() {place(&mut schematic, def, line)?;}
+		if let Some(rest) = curr.strip_prefix("tag ")
+		{
+			let parsed = parse_quoted(rest).and_then(|(key, rest)| Some((key, parse_quoted(rest.strip_prefix(' ')?)?)));
+			match parsed
+			{
+				Some((key, (value, ""))) => {schematic.get_tags_mut().insert(key, value);},
+				_ => return Err(TextError::Syntax{line, expect: "tag \"<key>\" \"<value>\""}),
+			}
+		}
+		else if let Some(rest) = curr.strip_prefix("block ")
+		{
+			let mut parts = rest.splitn(5, ' ');
+			let (Some(x), Some(y), Some(name), Some(rot)) = (parts.next(), parts.next(), parts.next(), parts.next())
+			else {return Err(TextError::Syntax{line, expect: "block <x> <y> <name> <rotation> [<config>]"});};
+			let (Ok(x), Ok(y), Some(rot)) = (x.parse::<u16>(), y.parse::<u16>(), parse_rotation(rot))
+			else {return Err(TextError::Syntax{line, expect: "block <x> <y> <name> <rotation> [<config>]"});};
+			let Some(block) = reg.get(name)
+			else {return Err(TextError::NoSuchBlock{line, name: name.to_owned()});};
+			let config = match parts.next()
+			{
+				None => DynData::Empty,
+				Some(value) => parse_config(block, value).ok_or_else(|| TextError::Config{line, value: value.to_owned()})?,
+			};
+			pending = Some((line, BlockDef{x, y, block, rot, config, links: Vec::new(), code: None}));
+		}
+		else {return Err(TextError::Syntax{line, expect: "tag or block"});}
+	}
+	if let Some((line, def)) = pending.take() {place(&mut schematic, def, line)?;}
+	Ok(schematic)
+}
+
+// reads the json form written by to_json, errors use the index of the block instead of a line
+pub fn from_json<'l>(reg: &'l BlockRegistry<'l>, value: &Value) -> Result<Schematic<'l>, TextError>
+{
+	fn number<T: TryFrom<i64>>(value: &Value, key: &'static str, line: usize) -> Result<T, TextError>
+	{
+		value.get(key).and_then(Value::as_i64).and_then(|v| T::try_from(v).ok()).ok_or(TextError::Field{line, key})
+	}
+	
+	fn string<'v>(value: &'v Value, key: &'static str, line: usize) -> Result<&'v str, TextError>
+	{
+		value.get(key).and_then(Value::as_str).ok_or(TextError::Field{line, key})
+	}
+	
+	let mut schematic = Schematic::try_new(number(value, "width", 0)?, number(value, "height", 0)?)
+		.map_err(|e| TextError::Size{line: 0, source: e})?;
+	schematic.get_tags_mut().clear();
+	for (key, v) in value.get("tags").and_then(Value::as_object).ok_or(TextError::Field{line: 0, key: "tags"})?
+	{
+		let v = v.as_str().ok_or(TextError::Field{line: 0, key: "tags"})?;
+		schematic.get_tags_mut().insert(key.clone(), v.to_owned());
+	}
+	for (i, curr) in value.get("blocks").and_then(Value::as_array).ok_or(TextError::Field{line: 0, key: "blocks"})?.iter().enumerate()
+	{
+		let name = string(curr, "block", i)?;
+		let Some(block) = reg.get(name)
+		else {return Err(TextError::NoSuchBlock{line: i, name: name.to_owned()});};
+		let rot = parse_rotation(string(curr, "rotation", i)?).ok_or(TextError::Field{line: i, key: "rotation"})?;
+		let config = match curr.get("config")
+		{
+			None | Some(Value::Null) => DynData::Empty,
+			Some(v) =>
+			{
+				let v = v.as_str().ok_or(TextError::Field{line: i, key: "config"})?;
+				parse_config(block, v).ok_or_else(|| TextError::Config{line: i, value: v.to_owned()})?
+			},
+		};
+		let mut links = Vec::new();
+		for link in curr.get("links").and_then(Value::as_array).unwrap_or_default()
+		{
+			links.push((string(link, "name", i)?.to_owned(), number(link, "x", i)?, number(link, "y", i)?));
+		}
+		let code = curr.get("code").and_then(Value::as_str).map(str::to_owned);
+		place(&mut schematic, BlockDef{x: number(curr, "x", i)?, y: number(curr, "y", i)?, block, rot, config, links, code}, i)?;
+	}
+	Ok(schematic)
+}
+
+#[derive(Debug)]
+pub enum TextError
+{
+	Empty,
+	Syntax{line: usize, expect: &'static str},
+	Field{line: usize, key: &'static str},
+	Size{line: usize, source: NewError},
+	NoSuchBlock{line: usize, name: String},
+	Config{line: usize, value: String},
+	NotProcessor{line: usize, name: String},
+	Processor{line: usize},
+	Place{line: usize, source: PlaceError},
+}
+
+impl fmt::Display for TextError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Empty => f.write_str("no schematic in text"),
+			Self::Syntax{line, expect} => write!(f, "expected {expect} (line {line})"),
+			Self::Field{line, key} => write!(f, "missing or invalid field {key:?} (block {line})"),
+			Self::Size{line, ..} => write!(f, "invalid size (line {line})"),
+			Self::NoSuchBlock{line, name} => write!(f, "no such block {name:?} (line {line})"),
+			Self::Config{line, value} => write!(f, "invalid configuration {value:?} (line {line})"),
+			Self::NotProcessor{line, name} => write!(f, "{name:?} cannot have links or code (line {line})"),
+			Self::Processor{line} => write!(f, "invalid processor links or code (line {line})"),
+			Self::Place{line, ..} => write!(f, "could not place block (line {line})"),
+		}
+	}
+}
+
+impl Error for TextError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Size{source, ..} => Some(source),
+			Self::Place{source, ..} => Some(source),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::{build_registry, Rotation};
+	use crate::content;
+	
+	#[test]
+	fn ordering()
+	{
+		let reg = build_registry();
+		let mut first = Schematic::new(4, 4);
+		first.set(2, 2, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		first.set(1, 0, reg.get("sorter").unwrap(), DynData::Content(content::Type::Item, 0), Rotation::Up).unwrap();
+		let mut second = Schematic::new(4, 4);
+		second.set(1, 0, reg.get("sorter").unwrap(), DynData::Content(content::Type::Item, 0), Rotation::Up).unwrap();
+		second.set(2, 2, reg.get("copper-wall").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let text = to_text(&first);
+		assert_eq!(text, to_text(&second));
+		assert_eq!(text, concat!(
+			"size 4 4\n",
+			"tag \"description\" \"\"\n",
+			"tag \"labels\" \"[]\"\n",
+			"tag \"name\" \"\"\n",
+			"block 1 0 sorter up copper\n",
+			"block 2 2 copper-wall right\n",
+		));
+	}
+	
+	#[test]
+	fn reparse()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(6, 6);
+		schematic.set(0, 0, reg.get("sorter").unwrap(), DynData::Content(content::Type::Item, 3), Rotation::Up).unwrap();
+		schematic.set(1, 0, reg.get("illuminator").unwrap(), DynData::Int(0x12345678), Rotation::Right).unwrap();
+		schematic.set(2, 0, reg.get("message").unwrap(), DynData::String(Some("say \"hi\"\n\u{1F998}".to_string())), Rotation::Right).unwrap();
+		schematic.set(3, 0, reg.get("bridge-conveyor").unwrap(), DynData::Point2(0, 3), Rotation::Right).unwrap();
+		schematic.set(4, 0, reg.get("power-node").unwrap(), DynData::Point2Array(vec![(-1, 0), (0, 2)]), Rotation::Right).unwrap();
+		schematic.set(2, 3, reg.get("additive-reconstructor").unwrap(), DynData::UnitCommand(UnitCommand::Rebuild), Rotation::Left).unwrap();
+		let proc = schematic.set(5, 5, reg.get("micro-processor").unwrap(), DynData::Empty, Rotation::Right).unwrap().get_pos();
+		let state = schematic.get_mut(proc.0, proc.1).unwrap().unwrap().get_state_mut().unwrap();
+		let state = state.downcast_mut::<ProcessorState>().unwrap();
+		state.set_code("set a 1\n\n  print a".into()).unwrap();
+		state.create_link("sorter1".to_string(), -5, -5).unwrap();
+		schematic.get_tags_mut().insert("name".to_string(), "tab\tbed".to_string());
+		let text = to_text(&schematic);
+		let copy = read_text(&reg, &text).unwrap();
+		assert!(copy == schematic, "{text}");
+		assert!(from_json(&reg, &to_json(&schematic)).unwrap() == schematic);
+		assert!(matches!(read_text(&reg, "size 2 2\nblock 0 0 sorter up water"), Err(TextError::Config{line: 2, ..})));
+	}
+	
+	#[test]
+	fn trailing_newline()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(1, 1);
+		schematic.set(0, 0, reg.get("micro-processor").unwrap(), DynData::Empty, Rotation::Right).unwrap();
+		let state = schematic.get_mut(0, 0).unwrap().unwrap().get_state_mut().unwrap();
+		state.downcast_mut::<ProcessorState>().unwrap().set_code("set a 1\nprint a\n".into()).unwrap();
+		let text = to_text(&schematic);
+		assert!(text.ends_with("\tcode set a 1\n\tcode print a\n\tcode\n"), "{text}");
+		let copy = read_text(&reg, &text).unwrap();
+		let state = copy.get(0, 0).unwrap().unwrap().get_state().unwrap();
+		assert_eq!(state.downcast_ref::<ProcessorState>().unwrap().get_code(), "set a 1\nprint a\n");
+	}
+}
@@ -55,7 +55,8 @@ pub fn parse<I: Iterator, H: ArgHandler>(args: &mut I, handler: &mut H, arg_off:
 		let arg = arg.as_ref();
 		if !arg.is_empty()
 		{
-			if arg.as_bytes()[0] == b'-'
+			// a single dash is a literal, conventionally standing for stdin
+			if arg.as_bytes()[0] == b'-' && arg != "-"
 			{
 				if arg.len() >= 2 && arg.as_bytes()[1] == b'-'
 				{
@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+use plandustry::block::{build_registry, Rotation};
+use plandustry::data::dynamic::DynData;
+use plandustry::data::processors;
+use plandustry::logic::compile::compile;
+
+use crate::{print_err, read_schematic};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+use crate::convert::{self, Format};
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	let opt_schematic = handler.add(ArgOption::new(Some('s'), Some(Cow::Borrowed("schematic")), ArgCount::Required(1))).unwrap();
+	let opt_pos = handler.add(ArgOption::new(Some('p'), Some(Cow::Borrowed("pos")), ArgCount::Required(1))).unwrap();
+	let opt_block = handler.add(ArgOption::new(Some('b'), Some(Cow::Borrowed("block")), ArgCount::Required(1))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 1
+	{
+		eprintln!("Expected exactly one source file (or - for stdin)");
+		process::exit(1);
+	}
+	let path = literals[0].as_str();
+	let source = if path == "-"
+	{
+		let mut source = String::new();
+		if let Err(e) = io::stdin().read_to_string(&mut source)
+		{
+			print_err!(e, "Could not read from stdin");
+			process::exit(1);
+		}
+		source
+	}
+	else
+	{
+		match fs::read_to_string(path)
+		{
+			Ok(s) => s,
+			Err(e) =>
+			{
+				print_err!(e, "Could not read file {path:?}");
+				process::exit(1);
+			},
+		}
+	};
+	let program = match compile(&source)
+	{
+		Ok(p) => p,
+		Err(e) =>
+		{
+			eprintln!("{path}:{e}");
+			process::exit(1);
+		},
+	};
+	let output = handler.get_value(opt_output).get_value();
+	
+	let Some(target) = handler.get_value(opt_schematic).get_value()
+	else
+	{
+		if !program.links.is_empty()
+		{
+			eprintln!("Note: links are only created when placing the program into a schematic (--schematic)");
+		}
+		let result = match output
+		{
+			Some(out) => fs::write(out, &program.code),
+			None => io::stdout().write_all(program.code.as_bytes()),
+		};
+		if let Err(e) = result
+		{
+			print_err!(e, "Could not write the compiled code");
+			process::exit(1);
+		}
+		return;
+	};
+	let Some(pos) = handler.get_value(opt_pos).get_value()
+	else
+	{
+		eprintln!("Expected the position of the processor (--pos=<x>,<y>)");
+		process::exit(1);
+	};
+	let Some((x, y)) = pos.split_once(',').and_then(|(x, y)| Some((x.trim().parse::<u16>().ok()?, y.trim().parse::<u16>().ok()?)))
+	else
+	{
+		eprintln!("Invalid position {pos:?}, expected <x>,<y>");
+		process::exit(1);
+	};
+	let reg = build_registry();
+	let Some(mut schematic) = read_schematic(&reg, target)
+	else {process::exit(1);};
+	match schematic.get(x, y)
+	{
+		Ok(Some(..)) => (),
+		Ok(None) =>
+		{
+			// nothing there yet, so place a new processor
+			let name = handler.get_value(opt_block).get_value().map_or("micro-processor", |s| s.as_str());
+			let Some(block) = reg.get(name)
+			else
+			{
+				eprintln!("No such block {name:?}");
+				process::exit(1);
+			};
+			if let Err(e) = schematic.set(x, y, block, DynData::Empty, Rotation::Right)
+			{
+				print_err!(e, "Could not place {name} at {x} / {y}");
+				process::exit(1);
+			}
+		},
+		Err(e) =>
+		{
+			print_err!(e, "Invalid position");
+			process::exit(1);
+		},
+	}
+	if let Err(e) = processors::set_program(&mut schematic, x, y, &program.code, &program.links)
+	{
+		print_err!(e, "Could not place the program");
+		process::exit(1);
+	}
+	
+	// written back to the schematic file unless told otherwise, base-64 schematics are printed
+	let dest = output.map(String::as_str).or(Path::new(target).is_file().then_some(target.as_str()));
+	let format = match dest
+	{
+		Some(d) => Path::new(d).extension().and_then(|e| e.to_str()).and_then(Format::parse).unwrap_or(Format::Msch),
+		None => Format::Base64,
+	};
+	let Some(data) = convert::write_output(&reg, &schematic, format, 16)
+	else {process::exit(1);};
+	let result = match dest
+	{
+		Some(d) => fs::write(d, data),
+		None => io::stdout().write_all(&data),
+	};
+	if let Err(e) = result
+	{
+		print_err!(e, "Could not write the schematic");
+		process::exit(1);
+	}
+}
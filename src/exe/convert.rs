@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::env::Args;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use plandustry::block::{build_registry, BlockRegistry};
+use plandustry::data::{DataRead, DataWrite, Serializer};
+use plandustry::data::json;
+use plandustry::data::render::{render_canvases, render_png, render_svg};
+use plandustry::data::schematic::{Schematic, SchematicSerializer};
+use plandustry::data::text::{from_json, read_text, to_json, to_text};
+
+use crate::{find_files, print_err};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Format
+{
+	Msch, Base64, Text, Json, Png, Svg, Ascii
+}
+
+impl Format
+{
+	pub(crate) fn parse(name: &str) -> Option<Self>
+	{
+		match name
+		{
+			"msch" => Some(Self::Msch),
+			"base64" | "b64" => Some(Self::Base64),
+			"text" | "txt" => Some(Self::Text),
+			"json" => Some(Self::Json),
+			"png" => Some(Self::Png),
+			"svg" => Some(Self::Svg),
+			"ascii" => Some(Self::Ascii),
+			_ => None,
+		}
+	}
+	
+	fn get_extension(self) -> &'static str
+	{
+		match self
+		{
+			Self::Msch => "msch",
+			Self::Base64 => "b64",
+			Self::Text | Self::Ascii => "txt",
+			Self::Json => "json",
+			Self::Png => "png",
+			Self::Svg => "svg",
+		}
+	}
+	
+	fn is_readable(self) -> bool
+	{
+		matches!(self, Self::Msch | Self::Base64 | Self::Text | Self::Json)
+	}
+	
+	// guesses the format from the start of the data
+	fn detect(data: &[u8]) -> Self
+	{
+		let text = data.trim_ascii_start();
+		if data.starts_with(b"msch") {Self::Msch}
+		else if text.starts_with(b"size ") {Self::Text}
+		else if text.starts_with(b"{") {Self::Json}
+		else {Self::Base64}
+	}
+}
+
+struct Input
+{
+	// for error messages
+	label: String,
+	// file name without extension and the directory relative to the given one, for output templates
+	stem: String,
+	dir: String,
+	data: Vec<u8>,
+}
+
+fn read_input<'l>(reg: &'l BlockRegistry<'l>, input: &Input, format: Option<Format>) -> Option<Schematic<'l>>
+{
+	let format = format.unwrap_or_else(|| Format::detect(&input.data));
+	let label = &input.label;
+	let as_text = ||
+	{
+		match std::str::from_utf8(&input.data)
+		{
+			Ok(s) => Some(s),
+			Err(e) =>
+			{
+				print_err!(e, "Could not read {label} as text");
+				None
+			},
+		}
+	};
+	let result = match format
+	{
+		Format::Msch => SchematicSerializer(reg).deserialize(&mut DataRead::new(&input.data)).map_err(|e| print_err!(e, "Could not read schematic from {label}")),
+		Format::Base64 => SchematicSerializer(reg).deserialize_base64(as_text()?.trim()).map_err(|e| print_err!(e, "Could not read schematic from {label}")),
+		Format::Text => read_text(reg, as_text()?).map_err(|e| print_err!(e, "Could not read schematic from {label}")),
+		Format::Json =>
+		{
+			match json::parse(as_text()?)
+			{
+				Ok(v) => from_json(reg, &v).map_err(|e| print_err!(e, "Could not read schematic from {label}")),
+				Err(e) =>
+				{
+					print_err!(e, "Could not read {label} as json");
+					Err(())
+				},
+			}
+		},
+		_ => unreachable!("format {format:?} cannot be read"),
+	};
+	result.ok()
+}
+
+pub(crate) fn write_output(reg: &BlockRegistry, schematic: &Schematic, format: Format, scale: u32) -> Option<Vec<u8>>
+{
+	let mut ss = SchematicSerializer(reg);
+	match format
+	{
+		Format::Msch =>
+		{
+			let mut data = DataWrite::new();
+			match ss.serialize(&mut data, schematic)
+			{
+				Ok(()) => Some(data.get_written().to_vec()),
+				Err(e) =>
+				{
+					print_err!(e, "Could not serialize schematic");
+					None
+				},
+			}
+		},
+		Format::Base64 =>
+		{
+			match ss.serialize_base64(schematic)
+			{
+				Ok(s) => Some(format!("{s}\n").into_bytes()),
+				Err(e) =>
+				{
+					print_err!(e, "Could not serialize schematic");
+					None
+				},
+			}
+		},
+		Format::Text => Some(to_text(schematic).into_bytes()),
+		Format::Json => Some(format!("{}\n", to_json(schematic)).into_bytes()),
+		Format::Png => Some(render_png(schematic, scale)),
+		Format::Svg => Some(render_svg(schematic, scale).into_bytes()),
+		Format::Ascii =>
+		{
+			let canvases = render_canvases(schematic);
+			if canvases.is_empty() {Some(format!("{schematic}\n").into_bytes())}
+			else {Some(format!("{schematic}\n\n{canvases}").into_bytes())}
+		},
+	}
+}
+
+// replaces the placeholders in an output path template
+fn fill_template(template: &str, input: &Input, schematic: &Schematic, format: Format, index: usize) -> PathBuf
+{
+	let name = schematic.get_tags().get("name").map_or("", |s| s.as_str());
+	// keep names usable as file names
+	let name = String::from_iter(name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {c} else {'_'}));
+	let name = if name.trim().is_empty() {input.stem.as_str()} else {name.trim()};
+	let path = template.replace("{stem}", &input.stem).replace("{dir}", &input.dir).replace("{name}", name)
+		.replace("{ext}", format.get_extension()).replace("{index}", &index.to_string());
+	PathBuf::from_iter(Path::new(&path).components())
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_from = handler.add(ArgOption::new(Some('f'), Some(Cow::Borrowed("from")), ArgCount::Required(1))).unwrap();
+	let opt_to = handler.add(ArgOption::new(Some('t'), Some(Cow::Borrowed("to")), ArgCount::Required(1))).unwrap();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	let opt_scale = handler.add(ArgOption::new(Some('s'), Some(Cow::Borrowed("scale")), ArgCount::Required(1))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let from = match handler.get_value(opt_from).get_value()
+	{
+		None => None,
+		Some(name) =>
+		{
+			match Format::parse(name)
+			{
+				Some(f) if f.is_readable() => Some(f),
+				_ =>
+				{
+					eprintln!("Cannot read format {name:?}, expected msch, base64, text or json");
+					process::exit(1);
+				},
+			}
+		},
+	};
+	let output = handler.get_value(opt_output).get_value();
+	let to = match handler.get_value(opt_to).get_value()
+	{
+		Some(name) =>
+		{
+			let Some(f) = Format::parse(name)
+			else
+			{
+				eprintln!("Unknown format {name:?}, expected msch, base64, text, json, png, svg or ascii");
+				process::exit(1);
+			};
+			f
+		},
+		// guess from the output file name, base-64 by default
+		None => output.and_then(|o| Path::new(o).extension()).and_then(|e| e.to_str()).and_then(Format::parse).unwrap_or(Format::Base64),
+	};
+	let scale = match handler.get_value(opt_scale).get_value()
+	{
+		None => 16,
+		Some(s) =>
+		{
+			match s.parse::<u32>()
+			{
+				Ok(n) if n > 0 && n <= 64 => n,
+				_ =>
+				{
+					eprintln!("Invalid scale {s:?}, expected a number from 1 to 64");
+					process::exit(1);
+				},
+			}
+		},
+	};
+	
+	let literals = handler.get_literals();
+	if literals.is_empty()
+	{
+		eprintln!("Expected at least one input (a file, directory, base-64 encoded schematic or - for stdin)");
+		process::exit(1);
+	}
+	let mut inputs = Vec::new();
+	let mut failed = false;
+	for (i, arg) in literals.iter().enumerate()
+	{
+		let path = Path::new(arg);
+		if arg == "-"
+		{
+			let mut data = Vec::new();
+			if let Err(e) = io::stdin().read_to_end(&mut data)
+			{
+				print_err!(e, "Could not read from stdin");
+				process::exit(1);
+			}
+			inputs.push(Input{label: "stdin".to_owned(), stem: format!("stdin{i}"), dir: String::new(), data});
+		}
+		else if path.is_dir()
+		{
+			let mut files = Vec::new();
+			find_files(path, from.unwrap_or(Format::Msch).get_extension(), &mut files);
+			for file in files
+			{
+				let dir = file.parent().and_then(|p| p.strip_prefix(path).ok()).map_or(String::new(), |p| p.to_string_lossy().into_owned());
+				let stem = file.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+				match fs::read(&file)
+				{
+					Ok(data) => inputs.push(Input{label: file.display().to_string(), stem, dir, data}),
+					Err(e) =>
+					{
+						print_err!(e, "Could not read file {file:?}");
+						failed = true;
+					},
+				}
+			}
+		}
+		else if path.is_file()
+		{
+			let stem = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+			match fs::read(path)
+			{
+				Ok(data) => inputs.push(Input{label: arg.clone(), stem, dir: String::new(), data}),
+				Err(e) =>
+				{
+					print_err!(e, "Could not read file {arg:?}");
+					failed = true;
+				},
+			}
+		}
+		else {inputs.push(Input{label: format!("argument {}", i + 1), stem: format!("schematic{i}"), dir: String::new(), data: arg.clone().into_bytes()});}
+	}
+	// a directory as output means converting into it with the same structure
+	let template = output.map(|o|
+	{
+		if Path::new(o).is_dir() || o.ends_with('/') {format!("{}/{{dir}}/{{stem}}.{{ext}}", o.trim_end_matches('/'))}
+		else {o.clone()}
+	});
+	if let Some(ref t) = template
+	{
+		if inputs.len() > 1 && !t.contains('{')
+		{
+			eprintln!("Converting {} schematics needs an output directory or a template like \"out/{{dir}}/{{stem}}.{{ext}}\"", inputs.len());
+			process::exit(1);
+		}
+	}
+	
+	let reg = build_registry();
+	let mut converted = 0;
+	// templates without {index} can give several schematics the same path, which must not overwrite each other
+	let mut written = HashSet::new();
+	for (i, input) in inputs.iter().enumerate()
+	{
+		let Some(schematic) = read_input(&reg, input, from)
+		else
+		{
+			failed = true;
+			continue;
+		};
+		let Some(data) = write_output(&reg, &schematic, to, scale)
+		else
+		{
+			failed = true;
+			continue;
+		};
+		match template
+		{
+			None =>
+			{
+				if let Err(e) = io::stdout().write_all(&data)
+				{
+					print_err!(e, "Could not write to stdout");
+					process::exit(1);
+				}
+			},
+			Some(ref t) =>
+			{
+				let path = fill_template(t, input, &schematic, to, i + 1);
+				if !written.insert(path.clone())
+				{
+					eprintln!("Not converting {}, {path:?} is already the output of another schematic (try {{index}} in the template)", input.label);
+					failed = true;
+					continue;
+				}
+				if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty())
+				{
+					if let Err(e) = fs::create_dir_all(parent)
+					{
+						print_err!(e, "Could not create directory {parent:?}");
+						failed = true;
+						continue;
+					}
+				}
+				if let Err(e) = fs::write(&path, &data)
+				{
+					print_err!(e, "Could not write to file {path:?}");
+					failed = true;
+					continue;
+				}
+			},
+		}
+		converted += 1;
+	}
+	if template.is_some() {eprintln!("Converted {converted} of {} schematics", inputs.len());}
+	if failed {process::exit(1);}
+}
@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+use std::env::Args;
+
+use plandustry::block::build_registry;
+use plandustry::data::diff::diff;
+
+use crate::{print_err, read_schematic};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+use crate::print::print_cost_delta;
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_translate = handler.add(ArgOption::new(Some('t'), Some(Cow::Borrowed("translate")), ArgCount::Forbidden)).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		return;
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 2
+	{
+		eprintln!("Expected the old and the new schematic, got {} arguments", literals.len());
+		return;
+	}
+	let reg = build_registry();
+	let Some(old) = read_schematic(&reg, &literals[0])
+	else {return;};
+	let Some(new) = read_schematic(&reg, &literals[1])
+	else {return;};
+	let result = diff(&old, &new, handler.get_value(opt_translate).is_present());
+	if result.is_empty()
+	{
+		println!("No differences");
+		return;
+	}
+	if result.offset != (0, 0)
+	{
+		println!("~ moved by {} / {}", result.offset.0, result.offset.1);
+	}
+	for change in result.changes.iter()
+	{
+		println!("{change}");
+	}
+	print_cost_delta(&result.cost_before, &result.cost_after);
+}
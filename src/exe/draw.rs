@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use plandustry::block::build_registry;
+use plandustry::data::image;
+use plandustry::logic::display::{build_schematic, draw_code, DisplayKind, DrawOptions};
+
+use crate::print_err;
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+use crate::convert::{self, Format};
+
+// parses an optional number option within the given range
+fn parse_option(value: Option<&String>, name: &str, min: u32, max: u32) -> Option<u32>
+{
+	let value = value?;
+	match value.parse::<u32>()
+	{
+		Ok(n) if n >= min && n <= max => Some(n),
+		_ =>
+		{
+			eprintln!("Invalid {name} {value:?}, expected a number from {min} to {max}");
+			process::exit(1);
+		},
+	}
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_display = handler.add(ArgOption::new(Some('d'), Some(Cow::Borrowed("display")), ArgCount::Required(1))).unwrap();
+	let opt_processor = handler.add(ArgOption::new(Some('p'), Some(Cow::Borrowed("processor")), ArgCount::Required(1))).unwrap();
+	let opt_cells = handler.add(ArgOption::new(Some('c'), Some(Cow::Borrowed("cells")), ArgCount::Required(1))).unwrap();
+	let opt_levels = handler.add(ArgOption::new(Some('l'), Some(Cow::Borrowed("levels")), ArgCount::Required(1))).unwrap();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	let opt_code = handler.add(ArgOption::new(None, Some(Cow::Borrowed("code")), ArgCount::Forbidden)).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 1
+	{
+		eprintln!("Expected exactly one image (PNG or PPM)");
+		process::exit(1);
+	}
+	let path = literals[0].as_str();
+	let display = match handler.get_value(opt_display).get_value()
+	{
+		None => DisplayKind::Logic,
+		Some(name) =>
+		{
+			let Some(d) = DisplayKind::by_name(name)
+			else
+			{
+				eprintln!("Unknown display {name:?}, expected logic-display or large-logic-display");
+				process::exit(1);
+			};
+			d
+		},
+	};
+	let mut options = DrawOptions::new(display);
+	if let Some(cells) = parse_option(handler.get_value(opt_cells).get_value(), "cell count", 1, display.get_resolution()) {options.cells = cells;}
+	if let Some(levels) = parse_option(handler.get_value(opt_levels).get_value(), "level count", 2, 256) {options.levels = levels as u16;}
+	let image = match fs::read(path).map(|data| image::decode(&data))
+	{
+		Ok(Ok(image)) => image,
+		Ok(Err(e)) =>
+		{
+			print_err!(e, "Could not read image {path:?}");
+			process::exit(1);
+		},
+		Err(e) =>
+		{
+			print_err!(e, "Could not read file {path:?}");
+			process::exit(1);
+		},
+	};
+	let programs = draw_code(&image, &options);
+	let instructions = programs.iter().map(|p| p.lines().count()).sum::<usize>();
+	eprintln!("{} processors with {instructions} instructions in total", programs.len());
+	if handler.get_value(opt_code).is_present()
+	{
+		for (i, code) in programs.iter().enumerate()
+		{
+			println!("# processor {}", i + 1);
+			print!("{code}");
+		}
+		return;
+	}
+	
+	let reg = build_registry();
+	let processor = handler.get_value(opt_processor).get_value().map_or("micro-processor", |s| s.as_str());
+	let mut schematic = match build_schematic(&reg, &programs, display, processor)
+	{
+		Ok(s) => s,
+		Err(e) =>
+		{
+			print_err!(e, "Could not build schematic");
+			process::exit(1);
+		},
+	};
+	if let Some(stem) = Path::new(path).file_stem()
+	{
+		schematic.get_tags_mut().insert("name".to_owned(), stem.to_string_lossy().into_owned());
+	}
+	let output = handler.get_value(opt_output).get_value();
+	let format = output.and_then(|o| Path::new(o).extension()).and_then(|e| e.to_str()).and_then(Format::parse).unwrap_or(Format::Base64);
+	let Some(data) = convert::write_output(&reg, &schematic, format, 16)
+	else {process::exit(1);};
+	let result = match output
+	{
+		Some(out) => fs::write(out, data),
+		None => io::stdout().write_all(&data),
+	};
+	if let Err(e) = result
+	{
+		print_err!(e, "Could not write the schematic");
+		process::exit(1);
+	}
+}
@@ -1,15 +1,33 @@
+use std::any::Any;
 use std::borrow::Cow;
 use std::env::Args;
 use std::io::{self, Write};
 use std::fs;
+use std::path::Path;
 
-use plandustry::block::{BlockRegistry, build_registry, Rotation};
+use plandustry::block::{Block, BlockRegistry, build_registry, Rotation, upgrade};
+use plandustry::block::base::RGBA;
+use plandustry::block::logic::{CanvasState, ProcessorState};
+use plandustry::content;
+use plandustry::data::command::UnitCommand;
 use plandustry::data::dynamic::DynData;
+use plandustry::data::flow::analyze;
+use plandustry::data::image;
 use plandustry::data::{base64, DataRead, Serializer, DataWrite, GridPos};
-use plandustry::data::schematic::{Placement, ResizeError, Schematic, SchematicSerializer};
+use plandustry::data::schematic::{Placement, ReplaceSummary, ResizeError, Schematic, SchematicSerializer};
+use plandustry::data::simulate::{simulate, SimulationOptions, Source};
+use plandustry::data::processors;
+use plandustry::data::autolink::autolink;
+use plandustry::data::route::{route, RouteOptions};
+use plandustry::data::substitute;
+use plandustry::fluid;
+use plandustry::item;
+use plandustry::item::storage::Storage;
+use plandustry::logic::format::{add_labels, remove_labels};
 use plandustry::registry::RegistryEntry;
+use plandustry::unit;
 
-use crate::print::print_schematic;
+use crate::print::{print_cost_delta, print_schematic};
 use crate::print_err;
 use crate::args::{self, ArgCount, ArgOption, OptionHandler};
 
@@ -212,6 +230,8 @@ impl<'l> Tokenizer<'l>
 	fn remainder(&mut self) -> Option<&'l str>
 	{
 		self.skip_ws();
+		// the line break (if any) is not part of the argument
+		self.0 = self.0.map(str::trim_end);
 		if let Some(curr) = self.0
 		{
 			let bytes = curr.as_bytes();
@@ -227,9 +247,277 @@ impl<'l> Tokenizer<'l>
 	}
 }
 
+fn parse_block<'l>(reg: &'l BlockRegistry<'l>, cmd: &Command, name: Option<&str>) -> Option<&'l Block>
+{
+	match name
+	{
+		None =>
+		{
+			eprintln!("Missing argument: block name");
+			cmd.print_usage(0);
+			None
+		},
+		Some(name) =>
+		{
+			let block = reg.get(name);
+			if block.is_none() {eprintln!("No such block {name:?}");}
+			block
+		},
+	}
+}
+
+fn parse_bool(arg: &str) -> Option<bool>
+{
+	match arg
+	{
+		"true" | "yes" => Some(true),
+		"false" | "no" => Some(false),
+		_ => None,
+	}
+}
+
+fn parse_rotation(arg: &str) -> Option<Rotation>
+{
+	match arg
+	{
+		"right" | "east" => Some(Rotation::Right),
+		"up" | "north" => Some(Rotation::Up),
+		"left" | "west" => Some(Rotation::Left),
+		"down" | "south" => Some(Rotation::Down),
+		_ => None,
+	}
+}
+
+// parses the optional rotation and replacement flag shared by the placement commands
+fn parse_placement(tokens: &mut Tokenizer) -> Option<(Rotation, bool)>
+{
+	let rot = match tokens.next()
+	{
+		None => return Some((Rotation::Right, false)),
+		Some(rot) =>
+		{
+			let Some(rot) = parse_rotation(rot)
+			else
+			{
+				eprintln!("Invalid rotation {rot:?}");
+				return None;
+			};
+			rot
+		},
+	};
+	match tokens.next()
+	{
+		None => Some((rot, false)),
+		Some(arg) =>
+		{
+			let replace = parse_bool(arg);
+			if replace.is_none() {eprintln!("Invalid replacement {arg:?}");}
+			replace.map(|replace| (rot, replace))
+		},
+	}
+}
+
+// places the block wherever possible, returns the number of placed and skipped blocks
+fn place_all<'l, I: Iterator<Item = (u16, u16, Rotation)>>(schematic: &mut Schematic<'l>, block: &'l Block, replace: bool, positions: I) -> (u32, u32)
+{
+	let mut placed = 0u32;
+	let mut skipped = 0u32;
+	for (x, y, rot) in positions
+	{
+		let ok = if replace
+		{
+			schematic.replace(x, y, block, DynData::Empty, rot, false).is_ok()
+		}
+		else
+		{
+			schematic.set(x, y, block, DynData::Empty, rot).is_ok()
+		};
+		if ok {placed += 1;} else {skipped += 1;}
+	}
+	(placed, skipped)
+}
+
+// parses an optional region given by two corners, returns its position and size
+fn parse_region(schematic: &Schematic, cmd: &Command, tokens: &mut Tokenizer) -> Option<Option<(u16, u16, u16, u16)>>
+{
+	let arg = tokens.next();
+	if arg.is_none() {return Some(None);}
+	let mut corners = [(0u16, 0u16); 2];
+	for (i, corner) in corners.iter_mut().enumerate()
+	{
+		let coord = |name: &str, arg: Option<&str>|
+		{
+			match arg.map(str::parse::<u16>)
+			{
+				None =>
+				{
+					eprintln!("Missing argument: {name}{i}");
+					cmd.print_usage(0);
+					None
+				},
+				Some(Err(e)) =>
+				{
+					print_err!(e, "Could not parse {name}{i}");
+					cmd.print_usage(0);
+					None
+				},
+				Some(Ok(v)) => Some(v),
+			}
+		};
+		let x = coord("x", if i == 0 {arg} else {tokens.next()})?;
+		let y = coord("y", tokens.next())?;
+		if x >= schematic.get_width() || y >= schematic.get_height()
+		{
+			eprintln!("Invalid coordinate ({x} / {y}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+			return None;
+		}
+		*corner = (x, y);
+	}
+	let [(x0, y0), (x1, y1)] = corners;
+	Some(Some((x0.min(x1), y0.min(y1), x0.abs_diff(x1) + 1, y0.abs_diff(y1) + 1)))
+}
+
+fn print_replace(summary: &ReplaceSummary)
+{
+	println!("Replaced {} blocks", summary.replaced);
+	if !summary.reset.is_empty()
+	{
+		let pos = Vec::from_iter(summary.reset.iter().map(|p| format!("{} / {}", p.0, p.1)));
+		println!("Configuration could not be kept at {}", pos.join(", "));
+	}
+	if !summary.skipped.is_empty()
+	{
+		let pos = Vec::from_iter(summary.skipped.iter().map(|p| format!("{} / {}", p.0, p.1)));
+		println!("Skipped {} blocks without enough space at {}", summary.skipped.len(), pos.join(", "));
+	}
+	print_cost_delta(&summary.cost_before, &summary.cost_after);
+}
+
+fn format_config(state: &dyn Any, data: &DynData) -> String
+{
+	// some blocks store their configuration in a way that isn't meaningful on its own
+	if let Some(rgba) = state.downcast_ref::<RGBA>()
+	{
+		rgba.to_string()
+	}
+	else if let Some(unit) = state.downcast_ref::<Option<unit::Type>>()
+	{
+		match unit
+		{
+			None => "none".to_string(),
+			Some(unit) => format!("{} ({data})", content::Content::get_name(unit)),
+		}
+	}
+	else if let Some(proc) = state.downcast_ref::<ProcessorState>()
+	{
+		format!("{} lines of code, {} links", proc.get_code().lines().count(), proc.get_links().len())
+	}
+	else {data.to_string()}
+}
+
+fn parse_config(state: &dyn Any, value: &str) -> Result<DynData, Cow<'static, str>>
+{
+	let clear = matches!(value, "none" | "clear");
+	if state.is::<Option<item::Type>>()
+	{
+		if clear {return Ok(DynData::Empty);}
+		match item::Type::by_name(value)
+		{
+			Some(item) => Ok(DynData::Content(content::Type::Item, item.into())),
+			None => Err(Cow::Owned(format!("No such item {value:?}"))),
+		}
+	}
+	else if state.is::<Option<fluid::Type>>()
+	{
+		if clear {return Ok(DynData::Empty);}
+		match fluid::Type::by_name(value)
+		{
+			Some(fluid) => Ok(DynData::Content(content::Type::Fluid, fluid.into())),
+			None => Err(Cow::Owned(format!("No such fluid {value:?}"))),
+		}
+	}
+	else if state.is::<Option<unit::Type>>()
+	{
+		if clear {return Ok(DynData::Int(-1));}
+		match unit::Type::by_name(value)
+		{
+			Some(unit) => Ok(DynData::Content(content::Type::Unit, unit.into())),
+			None => Err(Cow::Owned(format!("No such unit {value:?}"))),
+		}
+	}
+	else if state.is::<Option<UnitCommand>>()
+	{
+		match value
+		{
+			"none" | "clear" => Ok(DynData::Empty),
+			"move" => Ok(DynData::UnitCommand(UnitCommand::Move)),
+			"repair" => Ok(DynData::UnitCommand(UnitCommand::Repair)),
+			"rebuild" => Ok(DynData::UnitCommand(UnitCommand::Rebuild)),
+			"assist" => Ok(DynData::UnitCommand(UnitCommand::Assist)),
+			"mine" => Ok(DynData::UnitCommand(UnitCommand::Mine)),
+			"boost" => Ok(DynData::UnitCommand(UnitCommand::Boost)),
+			_ => Err(Cow::Owned(format!("No such unit command {value:?}"))),
+		}
+	}
+	else if state.is::<RGBA>()
+	{
+		let hex = value.strip_prefix('#').unwrap_or(value);
+		match u32::from_str_radix(hex, 16)
+		{
+			Ok(rgb) if hex.len() == 6 => Ok(DynData::Int(((rgb << 8) | 0xFF) as i32)),
+			Ok(rgba) if hex.len() == 8 => Ok(DynData::Int(rgba as i32)),
+			_ => Err(Cow::Owned(format!("Invalid color {value:?}, expected hexadecimal RRGGBB or RRGGBBAA"))),
+		}
+	}
+	else if state.is::<String>()
+	{
+		// the game uses actual line breaks, which can't be typed into the prompt
+		Ok(DynData::String(Some(value.replace("\\n", "\n"))))
+	}
+	else if state.is::<bool>()
+	{
+		match value
+		{
+			"true" | "yes" | "on" | "open" | "enabled" => Ok(DynData::Boolean(true)),
+			"false" | "no" | "off" | "closed" | "disabled" => Ok(DynData::Boolean(false)),
+			_ => Err(Cow::Owned(format!("Invalid boolean {value:?}"))),
+		}
+	}
+	else if state.is::<Option<(i32, i32)>>()
+	{
+		if clear {return Ok(DynData::Empty);}
+		let mut tokens = Tokenizer(Some(value));
+		match (tokens.next().map(str::parse), tokens.next().map(str::parse), tokens.remainder())
+		{
+			(Some(Ok(dx)), Some(Ok(dy)), None) => Ok(DynData::Point2(dx, dy)),
+			_ => Err(Cow::Owned(format!("Invalid offset {value:?}, expected <dx> <dy>"))),
+		}
+	}
+	else if state.is::<Vec<(i16, i16)>>()
+	{
+		if clear {return Ok(DynData::Point2Array(Vec::new()));}
+		let mut links = Vec::new();
+		let mut tokens = Tokenizer(Some(value));
+		while let Some(link) = tokens.next()
+		{
+			match link.split_once(',').map(|(dx, dy)| (dx.parse(), dy.parse()))
+			{
+				Some((Ok(dx), Ok(dy))) => links.push((dx, dy)),
+				_ => return Err(Cow::Owned(format!("Invalid link {link:?}, expected <dx>,<dy>"))),
+			}
+		}
+		Ok(DynData::Point2Array(links))
+	}
+	else if state.is::<ProcessorState>()
+	{
+		Err(Cow::Borrowed("Processor code and links cannot be configured this way"))
+	}
+	else {Err(Cow::Borrowed("Unsupported configuration type"))}
+}
+
 enum Command
 {
-	Help, New, Input, Load, Place, Rotate, Mirror, Move, Resize, Remove, Sub, Print, Dump, Save, Quit
+	Help, New, Input, Load, Place, Fill, Line, Route, Autolink, Flow, Simulate, Outline, Rotate, Mirror, Move, Resize, Remove, Replace, Upgrade, Budget, Config, Code, Canvas, Sub, Name, Description, Labels, Tag, Print, Dump, Save, Quit
 }
 
 impl Command
@@ -243,12 +531,29 @@ impl Command
 			Self::Input => println!("{:<indent$}Loads a new schematic from a base-64 encoded string", "\"input\":"),
 			Self::Load => println!("{:<indent$}Loads a new schematic from a file", "\"load\":"),
 			Self::Place => println!("{:<indent$}Places a block if enough space is available", "\"place\":"),
+			Self::Fill => println!("{:<indent$}Fills a region with as many blocks as fit", "\"fill\":"),
+			Self::Line => println!("{:<indent$}Places blocks along a path, facing the direction of travel", "\"line\":"),
+			Self::Route => println!("{:<indent$}Connects two tiles with conveyors along the shortest free path", "\"route\":"),
+			Self::Autolink => println!("{:<indent$}Links power nodes to the blocks and networks in their laser range", "\"autolink\":"),
+			Self::Flow => println!("{:<indent$}Lists conveyors that lead nowhere or into each other, loops and bridges without an outlet", "\"flow\":"),
+			Self::Simulate => println!("{:<indent$}Moves items through the transport blocks and lists where they arrive", "\"simulate\":"),
+			Self::Outline => println!("{:<indent$}Places blocks along the edges of a region", "\"outline\":"),
 			Self::Rotate => println!("{:<indent$}Rotates the schematic (CCW) in increments of 90 degrees", "\"rotate\":"),
 			Self::Mirror => println!("{:<indent$}Mirrors the schematic horizontally or vertically", "\"mirror\":"),
 			Self::Move => println!("{:<indent$}Moves all blocks by a certain offset", "\"move\":"),
 			Self::Resize => println!("{:<indent$}Resizes the schematic and offsets it", "\"resize\":"),
 			Self::Remove => println!("{:<indent$}Removes blocks at a position or within a region", "\"remove\":"),
+			Self::Replace => println!("{:<indent$}Replaces every instance of a block with another", "\"replace\":"),
+			Self::Upgrade => println!("{:<indent$}Replaces blocks with their next higher or lower tier", "\"upgrade\":"),
+			Self::Budget => println!("{:<indent$}Substitutes cheaper blocks until the schematic fits the resources", "\"budget\":"),
+			Self::Config => println!("{:<indent$}Prints or changes the configuration of a block", "\"config\":"),
+			Self::Code => println!("{:<indent$}Lists, shows, exports, imports or replaces the code of processors", "\"code\":"),
+			Self::Canvas => println!("{:<indent$}Shows or changes the pixels of a canvas", "\"canvas\":"),
 			Self::Sub => println!("{:<indent$}Various commands for editing subregions", "\"sub\":"),
+			Self::Name => println!("{:<indent$}Prints or changes the schematic's name", "\"name\":"),
+			Self::Description => println!("{:<indent$}Prints or changes the schematic's description", "\"desc\":"),
+			Self::Labels => println!("{:<indent$}Lists, adds or removes the schematic's labels", "\"labels\":"),
+			Self::Tag => println!("{:<indent$}Lists, prints, changes or removes raw metadata tags", "\"tag\":"),
 			Self::Print => println!("{:<indent$}Prints the schematic in a visual representation", "\"print\":"),
 			Self::Dump => println!("{:<indent$}Prints the schematic as a base-64 encoded string", "\"dump\":"),
 			Self::Save => println!("{:<indent$}Saves the schematic to a file", "\"save\":"),
@@ -270,12 +575,66 @@ impl Command
 				println!(r#"{:indent$}  Usage: "place" <x> <y> <block name> [<rotation> [<replace>]]"#, "");
 				println!(r#"{:indent$}  Rotation is one of right, up, left, down or compass angles"#, "")
 			},
+			Self::Fill => println!(r#"{:indent$}  Usage: "fill" <x0> <y0> <x1> <y1> <block name> [<rotation> [<replace>]]"#, ""),
+			Self::Line =>
+			{
+				println!(r#"{:indent$}  Usage: "line" <x0> <y0> <x1> <y1> [<x2> <y2> ...] <block name> [<replace>]"#, "");
+				println!(r#"{:indent$}  Diagonal segments move horizontally first, then vertically"#, "")
+			},
+			Self::Route =>
+			{
+				println!(r#"{:indent$}  Usage: "route" <x0> <y0> <x1> <y1> [<conveyor> [<bridge> | "none" [<rotation>]]]"#, "");
+				println!(r#"{:indent$}  Both tiles must be empty, the last conveyor faces the given rotation or the direction of travel"#, "");
+				println!(r#"{:indent$}  Conveyors in the way are crossed with junctions, other blocks are bridged (bridge-conveyor by default)"#, "")
+			},
+			Self::Autolink =>
+			{
+				println!(r#"{:indent$}  Usage: "autolink" ["clear"]"#, "");
+				println!(r#"{:indent$}  Keeps existing links unless "clear" is given, in which case all nodes are relinked"#, "")
+			},
+			Self::Flow => println!(r#"{:indent$}  Usage: "flow""#, ""),
+			Self::Simulate =>
+			{
+				println!(r#"{:indent$}  Usage: "simulate" [<seconds> [<x> <y> <item name> <rate> ...]]"#, "");
+				println!(r#"{:indent$}  Items come from configured item sources and unloaders and the given blocks, at a rate in items per second"#, "")
+			},
+			Self::Outline => println!(r#"{:indent$}  Usage: "outline" <x0> <y0> <x1> <y1> <block name> [<rotation> [<replace>]]"#, ""),
 			Self::Rotate => println!(r#"{:indent$}  Usage: "rotate" <angle>"#, ""),
 			Self::Mirror => println!(r#"{:indent$}  Usage: "mirror" <axis>"#, ""),
 			Self::Move => println!(r#"{:indent$}  Usage: "move" <dx> <dy>"#, ""),
 			Self::Resize => println!(r#"{:indent$}  Usage: "resize" <width> <height> [<dx> <dy>]"#, ""),
 			Self::Remove => println!(r#"{:indent$}  Usage: "remove" <x0> <y0> [<x1> <y1>]"#, ""),
+			Self::Replace => println!(r#"{:indent$}  Usage: "replace" <block name> <block name> [<x0> <y0> <x1> <y1>]"#, ""),
+			Self::Upgrade => println!(r#"{:indent$}  Usage: "upgrade" | "downgrade" <block name | "all"> [<x0> <y0> <x1> <y1>]"#, ""),
+			Self::Budget =>
+			{
+				println!(r#"{:indent$}  Usage: "budget" ["apply"] <item name> <amount> [<item name> <amount> ...]"#, "");
+				println!(r#"{:indent$}  Only lists the substitutions unless "apply" is given"#, "")
+			},
+			Self::Config =>
+			{
+				println!(r#"{:indent$}  Usage: "config" <x> <y> [<value>]"#, "");
+				println!(r#"{:indent$}  Value is an item, fluid, unit or command name, an RGBA hex color, text, a boolean,"#, "");
+				println!(r#"{:indent$}  an offset <dx> <dy>, a list of links <dx>,<dy> or "none" depending on the block"#, "")
+			},
+			Self::Code =>
+			{
+				println!(r#"{:indent$}  Usage: "code" ["list" | "show" <x> <y> | "export" <directory> | "import" <directory> | "replace" <old path | x y> <new path>]"#, "");
+				println!(r#"{:indent$}  Show and export use labels instead of numeric jumps, import and replace turn them back into numbers"#, "");
+				println!(r#"{:indent$}  Export writes a file per processor and a manifest.json with positions and links, which import reads"#, "");
+				println!(r#"{:indent$}  Replace sets the code of all processors running the same code as the file or the processor at x / y"#, "")
+			},
+			Self::Canvas =>
+			{
+				println!(r#"{:indent$}  Usage: "canvas" <x> <y> ["show" | "set" <px> <py> <color> | "import" <image path>]"#, "");
+				println!(r#"{:indent$}  Pixels count from the top left, colors are palette indices from 0 to 7"#, "");
+				println!(r#"{:indent$}  Import scales a PNG or PPM image to the canvas and picks the closest colors"#, "")
+			},
 			Self::Sub => println!(r#"{:indent$}  Usage: "sub" ... (see "sub help")"#, ""),
+			Self::Name => println!(r#"{:indent$}  Usage: "name" [<name>]"#, ""),
+			Self::Description => println!(r#"{:indent$}  Usage: "desc" [<description>]"#, ""),
+			Self::Labels => println!(r#"{:indent$}  Usage: "labels" ["list" | "clear" | "add" <label> | "remove" <label>]"#, ""),
+			Self::Tag => println!(r#"{:indent$}  Usage: "tag" ["list" | "get" <key> | "set" <key> <value> | "remove" <key>]"#, ""),
 			Self::Print | Self::Dump => (),
 			Self::Save => println!(r#"{:indent$}  Usage: "save" <save path>"#, ""),
 			Self::Quit => (),
@@ -331,10 +690,27 @@ fn interpret(state: &mut State, cmd: &str)
 			Command::Input.print_help(INDENT);
 			Command::Load.print_help(INDENT);
 			Command::Place.print_help(INDENT);
+			Command::Fill.print_help(INDENT);
+			Command::Line.print_help(INDENT);
+			Command::Route.print_help(INDENT);
+			Command::Autolink.print_help(INDENT);
+			Command::Flow.print_help(INDENT);
+			Command::Simulate.print_help(INDENT);
+			Command::Outline.print_help(INDENT);
 			Command::Rotate.print_help(INDENT);
 			Command::Mirror.print_help(INDENT);
 			Command::Remove.print_help(INDENT);
+			Command::Replace.print_help(INDENT);
+			Command::Upgrade.print_help(INDENT);
+			Command::Budget.print_help(INDENT);
+			Command::Config.print_help(INDENT);
+			Command::Code.print_help(INDENT);
+			Command::Canvas.print_help(INDENT);
 			Command::Sub.print_help(INDENT);
+			Command::Name.print_help(INDENT);
+			Command::Description.print_help(INDENT);
+			Command::Labels.print_help(INDENT);
+			Command::Tag.print_help(INDENT);
 			Command::Print.print_help(INDENT);
 			Command::Dump.print_help(INDENT);
 			Command::Save.print_help(INDENT);
@@ -484,42 +860,15 @@ fn interpret(state: &mut State, cmd: &str)
 					}
 				},
 			};
-			let rot = match tokens.next()
-			{
-				None => None,
-				Some("right") | Some("east") => Some(Rotation::Right),
-				Some("up") | Some("north") => Some(Rotation::Up),
-				Some("left") | Some("west") => Some(Rotation::Left),
-				Some("down") | Some("south") => Some(Rotation::Down),
-				Some(rot) =>
-				{
-					eprintln!("Invalid rotation {rot:?}");
-					return;
-				},
-			};
-			let replace = if rot.is_some()
-			{
-				match tokens.next()
-				{
-					None => None,
-					Some("true") | Some("yes") => Some(true),
-					Some("false") | Some("no") => Some(false),
-					Some(replace) =>
-					{
-						eprintln!("Invalid replacement {replace:?}");
-						return;
-					},
-				}
-			}
-			else {None};
+			let Some((rot, replace)) = parse_placement(&mut tokens)
+			else {return;};
 			if tokens.remainder().is_some()
 			{
 				eprintln!(r#"Too many parameters for "place""#);
 				Command::Place.print_usage(0);
 				return;
 			}
-			let rot = rot.unwrap_or(Rotation::Right);
-			let result = if replace.unwrap_or(false)
+			let result = if replace
 			{
 				schematic.replace(x, y, block, DynData::Empty, rot, false).err()
 			}
@@ -532,249 +881,1312 @@ fn interpret(state: &mut State, cmd: &str)
 				print_err!(e, "Failed to place block at {x} / {y}");
 				return;
 			}
+			state.unsaved = true;
 		},
-		Some("rotate") =>
+		Some(name @ ("fill" | "outline")) =>
 		{
+			let cmd = if name == "fill" {Command::Fill} else {Command::Outline};
 			let Some(ref mut schematic) = state.schematic
 			else
 			{
-				eprintln!(r#"Command "rotate" requires an active schematic (see "help")"#);
+				eprintln!(r#"Command "{name}" requires an active schematic (see "help")"#);
 				return;
 			};
-			let angle = parse_num!(Command::Rotate, tokens, "angle", i32);
-			if angle % 90 != 0
+			let x0 = parse_num!(cmd, tokens, "x0", u16);
+			let y0 = parse_num!(cmd, tokens, "y0", u16);
+			let x1 = parse_num!(cmd, tokens, "x1", u16);
+			let y1 = parse_num!(cmd, tokens, "y1", u16);
+			for (x, y) in [(x0, y0), (x1, y1)]
 			{
-				eprintln!("Rotation angle must be a multiple of 90 degrees");
+				if x >= schematic.get_width() || y >= schematic.get_height()
+				{
+					eprintln!("Invalid coordinate ({x} / {y}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+					return;
+				}
+			}
+			let Some(block) = parse_block(state.reg, &cmd, tokens.next())
+			else {return;};
+			let Some((rot, replace)) = parse_placement(&mut tokens)
+			else {return;};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "{name}""#);
+				cmd.print_usage(0);
+				return;
+			}
+			// tile the region with the block's footprint, leaving out any remainder at the top and right
+			let (x0, y0, x1, y1) = (x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1));
+			let sz = block.get_size() as u16;
+			let off = (sz - 1) / 2;
+			let (nx, ny) = ((x1 - x0 + 1) / sz, (y1 - y0 + 1) / sz);
+			let outline = name == "outline";
+			let positions = (0..ny).flat_map(|ty| (0..nx).map(move |tx| (tx, ty)))
+				.filter(|&(tx, ty)| !outline || tx == 0 || ty == 0 || tx == nx - 1 || ty == ny - 1)
+				.map(|(tx, ty)| (x0 + tx * sz + off, y0 + ty * sz + off, rot));
+			let (placed, skipped) = place_all(schematic, block, replace, positions);
+			println!("Placed {placed} blocks, skipped {skipped}");
+			if placed > 0 {state.unsaved = true;}
+		},
+		Some("line") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "line" requires an active schematic (see "help")"#);
+				return;
+			};
+			let mut points = Vec::<(u16, u16)>::new();
+			let name = loop
+			{
+				let arg = tokens.next();
+				// the first argument that isn't a number is the block name
+				if points.len() >= 2 && arg.is_some_and(|s| s.parse::<u16>().is_err()) {break arg;}
+				let x = parse_num!(Command::Line, "x", <u16>::from(arg));
+				let y = parse_num!(Command::Line, tokens, "y", u16);
+				if x >= schematic.get_width() || y >= schematic.get_height()
+				{
+					eprintln!("Invalid coordinate ({x} / {y}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+					return;
+				}
+				points.push((x, y));
+			};
+			let Some(block) = parse_block(state.reg, &Command::Line, name)
+			else {return;};
+			let replace = match tokens.next()
+			{
+				None => false,
+				Some(arg) =>
+				{
+					let Some(replace) = parse_bool(arg)
+					else
+					{
+						eprintln!("Invalid replacement {arg:?}");
+						return;
+					};
+					replace
+				},
+			};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "line""#);
+				Command::Line.print_usage(0);
+				return;
+			}
+			// walk the path in steps of the block size
+			let sz = block.get_size() as i32;
+			let mut path = vec![(points[0].0 as i32, points[0].1 as i32)];
+			for &(tx, ty) in &points[1..]
+			{
+				let (mut x, mut y) = *path.last().unwrap();
+				while (tx as i32 - x).abs() >= sz
+				{
+					x += sz * (tx as i32 - x).signum();
+					path.push((x, y));
+				}
+				while (ty as i32 - y).abs() >= sz
+				{
+					y += sz * (ty as i32 - y).signum();
+					path.push((x, y));
+				}
+			}
+			// each block faces the next one, the last keeps the direction it was entered from
+			let mut rot = Rotation::Right;
+			let positions = (0..path.len()).map(|i|
+			{
+				let (x, y) = path[i];
+				if let Some(&(nx, ny)) = path.get(i + 1)
+				{
+					rot = match (nx - x, ny - y)
+					{
+						(dx, _) if dx > 0 => Rotation::Right,
+						(dx, _) if dx < 0 => Rotation::Left,
+						(_, dy) if dy > 0 => Rotation::Up,
+						_ => Rotation::Down,
+					};
+				}
+				(x as u16, y as u16, rot)
+			});
+			let (placed, skipped) = place_all(schematic, block, replace, positions);
+			println!("Placed {placed} blocks, skipped {skipped}");
+			if placed > 0 {state.unsaved = true;}
+		},
+		Some("route") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "route" requires an active schematic (see "help")"#);
 				return;
+			};
+			let x0 = parse_num!(Command::Route, tokens, "x0", u16);
+			let y0 = parse_num!(Command::Route, tokens, "y0", u16);
+			let x1 = parse_num!(Command::Route, tokens, "x1", u16);
+			let y1 = parse_num!(Command::Route, tokens, "y1", u16);
+			let Some(conveyor) = parse_block(state.reg, &Command::Route, Some(tokens.next().unwrap_or("conveyor")))
+			else {return;};
+			let mut options = RouteOptions::new(conveyor);
+			options.junction = state.reg.get("junction");
+			options.bridge = match tokens.next()
+			{
+				Some("none") => None,
+				name =>
+				{
+					let Some(bridge) = parse_block(state.reg, &Command::Route, Some(name.unwrap_or("bridge-conveyor")))
+					else {return;};
+					Some(bridge)
+				},
+			};
+			if let Some(arg) = tokens.next()
+			{
+				let Some(rot) = parse_rotation(arg)
+				else
+				{
+					eprintln!("Invalid rotation {arg:?}");
+					return;
+				};
+				options.end = Some(rot);
 			}
 			if tokens.remainder().is_some()
 			{
-				eprintln!(r#"Too many parameters for "rotate""#);
-				Command::Rotate.print_usage(0);
+				eprintln!(r#"Too many parameters for "route""#);
+				Command::Route.print_usage(0);
 				return;
 			}
-			match (angle / 90) % 4
+			match route(schematic, (x0, y0), (x1, y1), &options)
 			{
-				0 => (),
-				1 | -3 =>
+				Ok(summary) =>
 				{
-					schematic.rotate(false);
+					println!("Placed {} conveyors, {} junctions and {} bridges", summary.conveyors, summary.junctions, summary.bridges);
 					state.unsaved = true;
 				},
-				2 | -2 =>
+				Err(e) => print_err!(e, "Could not route from {x0} / {y0} to {x1} / {y1}"),
+			}
+		},
+		Some("autolink") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "autolink" requires an active schematic (see "help")"#);
+				return;
+			};
+			let clear = match tokens.next()
+			{
+				None => false,
+				Some("clear") => true,
+				Some(arg) =>
+				{
+					eprintln!("Invalid argument {arg:?}, expected \"clear\"");
+					Command::Autolink.print_usage(0);
+					return;
+				},
+			};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "autolink""#);
+				Command::Autolink.print_usage(0);
+				return;
+			}
+			let summary = autolink(schematic, clear);
+			println!("Added {} links between {} nodes", summary.links, summary.nodes);
+			if summary.links > 0 || clear {state.unsaved = true;}
+		},
+		Some("flow") =>
+		{
+			let Some(ref schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "flow" requires an active schematic (see "help")"#);
+				return;
+			};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "flow""#);
+				Command::Flow.print_usage(0);
+				return;
+			}
+			let issues = analyze(schematic);
+			if issues.is_empty() {println!("No item flow problems found");}
+			for issue in issues
+			{
+				println!("{issue}");
+			}
+		},
+		Some("simulate") =>
+		{
+			let Some(ref schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "simulate" requires an active schematic (see "help")"#);
+				return;
+			};
+			let mut options = SimulationOptions::default();
+			let arg = tokens.next();
+			if arg.is_some()
+			{
+				let seconds = parse_num!(Command::Simulate, "seconds", <u16>::from(arg));
+				if seconds == 0
+				{
+					eprintln!("Expected a positive number of seconds");
+					return;
+				}
+				options.ticks = seconds as u32 * 60;
+			}
+			while let Some(arg) = tokens.next()
+			{
+				let x = parse_num!(Command::Simulate, "x", <u16>::from(Some(arg)));
+				let y = parse_num!(Command::Simulate, tokens, "y", u16);
+				let Some(name) = tokens.next()
+				else
+				{
+					eprintln!("Missing argument: item name");
+					Command::Simulate.print_usage(0);
+					return;
+				};
+				let Some(item) = item::Type::by_name(name)
+				else
+				{
+					eprintln!("No such item {name:?}");
+					return;
+				};
+				let rate = match tokens.next().map(str::parse::<f32>)
+				{
+					None =>
+					{
+						eprintln!("Missing argument: rate");
+						Command::Simulate.print_usage(0);
+						return;
+					},
+					Some(Ok(v)) => v,
+					Some(Err(e)) =>
+					{
+						print_err!(e, "Could not parse rate");
+						Command::Simulate.print_usage(0);
+						return;
+					},
+				};
+				options.sources.push(Source{pos: (x, y), item, rate});
+			}
+			match simulate(schematic, &options)
+			{
+				Ok(deliveries) =>
+				{
+					if deliveries.is_empty() {println!("No items arrived anywhere");}
+					for delivery in deliveries
+					{
+						println!("{delivery}");
+					}
+				},
+				Err(e) => print_err!(e, "Could not simulate the schematic"),
+			}
+		},
+		Some("rotate") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "rotate" requires an active schematic (see "help")"#);
+				return;
+			};
+			let angle = parse_num!(Command::Rotate, tokens, "angle", i32);
+			if angle % 90 != 0
+			{
+				eprintln!("Rotation angle must be a multiple of 90 degrees");
+				return;
+			}
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "rotate""#);
+				Command::Rotate.print_usage(0);
+				return;
+			}
+			match (angle / 90) % 4
+			{
+				0 => (),
+				1 | -3 =>
+				{
+					schematic.rotate(false);
+					state.unsaved = true;
+				},
+				2 | -2 =>
+				{
+					schematic.rotate_180();
+					state.unsaved = true;
+				},
+				3 | -1 =>
+				{
+					schematic.rotate(true);
+					state.unsaved = true;
+				},
+				a => unreachable!("angle {angle} -> {a}"),
+			}
+		},
+		Some("mirror") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "mirror" requires an active schematic (see "help")"#);
+				return;
+			};
+			let (x, y) = match tokens.next()
+			{
+				None =>
+				{
+					eprintln!("Missing argument: axis");
+					Command::Mirror.print_usage(0);
+					return;
+				},
+				Some("x") | Some("h") | Some("horizontal") | Some("horizontally") => (true, false),
+				Some("y") | Some("v") | Some("vertical") | Some("vertically") => (false, true),
+				Some("both") | Some("all") => (true, true),
+				Some(axis) =>
+				{
+					eprintln!("Invalid mirroring axis: {axis:?}");
+					return;
+				},
+			};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "mirror""#);
+				Command::Mirror.print_usage(0);
+				return;
+			}
+			schematic.mirror(x, y);
+			state.unsaved = true;
+		},
+		Some("move") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "move" requires an active schematic (see "help")"#);
+				return;
+			};
+			let dx = parse_num!(Command::Move, tokens, "dx", i16);
+			let dy = parse_num!(Command::Move, tokens, "dy", i16);
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "move""#);
+				Command::Move.print_usage(0);
+				return;
+			}
+			if dx != 0 || dy != 0
+			{
+				if let Err(e) = schematic.resize(dx, dy, schematic.get_width(), schematic.get_height())
+				{
+					match e
+					{
+						ResizeError::XOffset{dx, old_w, new_w} =>
+						{
+							debug_assert_eq!(old_w, new_w);
+							eprintln!("Invalid horizontal move {dx} not in ]-{old_w}, {new_w}[");
+						},
+						ResizeError::YOffset{dy, old_h, new_h} =>
+						{
+							debug_assert_eq!(old_h, new_h);
+							eprintln!("Invalid vertical move {dy} not in ]-{old_h}, {new_h}[");
+						},
+						ResizeError::Truncated{right, top, left, bottom} =>
+						{
+							eprint!("Move would truncate schematic: ");
+							let mut first = true;
+							if right > 0
+							{
+								eprint!("{}right: {right}", if first {""} else {", "});
+								first = false;
+							}
+							if top > 0
+							{
+								eprint!("{}top: {top}", if first {""} else {", "});
+								first = false;
+							}
+							if left > 0
+							{
+								eprint!("{}left: {left}", if first {""} else {", "});
+								first = false;
+							}
+							if bottom > 0
+							{
+								eprint!("{}bottom: {bottom}", if first {""} else {", "});
+								first = false;
+							}
+							if first {eprintln!("<unknown>");} else {eprintln!();}
+						},
+						_ => print_err!(e, "Unexpected resize error (for {dx} / {dy})")
+					}
+				}
+				else {state.unsaved = true;}
+			}
+		},
+		Some("resize") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "resize" requires an active schematic (see "help")"#);
+				return;
+			};
+			let w = parse_num!(Command::Resize, tokens, "width", u16);
+			if w == 0
+			{
+				eprintln!("Schematic width must be positive");
+				return;
+			}
+			let h = parse_num!(Command::Resize, tokens, "height", u16);
+			if h == 0
+			{
+				eprintln!("Schematic height must be positive");
+				return;
+			}
+			let (dx, dy) = if let arg @ Some(..) = tokens.next()
+			{
+				let dx = parse_num!(Command::Resize, "dx", <i16>::from(arg));
+				let dy = parse_num!(Command::Resize, tokens, "dy", i16);
+				(dx, dy)
+			}
+			else {(0, 0)};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "resize""#);
+				Command::Resize.print_usage(0);
+				return;
+			}
+			if w != schematic.get_width() || h != schematic.get_height() || dx != 0 || dy != 0
+			{
+				if let Err(e) = schematic.resize(dx, dy, w, h)
+				{
+					print_err!(e, "Could not resize schematic");
+				}
+			}
+		},
+		Some("remove") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "remove" requires an active schematic (see "help")"#);
+				return;
+			};
+			let x0 = parse_num!(Command::Remove, tokens, "x0", u16);
+			let y0 = parse_num!(Command::Remove, tokens, "y0", u16);
+			if x0 >= schematic.get_width() || y0 >= schematic.get_height()
+			{
+				eprintln!("Invalid coordinate ({x0} / {y0}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+				return;
+			}
+			let (x0, y0, x1, y1) = if let arg @ Some(..) = tokens.next()
+			{
+				let x1 = parse_num!(Command::Remove, "x1", <u16>::from(arg));
+				let y1 = parse_num!(Command::Remove, tokens, "y1", u16);
+				if x1 >= schematic.get_width() || y1 >= schematic.get_height()
+				{
+					eprintln!("Invalid coordinate ({x1} / {y1}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+					return;
+				}
+				(x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1))
+			}
+			else {(x0, y0, x0, y0)};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "remove""#);
+				Command::Remove.print_usage(0);
+				return;
+			}
+			if x1 > x0 || y1 > y0
+			{
+				let mut cnt = 0u32;
+				for y in y0..=y1
+				{
+					for x in x0..=x1
+					{
+						// position was already checked while parsing
+						if schematic.take(x, y).unwrap().is_some() {cnt += 1;}
+					}
+				}
+				println!("Removed {cnt} blocks in {x0} / {y0} to {x1} / {y1}");
+				if cnt > 0 {state.unsaved = true;}
+			}
+			else
+			{
+				// position was already checked while parsing
+				match schematic.take(x0, y0).unwrap()
+				{
+					None => (),
+					Some(p) =>
+					{
+						println!("Removed block {} from {x0} / {y0}", p.get_block().get_name());
+						state.unsaved = true;
+					},
+				}
+			}
+		},
+		Some("replace") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "replace" requires an active schematic (see "help")"#);
+				return;
+			};
+			let Some(from) = parse_block(state.reg, &Command::Replace, tokens.next())
+			else {return;};
+			let Some(to) = parse_block(state.reg, &Command::Replace, tokens.next())
+			else {return;};
+			let Some(region) = parse_region(schematic, &Command::Replace, &mut tokens)
+			else {return;};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "replace""#);
+				Command::Replace.print_usage(0);
+				return;
+			}
+			let summary = schematic.replace_all(from, to, region);
+			if summary.replaced > 0 {state.unsaved = true;}
+			print_replace(&summary);
+		},
+		Some(name @ ("upgrade" | "downgrade")) =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "{name}" requires an active schematic (see "help")"#);
+				return;
+			};
+			let up = name == "upgrade";
+			let step = |n| if up {upgrade::get_upgrade(n)} else {upgrade::get_downgrade(n)};
+			let pairs = match tokens.next()
+			{
+				None =>
+				{
+					eprintln!("Missing argument: block name");
+					Command::Upgrade.print_usage(0);
+					return;
+				},
+				Some("all") =>
+				{
+					// go against the direction of replacement so no block is replaced twice
+					let mut pairs = Vec::new();
+					for chain in upgrade::get_chains()
+					{
+						let mut chain = Vec::from_iter(chain.iter().copied());
+						if up {chain.reverse();}
+						pairs.extend(chain.into_iter().filter_map(|n| step(n).map(|t| (n, t))));
+					}
+					pairs
+				},
+				Some(from) =>
+				{
+					if state.reg.get(from).is_none()
+					{
+						eprintln!("No such block {from:?}");
+						return;
+					}
+					match step(from)
+					{
+						None =>
+						{
+							eprintln!("Block {from} has no {} tier", if up {"higher"} else {"lower"});
+							return;
+						},
+						Some(to) => vec![(from, to)],
+					}
+				},
+			};
+			let Some(region) = parse_region(schematic, &Command::Upgrade, &mut tokens)
+			else {return;};
+			if tokens.remainder().is_some()
+			{
+				eprintln!(r#"Too many parameters for "{name}""#);
+				Command::Upgrade.print_usage(0);
+				return;
+			}
+			let mut total: Option<ReplaceSummary> = None;
+			for (from, to) in pairs
+			{
+				// the chains only contain registered blocks
+				let summary = schematic.replace_all(state.reg.get(from).unwrap(), state.reg.get(to).unwrap(), region);
+				match total
+				{
+					None => total = Some(summary),
+					Some(ref mut total) =>
+					{
+						total.replaced += summary.replaced;
+						total.reset.extend(summary.reset);
+						total.skipped.extend(summary.skipped);
+						total.cost_after = summary.cost_after;
+					},
+				}
+			}
+			if let Some(total) = total
+			{
+				if total.replaced > 0 {state.unsaved = true;}
+				print_replace(&total);
+			}
+		},
+		Some("budget") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "budget" requires an active schematic (see "help")"#);
+				return;
+			};
+			let mut arg = tokens.next();
+			let apply = arg == Some("apply");
+			if apply {arg = tokens.next();}
+			let mut budget = Storage::new();
+			while let Some(name) = arg
+			{
+				let Some(item) = item::Type::by_name(name)
+				else
+				{
+					eprintln!("No such item {name:?}");
+					return;
+				};
+				let amount = match tokens.next().map(str::parse::<u32>)
+				{
+					None =>
+					{
+						eprintln!("Missing argument: amount");
+						Command::Budget.print_usage(0);
+						return;
+					},
+					Some(Err(e)) =>
+					{
+						print_err!(e, "Could not parse amount");
+						Command::Budget.print_usage(0);
+						return;
+					},
+					Some(Ok(v)) => v,
+				};
+				budget.add(item, amount, u32::MAX);
+				arg = tokens.next();
+			}
+			let mut copy;
+			let target = if apply {schematic}
+			else
+			{
+				copy = schematic.clone();
+				&mut copy
+			};
+			let summary = substitute::fit_budget(target, state.reg, &budget);
+			for sub in summary.changed.iter()
+			{
+				println!("{} at {} / {} -> {}", sub.from.get_name(), sub.pos.0, sub.pos.1, sub.to.get_name());
+			}
+			println!("{} blocks {}", summary.changed.len(), if apply {"substituted"} else {"can be substituted"});
+			if summary.shortfall.is_empty() {println!("The schematic fits the budget");}
+			else {println!("Still missing: {}", summary.shortfall);}
+			if apply && !summary.changed.is_empty() {state.unsaved = true;}
+		},
+		Some("config") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "config" requires an active schematic (see "help")"#);
+				return;
+			};
+			let x = parse_num!(Command::Config, tokens, "x", u16);
+			let y = parse_num!(Command::Config, tokens, "y", u16);
+			if x >= schematic.get_width() || y >= schematic.get_height()
+			{
+				eprintln!("Invalid coordinate ({x} / {y}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
+				return;
+			}
+			// position was already checked while parsing
+			let Some(placement) = schematic.get_mut(x, y).unwrap()
+			else
+			{
+				eprintln!("No block at {x} / {y}");
+				return;
+			};
+			let name = placement.get_block().get_name();
+			match tokens.remainder()
+			{
+				None =>
+				{
+					match placement.get_state()
+					{
+						None => println!("Block {name} at {x} / {y} has no configuration"),
+						Some(state) =>
+						{
+							match placement.get_block().serialize_state(state)
+							{
+								Ok(data) => println!("Block {name} at {x} / {y}: {}", format_config(state, &data)),
+								Err(e) => print_err!(e, "Could not serialize configuration of {name} at {x} / {y}"),
+							}
+						},
+					}
+				},
+				Some(value) =>
+				{
+					let Some(curr) = placement.get_state()
+					else
+					{
+						eprintln!("Block {name} at {x} / {y} has no configuration");
+						return;
+					};
+					let data = match parse_config(curr, value)
+					{
+						Ok(data) => data,
+						Err(e) =>
+						{
+							eprintln!("{e}");
+							Command::Config.print_usage(0);
+							return;
+						},
+					};
+					match placement.set_state(data)
+					{
+						Ok(..) => state.unsaved = true,
+						Err(e) => print_err!(e, "Could not configure {name} at {x} / {y}"),
+					}
+				},
+			}
+		},
+		Some("code") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "code" requires an active schematic (see "help")"#);
+				return;
+			};
+			match tokens.next()
+			{
+				None | Some("list") =>
+				{
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "code list""#);
+						Command::Code.print_usage(0);
+						return;
+					}
+					let exported = processors::export(schematic);
+					if exported.is_empty() {println!("The schematic has no processors");}
+					for (entry, code) in exported
+					{
+						let links = Vec::from_iter(entry.links.iter().map(|(name, ..)| name.as_str()));
+						println!("{} at {} / {}: {} lines of code, links: {}", entry.block, entry.x, entry.y, code.lines().count(),
+							if links.is_empty() {"none".to_string()} else {links.join(", ")});
+					}
+				},
+				Some("show") =>
+				{
+					let (Some(x), Some(y), None) = (tokens.next(), tokens.next(), tokens.remainder())
+					else
+					{
+						eprintln!(r#"Expected a position for "code show""#);
+						Command::Code.print_usage(0);
+						return;
+					};
+					let x = parse_num!(Command::Code, "x", <u16>::from(Some(x)));
+					let y = parse_num!(Command::Code, "y", <u16>::from(Some(y)));
+					let state = schematic.get(x, y).ok().flatten().and_then(|p| p.get_state());
+					match state.and_then(|s| s.downcast_ref::<ProcessorState>())
+					{
+						Some(proc) => print!("{}", add_labels(proc.get_code())),
+						None => eprintln!("No processor at {x} / {y}"),
+					}
+				},
+				Some("export") =>
+				{
+					let (Some(dir), None) = (tokens.next(), tokens.remainder())
+					else
+					{
+						eprintln!(r#"Expected a single directory for "code export""#);
+						Command::Code.print_usage(0);
+						return;
+					};
+					let exported = processors::export(schematic);
+					if let Err(e) = fs::create_dir_all(dir)
+					{
+						print_err!(e, "Could not create directory {dir:?}");
+						return;
+					}
+					for (entry, code) in exported.iter()
+					{
+						let path = Path::new(dir).join(&entry.file);
+						if let Err(e) = fs::write(&path, add_labels(code))
+						{
+							print_err!(e, "Could not write to file {path:?}");
+							return;
+						}
+					}
+					let entries = Vec::from_iter(exported.into_iter().map(|(e, _)| e));
+					let path = Path::new(dir).join("manifest.json");
+					if let Err(e) = fs::write(&path, processors::write_manifest(&entries))
+					{
+						print_err!(e, "Could not write to file {path:?}");
+						return;
+					}
+					println!("Exported {} processors to {dir}", entries.len());
+				},
+				Some("import") =>
+				{
+					let (Some(dir), None) = (tokens.next(), tokens.remainder())
+					else
+					{
+						eprintln!(r#"Expected a single directory for "code import""#);
+						Command::Code.print_usage(0);
+						return;
+					};
+					let path = Path::new(dir).join("manifest.json");
+					let entries = match fs::read_to_string(&path).map(|t| processors::read_manifest(&t))
+					{
+						Ok(Ok(entries)) => entries,
+						Ok(Err(e)) =>
+						{
+							print_err!(e, "Could not read manifest {path:?}");
+							return;
+						},
+						Err(e) =>
+						{
+							print_err!(e, "Could not read file {path:?}");
+							return;
+						},
+					};
+					// read everything first so a missing file doesn't leave the schematic half imported
+					let mut code = Vec::with_capacity(entries.len());
+					for entry in entries.iter()
+					{
+						let path = Path::new(dir).join(&entry.file);
+						match fs::read_to_string(&path)
+						{
+							Ok(c) => code.push(c),
+							Err(e) =>
+							{
+								print_err!(e, "Could not read file {path:?}");
+								return;
+							},
+						}
+					}
+					let mut imported = 0usize;
+					for (entry, code) in entries.iter().zip(code)
+					{
+						// labels are only for reading, the game gets numeric jumps
+						let code = match remove_labels(&code)
+						{
+							Ok(c) => c,
+							Err(e) =>
+							{
+								print_err!(e, "Could not import {}", entry.file);
+								continue;
+							},
+						};
+						match processors::import(schematic, entry, &code)
+						{
+							Ok(()) => imported += 1,
+							Err(e) => print_err!(e, "Could not import {}", entry.file),
+						}
+					}
+					if imported > 0 {state.unsaved = true;}
+					println!("Imported {imported} of {} processors", entries.len());
+				},
+				Some("replace") =>
+				{
+					let mut args = Vec::new();
+					while let Some(arg) = tokens.next() {args.push(arg);}
+					let (old, new) = match args[..]
+					{
+						[old, new] =>
+						{
+							match fs::read_to_string(old)
+							{
+								Ok(code) => (code, new),
+								Err(e) =>
+								{
+									print_err!(e, "Could not read file {old:?}");
+									return;
+								},
+							}
+						},
+						[x, y, new] =>
+						{
+							let x = parse_num!(Command::Code, "x", <u16>::from(Some(x)));
+							let y = parse_num!(Command::Code, "y", <u16>::from(Some(y)));
+							let state = schematic.get(x, y).ok().flatten().and_then(|p| p.get_state());
+							match state.and_then(|s| s.downcast_ref::<ProcessorState>())
+							{
+								Some(proc) => (proc.get_code().to_string(), new),
+								None =>
+								{
+									eprintln!("No processor at {x} / {y}");
+									return;
+								},
+							}
+						},
+						_ =>
+						{
+							eprintln!(r#"Expected the old code and a path to the new code for "code replace""#);
+							Command::Code.print_usage(0);
+							return;
+						},
+					};
+					let new = match fs::read_to_string(new)
+					{
+						Ok(code) => code,
+						Err(e) =>
+						{
+							print_err!(e, "Could not read file {new:?}");
+							return;
+						},
+					};
+					let new = match remove_labels(&new)
+					{
+						Ok(code) => code,
+						Err(e) =>
+						{
+							print_err!(e, "Invalid labels in the new code");
+							return;
+						},
+					};
+					match processors::replace_code(schematic, &old, &new)
+					{
+						Ok(replaced) =>
+						{
+							if !replaced.is_empty() {state.unsaved = true;}
+							let pos = Vec::from_iter(replaced.iter().map(|p| format!("{} / {}", p.0, p.1)));
+							println!("Replaced the code of {} processors{}{}", replaced.len(), if pos.is_empty() {""} else {" at "}, pos.join(", "));
+						},
+						Err(e) => print_err!(e, "Could not replace code"),
+					}
+				},
+				Some(op) =>
+				{
+					eprintln!("Invalid code operation {op:?}");
+					Command::Code.print_usage(0);
+				},
+			}
+		},
+		Some("canvas") =>
+		{
+			let Some(ref mut schematic) = state.schematic
+			else
+			{
+				eprintln!(r#"Command "canvas" requires an active schematic (see "help")"#);
+				return;
+			};
+			let x = parse_num!(Command::Canvas, tokens, "x", u16);
+			let y = parse_num!(Command::Canvas, tokens, "y", u16);
+			let canvas = schematic.get_mut(x, y).ok().flatten().and_then(|p| p.get_state_mut()).and_then(|s| s.downcast_mut::<CanvasState>());
+			let Some(canvas) = canvas
+			else
+			{
+				eprintln!("No canvas at {x} / {y}");
+				return;
+			};
+			match tokens.next()
+			{
+				None | Some("show") =>
+				{
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "canvas show""#);
+						Command::Canvas.print_usage(0);
+						return;
+					}
+					println!("{canvas}");
+				},
+				Some("set") =>
+				{
+					let px = parse_num!(Command::Canvas, tokens, "pixel x", u16);
+					let py = parse_num!(Command::Canvas, tokens, "pixel y", u16);
+					let color = parse_num!(Command::Canvas, tokens, "color", u16);
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "canvas set""#);
+						Command::Canvas.print_usage(0);
+						return;
+					}
+					let Ok(color) = u8::try_from(color)
+					else
+					{
+						eprintln!("Invalid color {color}, expected 0 to 7");
+						return;
+					};
+					match canvas.set_pixel(px, py, color)
+					{
+						Ok(()) => state.unsaved = true,
+						Err(e) => print_err!(e, "Could not set pixel"),
+					}
+				},
+				Some("import") =>
 				{
-					schematic.rotate_180();
-					state.unsaved = true;
+					let (Some(path), None) = (tokens.next(), tokens.remainder())
+					else
+					{
+						eprintln!(r#"Expected a single image for "canvas import""#);
+						Command::Canvas.print_usage(0);
+						return;
+					};
+					match fs::read(path).map(|data| image::decode(&data))
+					{
+						Ok(Ok(image)) =>
+						{
+							canvas.draw_image(&image);
+							state.unsaved = true;
+							println!("{canvas}");
+						},
+						Ok(Err(e)) => print_err!(e, "Could not read image {path:?}"),
+						Err(e) => print_err!(e, "Could not read file {path:?}"),
+					}
 				},
-				3 | -1 =>
+				Some(op) =>
 				{
-					schematic.rotate(true);
-					state.unsaved = true;
+					eprintln!("Invalid canvas operation {op:?}");
+					Command::Canvas.print_usage(0);
 				},
-				a => unreachable!("angle {angle} -> {a}"),
 			}
 		},
-		Some("mirror") =>
+		Some("sub") => interpret_sub(state, &mut tokens),
+		Some("name") =>
 		{
 			let Some(ref mut schematic) = state.schematic
 			else
 			{
-				eprintln!(r#"Command "mirror" requires an active schematic (see "help")"#);
+				eprintln!(r#"Command "name" requires an active schematic (see "help")"#);
 				return;
 			};
-			let (x, y) = match tokens.next()
+			match tokens.remainder()
 			{
 				None =>
 				{
-					eprintln!("Missing argument: axis");
-					Command::Mirror.print_usage(0);
-					return;
+					match schematic.get_tags().get("name")
+					{
+						Some(name) if !name.is_empty() => println!("Name: {name}"),
+						_ => println!("The schematic has no name"),
+					}
 				},
-				Some("x") | Some("h") | Some("horizontal") | Some("horizontally") => (true, false),
-				Some("y") | Some("v") | Some("vertical") | Some("vertically") => (false, true),
-				Some("both") | Some("all") => (true, true),
-				Some(axis) =>
+				Some(name) =>
 				{
-					eprintln!("Invalid mirroring axis: {axis:?}");
-					return;
+					schematic.get_tags_mut().insert("name".to_string(), name.to_string());
+					state.unsaved = true;
 				},
-			};
-			if tokens.remainder().is_some()
-			{
-				eprintln!(r#"Too many parameters for "mirror""#);
-				Command::Mirror.print_usage(0);
-				return;
 			}
-			schematic.mirror(x, y);
-			state.unsaved = true;
 		},
-		Some("move") =>
+		Some("desc") | Some("description") =>
 		{
 			let Some(ref mut schematic) = state.schematic
 			else
 			{
-				eprintln!(r#"Command "move" requires an active schematic (see "help")"#);
+				eprintln!(r#"Command "desc" requires an active schematic (see "help")"#);
 				return;
 			};
-			let dx = parse_num!(Command::Move, tokens, "dx", i16);
-			let dy = parse_num!(Command::Move, tokens, "dy", i16);
-			if tokens.remainder().is_some()
-			{
-				eprintln!(r#"Too many parameters for "move""#);
-				Command::Move.print_usage(0);
-				return;
-			}
-			if dx != 0 && dy != 0
+			match tokens.remainder()
 			{
-				if let Err(e) = schematic.resize(dx, dy, schematic.get_width(), schematic.get_height())
+				None =>
 				{
-					match e
+					match schematic.get_tags().get("description")
 					{
-						ResizeError::XOffset{dx, old_w, new_w} =>
-						{
-							debug_assert_eq!(old_w, new_w);
-							eprintln!("Invalid horizontal move {dx} not in ]-{old_w}, {new_w}[");
-						},
-						ResizeError::YOffset{dy, old_h, new_h} =>
-						{
-							debug_assert_eq!(old_h, new_h);
-							eprintln!("Invalid vertical move {dy} not in ]-{old_h}, {new_h}[");
-						},
-						ResizeError::Truncated{right, top, left, bottom} =>
-						{
-							eprint!("Move would truncate schematic: ");
-							let mut first = true;
-							if right > 0
-							{
-								eprint!("{}right: {right}", if first {""} else {", "});
-								first = false;
-							}
-							if top > 0
-							{
-								eprint!("{}top: {top}", if first {""} else {", "});
-								first = false;
-							}
-							if left > 0
-							{
-								eprint!("{}left: {left}", if first {""} else {", "});
-								first = false;
-							}
-							if bottom > 0
-							{
-								eprint!("{}bottom: {bottom}", if first {""} else {", "});
-								first = false;
-							}
-							if first {eprintln!("<unknown>");} else {eprintln!();}
-						},
-						_ => print_err!(e, "Unexpected resize error (for {dx} / {dy})")
+						Some(desc) if !desc.is_empty() => println!("Description: {desc}"),
+						_ => println!("The schematic has no description"),
 					}
-				}
-				else {state.unsaved = true;}
+				},
+				Some(desc) =>
+				{
+					// the game uses actual line breaks, which can't be typed into the prompt
+					schematic.get_tags_mut().insert("description".to_string(), desc.replace("\\n", "\n"));
+					state.unsaved = true;
+				},
 			}
 		},
-		Some("resize") =>
+		Some("labels") =>
 		{
 			let Some(ref mut schematic) = state.schematic
 			else
 			{
-				eprintln!(r#"Command "resize" requires an active schematic (see "help")"#);
+				eprintln!(r#"Command "labels" requires an active schematic (see "help")"#);
 				return;
 			};
-			let w = parse_num!(Command::Resize, tokens, "width", u16);
-			if w == 0
-			{
-				eprintln!("Schematic width must be positive");
-				return;
-			}
-			let h = parse_num!(Command::Resize, tokens, "height", u16);
-			if h == 0
-			{
-				eprintln!("Schematic height must be positive");
-				return;
-			}
-			let (dx, dy) = if let arg @ Some(..) = tokens.next()
-			{
-				let dx = parse_num!(Command::Resize, "dx", <i16>::from(arg));
-				let dy = parse_num!(Command::Resize, tokens, "dy", i16);
-				(dx, dy)
-			}
-			else {(0, 0)};
-			if tokens.remainder().is_some()
+			let mut labels = match schematic.get_labels()
 			{
-				eprintln!(r#"Too many parameters for "resize""#);
-				Command::Resize.print_usage(0);
-				return;
-			}
-			if w != schematic.get_width() || h != schematic.get_height() || dx != 0 || dy != 0
+				Ok(labels) => labels,
+				Err(e) =>
+				{
+					print_err!(e, "Could not read schematic labels");
+					return;
+				},
+			};
+			match tokens.next()
 			{
-				if let Err(e) = schematic.resize(dx, dy, w, h)
+				None | Some("list") =>
 				{
-					print_err!(e, "Could not resize schematic");
-				}
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "labels list""#);
+						Command::Labels.print_usage(0);
+						return;
+					}
+					if labels.is_empty() {println!("The schematic has no labels");}
+					else
+					{
+						println!("Labels ({}):", labels.len());
+						for label in labels
+						{
+							println!("\t{label}");
+						}
+					}
+				},
+				Some("clear") =>
+				{
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "labels clear""#);
+						Command::Labels.print_usage(0);
+						return;
+					}
+					if !labels.is_empty()
+					{
+						schematic.set_labels::<String>(&[]);
+						state.unsaved = true;
+					}
+				},
+				Some("add") =>
+				{
+					let Some(label) = tokens.remainder()
+					else
+					{
+						eprintln!("Missing argument: label");
+						Command::Labels.print_usage(0);
+						return;
+					};
+					if labels.iter().any(|l| l == label)
+					{
+						eprintln!("The schematic already has the label {label:?}");
+						return;
+					}
+					labels.push(label.to_string());
+					schematic.set_labels(&labels);
+					state.unsaved = true;
+				},
+				Some("remove") =>
+				{
+					let Some(label) = tokens.remainder()
+					else
+					{
+						eprintln!("Missing argument: label");
+						Command::Labels.print_usage(0);
+						return;
+					};
+					let len = labels.len();
+					labels.retain(|l| l != label);
+					if labels.len() == len
+					{
+						eprintln!("The schematic has no label {label:?}");
+						return;
+					}
+					schematic.set_labels(&labels);
+					state.unsaved = true;
+				},
+				Some(op) =>
+				{
+					eprintln!("Invalid labels operation {op:?}");
+					Command::Labels.print_usage(0);
+				},
 			}
 		},
-		Some("remove") =>
+		Some("tag") =>
 		{
 			let Some(ref mut schematic) = state.schematic
 			else
 			{
-				eprintln!(r#"Command "remove" requires an active schematic (see "help")"#);
+				eprintln!(r#"Command "tag" requires an active schematic (see "help")"#);
 				return;
 			};
-			let x0 = parse_num!(Command::Remove, tokens, "x0", u16);
-			let y0 = parse_num!(Command::Remove, tokens, "y0", u16);
-			if x0 >= schematic.get_width() || y0 >= schematic.get_height()
-			{
-				eprintln!("Invalid coordinate ({x0} / {y0}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
-				return;
-			}
-			let (x0, y0, x1, y1) = if let arg @ Some(..) = tokens.next()
+			match tokens.next()
 			{
-				let x1 = parse_num!(Command::Remove, "x1", <u16>::from(arg));
-				let y1 = parse_num!(Command::Remove, tokens, "y1", u16);
-				if x1 >= schematic.get_width() || y1 >= schematic.get_height()
+				None | Some("list") =>
 				{
-					eprintln!("Invalid coordinate ({x1} / {y1}) out of bounds ({} / {})", schematic.get_width(), schematic.get_height());
-					return;
-				}
-				(x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1))
-			}
-			else {(x0, y0, x0, y0)};
-			if tokens.remainder().is_some()
-			{
-				eprintln!(r#"Too many parameters for "remove""#);
-				Command::Remove.print_usage(0);
-				return;
-			}
-			if x1 > x0 || y1 > y0
-			{
-				let mut cnt = 0u32;
-				for y in y0..=y1
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "tag list""#);
+						Command::Tag.print_usage(0);
+						return;
+					}
+					let mut tags = Vec::from_iter(schematic.get_tags());
+					tags.sort();
+					for (k, v) in tags
+					{
+						println!("{k:?}: {v:?}");
+					}
+				},
+				Some("get") =>
 				{
-					for x in x0..=x1
+					let Some(key) = tokens.next()
+					else
 					{
-						// position was already checked while parsing
-						if schematic.take(x, y).unwrap().is_some() {cnt += 1;}
+						eprintln!("Missing argument: key");
+						Command::Tag.print_usage(0);
+						return;
+					};
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "tag get""#);
+						Command::Tag.print_usage(0);
+						return;
 					}
-				}
-				println!("Removed {cnt} blocks in {x0} / {y0} to {x1} / {y1}");
-				if cnt > 0 {state.unsaved = true;}
-			}
-			else
-			{
-				// position was already checked while parsing
-				match schematic.take(x0, y0).unwrap()
+					match schematic.get_tags().get(key)
+					{
+						None => eprintln!("No such tag {key:?}"),
+						Some(v) => println!("{key:?}: {v:?}"),
+					}
+				},
+				Some("set") =>
 				{
-					None => (),
-					Some(p) =>
+					let Some(key) = tokens.next()
+					else
 					{
-						println!("Removed block {} from {x0} / {y0}", p.get_block().get_name());
-						state.unsaved = true;
-					},
-				}
+						eprintln!("Missing argument: key");
+						Command::Tag.print_usage(0);
+						return;
+					};
+					let Some(value) = tokens.remainder()
+					else
+					{
+						eprintln!("Missing argument: value");
+						Command::Tag.print_usage(0);
+						return;
+					};
+					let tags = schematic.get_tags_mut();
+					if !tags.contains_key(key) && tags.len() >= u8::MAX as usize
+					{
+						eprintln!("Too many tags (max {})", u8::MAX);
+						return;
+					}
+					tags.insert(key.to_string(), value.to_string());
+					state.unsaved = true;
+				},
+				Some("remove") =>
+				{
+					let Some(key) = tokens.next()
+					else
+					{
+						eprintln!("Missing argument: key");
+						Command::Tag.print_usage(0);
+						return;
+					};
+					if tokens.remainder().is_some()
+					{
+						eprintln!(r#"Too many parameters for "tag remove""#);
+						Command::Tag.print_usage(0);
+						return;
+					}
+					match schematic.get_tags_mut().remove(key)
+					{
+						None => eprintln!("No such tag {key:?}"),
+						Some(..) => state.unsaved = true,
+					}
+				},
+				Some(op) =>
+				{
+					eprintln!("Invalid tag operation {op:?}");
+					Command::Tag.print_usage(0);
+				},
 			}
 		},
-		Some("sub") => interpret_sub(state, &mut tokens),
 		Some("print") =>
 		{
 			let Some(ref schematic) = state.schematic
@@ -1276,7 +2688,7 @@ fn interpret_sub(state: &mut State, tokens: &mut Tokenizer)
 				SubCommand::Move.print_usage(0);
 				return;
 			}
-			if dx != 0 && dy != 0
+			if dx != 0 || dy != 0
 			{
 				if let Err(e) = subregion.resize(dx, dy, subregion.get_width(), subregion.get_height())
 				{
@@ -1464,3 +2876,28 @@ fn interpret_sub(state: &mut State, tokens: &mut Tokenizer)
 		Some(unknown) => eprintln!("Unknown command \"sub\" {unknown:?}"),
 	}
 }
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn move_blocks()
+	{
+		let reg = build_registry();
+		let mut state = State{reg: &reg, schematic: None, unsaved: false, subregion: None, quit: false};
+		interpret(&mut state, "new 4 4");
+		interpret(&mut state, "place 0 0 copper-wall");
+		// moving along a single axis must not be ignored
+		interpret(&mut state, "move 1 0");
+		interpret(&mut state, "move 0 2");
+		let schematic = state.schematic.as_ref().unwrap();
+		assert!(schematic.get(1, 2).unwrap().is_some());
+		assert!(schematic.get(0, 0).unwrap().is_none());
+		interpret(&mut state, "sub copy 0 0 3 3");
+		interpret(&mut state, "sub move -1 0");
+		let subregion = state.subregion.as_ref().unwrap();
+		assert!(subregion.get(0, 2).unwrap().is_some());
+	}
+}
@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use plandustry::block::{build_registry, BlockRegistry};
+use plandustry::data::index::{write_index, Entry};
+
+use crate::{find_files, print_err, read_schematic};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 1
+	{
+		eprintln!("Expected a single directory, got {} arguments", literals.len());
+		process::exit(1);
+	}
+	let dir = Path::new(&literals[0]);
+	if !dir.is_dir()
+	{
+		eprintln!("{dir:?} is not a directory");
+		process::exit(1);
+	}
+	let output = match handler.get_value(opt_output).get_value()
+	{
+		Some(path) => PathBuf::from(path),
+		None => dir.join("index.json"),
+	};
+	let reg = build_registry();
+	let entries = match index_dir(&reg, dir, &output)
+	{
+		Ok(e) => e,
+		Err(e) =>
+		{
+			print_err!(e, "Could not locate {dir:?} relative to {output:?}");
+			process::exit(1);
+		},
+	};
+	if let Err(e) = fs::write(&output, write_index(&entries))
+	{
+		print_err!(e, "Could not write index to {output:?}");
+		process::exit(1);
+	}
+	println!("Indexed {} schematics into {}", entries.len(), output.display());
+}
+
+// the way from one absolute path to another, assuming no symlinks in between
+fn relative_path(path: &Path, base: &Path) -> PathBuf
+{
+	let (path, base) = (Vec::from_iter(path.components()), Vec::from_iter(base.components()));
+	let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+	let mut result = PathBuf::from_iter(base[common..].iter().map(|_| Component::ParentDir));
+	result.extend(&path[common..]);
+	result
+}
+
+fn index_dir(reg: &BlockRegistry, dir: &Path, output: &Path) -> io::Result<Vec<Entry>>
+{
+	// paths are relative to the index so that search finds them wherever the index is written (and it can be moved along with them)
+	let dir_abs = fs::canonicalize(dir)?;
+	let out_dir = fs::canonicalize(output.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")))?;
+	let mut files = Vec::new();
+	find_files(dir, "msch", &mut files);
+	let mut entries = Vec::new();
+	for path in files
+	{
+		let Some(schematic) = read_schematic(reg, &path.to_string_lossy())
+		else {continue;};
+		let abs = dir_abs.join(path.strip_prefix(dir).unwrap_or(&path));
+		entries.push(Entry::new(relative_path(&abs, &out_dir).to_string_lossy().into_owned(), &schematic));
+	}
+	Ok(entries)
+}
+
+#[cfg(test)]
+mod test
+{
+	use plandustry::data::{DataWrite, Serializer};
+	use plandustry::data::schematic::{Schematic, SchematicSerializer};
+	
+	use super::*;
+	
+	#[test]
+	fn elsewhere()
+	{
+		let root = std::env::temp_dir().join(format!("plandustry-index-{}", process::id()));
+		let (lib, out) = (root.join("lib"), root.join("elsewhere"));
+		fs::create_dir_all(lib.join("sub")).unwrap();
+		fs::create_dir_all(&out).unwrap();
+		let reg = build_registry();
+		let mut data = DataWrite::new();
+		SchematicSerializer(&reg).serialize(&mut data, &Schematic::new(2, 3)).unwrap();
+		fs::write(lib.join("sub").join("a.msch"), data.get_written()).unwrap();
+		// a loop back up the tree must not be followed
+		#[cfg(unix)]
+		std::os::unix::fs::symlink(&lib, lib.join("sub").join("loop")).unwrap();
+		
+		let entries = index_dir(&reg, &lib, &out.join("index.json")).unwrap();
+		assert_eq!(Vec::from_iter(entries.iter().map(|e| e.path.as_str())), vec![Path::new("../lib/sub/a.msch").to_str().unwrap()]);
+		// search resolves paths against the directory of the index
+		assert!(out.join(&entries[0].path).is_file());
+		let entries = index_dir(&reg, &lib, &lib.join("index.json")).unwrap();
+		assert_eq!(entries[0].path, Path::new("sub/a.msch").to_str().unwrap());
+		fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -0,0 +1,297 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env::Args;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process;
+
+use plandustry::block::build_registry;
+use plandustry::block::logic::ProcessorState;
+use plandustry::data::json::{self, Value};
+use plandustry::logic::mlog::{self, CompletionKind, ConstantKind, Diagnostic, Severity};
+use plandustry::registry::RegistryEntry;
+
+use crate::{print_err, read_schematic};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+
+// language server positions count utf-16 code units, the library counts bytes
+fn to_utf16(text: &str, line: usize, col: usize) -> usize
+{
+	let text = text.lines().nth(line).unwrap_or("");
+	text.get(..col.min(text.len())).map_or(0, |s| s.encode_utf16().count())
+}
+
+fn from_utf16(text: &str, line: usize, character: usize) -> usize
+{
+	let text = text.lines().nth(line).unwrap_or("");
+	let mut units = 0;
+	for (i, c) in text.char_indices()
+	{
+		if units >= character {return i;}
+		units += c.len_utf16();
+	}
+	text.len()
+}
+
+fn position(line: usize, character: usize) -> Value
+{
+	Value::Object(vec![("line".to_owned(), Value::from(line)), ("character".to_owned(), Value::from(character))])
+}
+
+fn range(text: &str, line: usize, start: usize, end: usize) -> Value
+{
+	Value::Object(vec![
+		("start".to_owned(), position(line, to_utf16(text, line, start))),
+		("end".to_owned(), position(line, to_utf16(text, line, end))),
+	])
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>>
+{
+	let mut length = None;
+	loop
+	{
+		let mut line = String::new();
+		if input.read_line(&mut line)? == 0 {return Ok(None);}
+		let line = line.trim_end();
+		if line.is_empty() {break;}
+		if let Some((name, value)) = line.split_once(':')
+		{
+			if name.eq_ignore_ascii_case("content-length") {length = value.trim().parse::<usize>().ok();}
+		}
+	}
+	let Some(length) = length
+	else {return Err(io::Error::new(io::ErrorKind::InvalidData, "missing content length"));};
+	let mut body = vec![0; length];
+	input.read_exact(&mut body)?;
+	let body = String::from_utf8(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	json::parse(&body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(value: Value) -> io::Result<()>
+{
+	let mut value = value;
+	if let Value::Object(ref mut fields) = value {fields.insert(0, ("jsonrpc".to_owned(), Value::from("2.0")));}
+	let body = value.to_string();
+	let mut out = io::stdout().lock();
+	write!(out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+	out.flush()
+}
+
+fn notify(method: &str, params: Value) -> io::Result<()>
+{
+	write_message(Value::Object(vec![("method".to_owned(), Value::from(method)), ("params".to_owned(), params)]))
+}
+
+fn publish_diagnostics(uri: &str, text: &str) -> io::Result<()>
+{
+	let diagnostics = mlog::check(text).into_iter().map(|d| Value::Object(vec![
+		("range".to_owned(), range(text, d.line, d.start, d.end)),
+		("severity".to_owned(), Value::from(match d.severity {Severity::Error => 1, Severity::Warning => 2})),
+		("source".to_owned(), Value::from("plandustry")),
+		("message".to_owned(), Value::from(d.message)),
+	])).collect();
+	notify("textDocument/publishDiagnostics", Value::Object(vec![
+		("uri".to_owned(), Value::from(uri)),
+		("diagnostics".to_owned(), Value::Array(diagnostics)),
+	]))
+}
+
+fn completion_kind(kind: CompletionKind) -> u8
+{
+	// numbers from the specification's CompletionItemKind
+	match kind
+	{
+		CompletionKind::Instruction => 3,
+		CompletionKind::Keyword => 14,
+		CompletionKind::Constant(ConstantKind::Sensor) => 5,
+		CompletionKind::Constant(..) => 21,
+		CompletionKind::Label => 18,
+		CompletionKind::Variable => 6,
+	}
+}
+
+// the document and position of a text document request
+fn get_position<'d>(docs: &'d HashMap<String, String>, params: &Value) -> Option<(&'d str, &'d str, usize, usize)>
+{
+	let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+	let (uri, text) = docs.get_key_value(uri)?;
+	let pos = params.get("position")?;
+	let line = pos.get("line")?.as_i64().and_then(|v| usize::try_from(v).ok())?;
+	let character = pos.get("character")?.as_i64().and_then(|v| usize::try_from(v).ok())?;
+	Some((uri, text, line, from_utf16(text, line, character)))
+}
+
+fn handle_request(docs: &HashMap<String, String>, method: &str, params: &Value) -> Option<Value>
+{
+	match method
+	{
+		"initialize" => Some(Value::Object(vec![
+			("capabilities".to_owned(), Value::Object(vec![
+				// full text on every change, documents are small
+				("textDocumentSync".to_owned(), Value::from(1)),
+				("completionProvider".to_owned(), Value::Object(vec![("triggerCharacters".to_owned(), Value::Array(vec![Value::from("@")]))])),
+				("definitionProvider".to_owned(), Value::from(true)),
+			])),
+			("serverInfo".to_owned(), Value::Object(vec![
+				("name".to_owned(), Value::from("plandustry")),
+				("version".to_owned(), Value::from(env!("CARGO_PKG_VERSION"))),
+			])),
+		])),
+		"shutdown" => Some(Value::Null),
+		"textDocument/completion" =>
+		{
+			let Some((_, text, line, col)) = get_position(docs, params)
+			else {return Some(Value::Null);};
+			let (start, items) = mlog::complete(text, line, col);
+			let replace = range(text, line, start, col);
+			Some(Value::Array(items.into_iter().map(|c| Value::Object(vec![
+				("label".to_owned(), Value::from(c.label.as_str())),
+				("kind".to_owned(), Value::from(completion_kind(c.kind))),
+				("detail".to_owned(), Value::from(c.detail)),
+				("textEdit".to_owned(), Value::Object(vec![("range".to_owned(), replace.clone()), ("newText".to_owned(), Value::from(c.label))])),
+			])).collect()))
+		},
+		"textDocument/definition" =>
+		{
+			let Some((uri, text, line, col)) = get_position(docs, params)
+			else {return Some(Value::Null);};
+			Some(match mlog::find_definition(text, line, col)
+			{
+				Some(tok) => Value::Object(vec![
+					("uri".to_owned(), Value::from(uri)),
+					("range".to_owned(), range(text, tok.line, tok.start, tok.get_end())),
+				]),
+				None => Value::Null,
+			})
+		},
+		_ => None,
+	}
+}
+
+fn serve() -> io::Result<()>
+{
+	let mut input = io::stdin().lock();
+	let mut docs = HashMap::<String, String>::new();
+	let mut shutdown = false;
+	while let Some(msg) = read_message(&mut input)?
+	{
+		let method = msg.get("method").and_then(Value::as_str).unwrap_or("");
+		let params = msg.get("params").cloned().unwrap_or(Value::Null);
+		let doc = params.get("textDocument");
+		let uri = doc.and_then(|d| d.get("uri")).and_then(Value::as_str).map(str::to_owned);
+		match method
+		{
+			"exit" => process::exit(if shutdown {0} else {1}),
+			"textDocument/didOpen" | "textDocument/didChange" =>
+			{
+				let text = if method == "textDocument/didOpen" {doc.and_then(|d| d.get("text"))}
+				else {params.get("contentChanges").and_then(Value::as_array).and_then(|c| c.last()).and_then(|c| c.get("text"))};
+				if let (Some(uri), Some(text)) = (uri, text.and_then(Value::as_str))
+				{
+					publish_diagnostics(&uri, text)?;
+					docs.insert(uri, text.to_owned());
+				}
+			},
+			"textDocument/didClose" =>
+			{
+				if let Some(uri) = uri
+				{
+					docs.remove(&uri);
+					publish_diagnostics(&uri, "")?;
+				}
+			},
+			_ =>
+			{
+				// anything without an id is a notification and needs no answer
+				let Some(id) = msg.get("id").cloned()
+				else {continue;};
+				if method == "shutdown" {shutdown = true;}
+				let reply = match handle_request(&docs, method, &params)
+				{
+					Some(result) => ("result".to_owned(), result),
+					None => ("error".to_owned(), Value::Object(vec![
+						("code".to_owned(), Value::from(-32601)),
+						("message".to_owned(), Value::from(format!("method {method:?} not supported"))),
+					])),
+				};
+				write_message(Value::Object(vec![("id".to_owned(), id), reply]))?;
+			},
+		}
+	}
+	Ok(())
+}
+
+fn print_diagnostics(label: &str, code: &str, diagnostics: &[Diagnostic]) -> bool
+{
+	for d in diagnostics
+	{
+		let line = code.lines().nth(d.line).unwrap_or("");
+		let col = line.get(..d.start).map_or(0, |s| s.chars().count()) + 1;
+		let severity = match d.severity {Severity::Error => "error", Severity::Warning => "warning"};
+		println!("{label}:{}:{col}: {severity}: {}", d.line + 1, d.message);
+	}
+	diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_check = handler.add(ArgOption::new(Some('c'), Some(Cow::Borrowed("check")), ArgCount::Forbidden)).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	if !handler.get_value(opt_check).is_present()
+	{
+		if let Err(e) = serve()
+		{
+			print_err!(e, "Language server failed");
+			process::exit(1);
+		}
+		return;
+	}
+	let literals = handler.get_literals();
+	if literals.is_empty()
+	{
+		eprintln!("Expected at least one .mlog file or schematic to check");
+		process::exit(1);
+	}
+	let reg = build_registry();
+	let mut failed = false;
+	for arg in literals
+	{
+		if Path::new(arg).extension().is_some_and(|e| e.eq_ignore_ascii_case("mlog"))
+		{
+			match fs::read_to_string(arg)
+			{
+				Ok(code) => failed |= print_diagnostics(arg, &code, &mlog::check(&code)),
+				Err(e) =>
+				{
+					print_err!(e, "Could not read file {arg:?}");
+					failed = true;
+				},
+			}
+			continue;
+		}
+		let Some(schematic) = read_schematic(&reg, arg)
+		else
+		{
+			failed = true;
+			continue;
+		};
+		let mut blocks = Vec::from_iter(schematic.block_iter());
+		blocks.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+		for p in blocks
+		{
+			let Some(proc) = p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>())
+			else {continue;};
+			let label = format!("{arg} ({} at {} / {})", p.get_block().get_name(), p.get_pos().0, p.get_pos().1);
+			failed |= print_diagnostics(&label, proc.get_code(), &mlog::check(proc.get_code()));
+		}
+	}
+	if failed {process::exit(1);}
+}
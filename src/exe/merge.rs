@@ -0,0 +1,200 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use plandustry::block::build_registry;
+use plandustry::data::{DataWrite, Serializer};
+use plandustry::data::dynamic::DynData;
+use plandustry::data::merge::{merge, Side};
+use plandustry::data::schematic::{Placement, SchematicSerializer};
+use plandustry::registry::RegistryEntry;
+
+use crate::{print_err, read_schematic};
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+
+fn format_placement(p: &Placement) -> String
+{
+	let pos = p.get_pos();
+	let mut result = format!("{} at {} / {}", p.get_block().get_name(), pos.0, pos.1);
+	if !p.get_block().is_symmetric()
+	{
+		result.push_str(&format!(" ({:?})", p.get_rotation()).to_lowercase());
+	}
+	if let Some(state) = p.get_state()
+	{
+		match p.get_block().serialize_state(state)
+		{
+			Ok(DynData::Empty) | Err(..) => (),
+			Ok(data) => result.push_str(&format!(" = {data}")),
+		}
+	}
+	result
+}
+
+fn format_side(blocks: &[Placement]) -> String
+{
+	if blocks.is_empty() {"removed".to_string()}
+	else {Vec::from_iter(blocks.iter().map(format_placement)).join(", ")}
+}
+
+fn parse_side(value: &str) -> Option<Side>
+{
+	match value
+	{
+		"o" | "ours" => Some(Side::Ours),
+		"t" | "theirs" => Some(Side::Theirs),
+		_ => None,
+	}
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	let opt_resolve = handler.add(ArgOption::new(Some('r'), Some(Cow::Borrowed("resolve")), ArgCount::Required(usize::MAX))).unwrap();
+	let opt_ours = handler.add(ArgOption::new(None, Some(Cow::Borrowed("ours")), ArgCount::Forbidden)).unwrap();
+	let opt_theirs = handler.add(ArgOption::new(None, Some(Cow::Borrowed("theirs")), ArgCount::Forbidden)).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(2);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 3
+	{
+		eprintln!("Expected the base, our and their schematic, got {} arguments", literals.len());
+		process::exit(2);
+	}
+	let prefer = match (handler.get_value(opt_ours).is_present(), handler.get_value(opt_theirs).is_present())
+	{
+		(false, false) => None,
+		(true, false) => Some(Side::Ours),
+		(false, true) => Some(Side::Theirs),
+		(true, true) =>
+		{
+			eprintln!("Cannot prefer both our and their changes");
+			process::exit(2);
+		},
+	};
+	// conflicts are numbered from 1 as printed, blocks first then tags
+	let mut choices = Vec::<(usize, Side)>::new();
+	for value in handler.get_value(opt_resolve).get_values().unwrap_or_default()
+	{
+		let parsed = value.split_once('=').and_then(|(idx, side)| Some((idx.parse::<usize>().ok().filter(|&i| i > 0)?, parse_side(side)?)));
+		match parsed
+		{
+			Some((idx, side)) => choices.push((idx - 1, side)),
+			None =>
+			{
+				eprintln!("Invalid resolution {value:?}, expected <number>=ours or <number>=theirs");
+				process::exit(2);
+			},
+		}
+	}
+	let reg = build_registry();
+	let Some(base) = read_schematic(&reg, &literals[0])
+	else {process::exit(2);};
+	let Some(ours) = read_schematic(&reg, &literals[1])
+	else {process::exit(2);};
+	let Some(theirs) = read_schematic(&reg, &literals[2])
+	else {process::exit(2);};
+	let mut result = match merge(&base, &ours, &theirs)
+	{
+		Ok(r) => r,
+		Err(e) =>
+		{
+			print_err!(e, "Could not merge schematics");
+			process::exit(2);
+		},
+	};
+	
+	let block_cnt = result.get_conflicts().len();
+	let total = block_cnt + result.get_tag_conflicts().len();
+	if let Some(&(idx, _)) = choices.iter().find(|&&(idx, _)| idx >= total)
+	{
+		eprintln!("No conflict number {} (found {total})", idx + 1);
+		process::exit(2);
+	}
+	for (i, c) in result.get_conflicts().iter().enumerate()
+	{
+		let pos = c.get_pos();
+		eprintln!("Conflict {} at {} / {}", i + 1, pos.0, pos.1);
+		eprintln!("\tbase: {}", if c.base.is_empty() {"empty".to_string()} else {format_side(&c.base)});
+		eprintln!("\tours: {}", format_side(&c.ours));
+		eprintln!("\ttheirs: {}", format_side(&c.theirs));
+	}
+	for (i, c) in result.get_tag_conflicts().iter().enumerate()
+	{
+		eprintln!("Conflict {} in tag {:?}", block_cnt + i + 1, c.key);
+		eprintln!("\tbase: {:?}", c.base);
+		eprintln!("\tours: {:?}", c.ours);
+		eprintln!("\ttheirs: {:?}", c.theirs);
+	}
+	// resolve from the back so the numbers of the remaining conflicts stay valid
+	choices.sort_by_key(|&(idx, _)| idx);
+	choices.dedup_by_key(|&mut (idx, _)| idx);
+	for &(idx, side) in choices.iter().rev()
+	{
+		if idx >= block_cnt {result.resolve_tag(idx - block_cnt, side);}
+		else if let Err(e) = result.resolve(idx, side)
+		{
+			print_err!(e, "Could not resolve conflict {}", idx + 1);
+			process::exit(2);
+		}
+	}
+	if let Some(side) = prefer
+	{
+		if let Err(e) = result.resolve_all(side)
+		{
+			print_err!(e, "Could not resolve conflicts");
+			process::exit(2);
+		}
+	}
+	
+	// as a merge driver the result replaces our version
+	let output = match handler.get_value(opt_output).get_value()
+	{
+		Some(path) => Some(path.as_str()),
+		None if Path::new(&literals[1]).is_file() => Some(literals[1].as_str()),
+		None => None,
+	};
+	let mut ss = SchematicSerializer(&reg);
+	match output
+	{
+		Some(path) =>
+		{
+			let mut data = DataWrite::new();
+			if let Err(e) = ss.serialize(&mut data, result.get_schematic())
+			{
+				print_err!(e, "Could not serialize schematic");
+				process::exit(2);
+			}
+			if let Err(e) = fs::write(path, data.get_written())
+			{
+				print_err!(e, "Could not write to file {path:?}");
+				process::exit(2);
+			}
+		},
+		None =>
+		{
+			match ss.serialize_base64(result.get_schematic())
+			{
+				Ok(b64) => println!("{b64}"),
+				Err(e) =>
+				{
+					print_err!(e, "Could not serialize schematic");
+					process::exit(2);
+				},
+			}
+		},
+	}
+	if !result.is_clean()
+	{
+		let left = result.get_conflicts().len() + result.get_tag_conflicts().len();
+		eprintln!("{left} of {total} conflicts unresolved, their area keeps the base version");
+		process::exit(1);
+	}
+}
@@ -1,6 +1,24 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use plandustry::block::BlockRegistry;
+use plandustry::data::{DataRead, Serializer};
+use plandustry::data::schematic::{Schematic, SchematicSerializer};
+
 pub mod args;
+pub mod compile;
+pub mod convert;
+pub mod diff;
+pub mod draw;
 pub mod edit;
+pub mod index;
+pub mod lsp;
+pub mod merge;
+pub mod pixelart;
 pub mod print;
+pub mod search;
+pub mod serve;
+pub mod textconv;
 
 macro_rules!print_err
 {
@@ -27,15 +45,89 @@ macro_rules!print_err
 }
 pub(crate) use print_err;
 
+// reads a schematic from an argument that is either a path to a file or base-64 encoded
+pub(crate) fn read_schematic<'l>(reg: &'l BlockRegistry<'l>, arg: &str) -> Option<Schematic<'l>>
+{
+	let mut ss = SchematicSerializer(reg);
+	if Path::new(arg).is_file()
+	{
+		match fs::read(arg)
+		{
+			Ok(data) =>
+			{
+				match ss.deserialize(&mut DataRead::new(&data))
+				{
+					Ok(s) => Some(s),
+					Err(e) =>
+					{
+						print_err!(e, "Could not read schematic from {arg}");
+						None
+					},
+				}
+			},
+			Err(e) =>
+			{
+				print_err!(e, "Could not read file {arg:?}");
+				None
+			},
+		}
+	}
+	else
+	{
+		match ss.deserialize_base64(arg)
+		{
+			Ok(s) => Some(s),
+			Err(e) =>
+			{
+				print_err!(e, "Could not read schematic (neither a file nor base-64)");
+				None
+			},
+		}
+	}
+}
+
+// collects all files with the extension (ignoring case) below the directory, in a stable order
+pub(crate) fn find_files(dir: &Path, ext: &str, found: &mut Vec<PathBuf>)
+{
+	let entries = match fs::read_dir(dir)
+	{
+		Ok(e) => e,
+		Err(e) =>
+		{
+			print_err!(e, "Could not read directory {dir:?}");
+			return;
+		},
+	};
+	let mut paths = Vec::from_iter(entries.filter_map(|e| e.ok()).filter_map(|e| Some((e.path(), e.file_type().ok()?))));
+	paths.sort_by(|a, b| a.0.cmp(&b.0));
+	for (path, ty) in paths
+	{
+		// symlinked directories are skipped as they can lead back up the tree
+		if ty.is_dir() {find_files(&path, ext, found);}
+		else if path.is_file() && path.extension().is_some_and(|e| e.eq_ignore_ascii_case(ext)) {found.push(path);}
+	}
+}
+
 fn main()
 {
 	let mut args = std::env::args();
 	args.next().unwrap(); // path to executable
 	match args.next()
 	{
-		None => eprintln!("Not enough arguments, valid commands are: edit, print"),
+		None => eprintln!("Not enough arguments, valid commands are: compile, convert, diff, draw, edit, index, lsp, merge, pixelart, print, search, serve, textconv"),
+		Some(s) if s == "compile" => compile::main(args, 1),
+		Some(s) if s == "convert" => convert::main(args, 1),
+		Some(s) if s == "diff" => diff::main(args, 1),
+		Some(s) if s == "draw" => draw::main(args, 1),
 		Some(s) if s == "edit" => edit::main(args, 1),
+		Some(s) if s == "index" => index::main(args, 1),
+		Some(s) if s == "lsp" => lsp::main(args, 1),
+		Some(s) if s == "merge" => merge::main(args, 1),
+		Some(s) if s == "pixelart" => pixelart::main(args, 1),
 		Some(s) if s == "print" => print::main(args, 1),
-		Some(s) => eprintln!("Unknown argument {s}, valid commands are: edit, print"),
+		Some(s) if s == "search" => search::main(args, 1),
+		Some(s) if s == "serve" => serve::main(args, 1),
+		Some(s) if s == "textconv" => textconv::main(args, 1),
+		Some(s) => eprintln!("Unknown argument {s}, valid commands are: compile, convert, diff, draw, edit, index, lsp, merge, pixelart, print, search, serve, textconv"),
 	}
 }
\ No newline at end of file
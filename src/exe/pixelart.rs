@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use plandustry::block::build_registry;
+use plandustry::content::Content;
+use plandustry::data::image;
+use plandustry::data::pixelart::{generate, get_palette, Style};
+use plandustry::data::schematic::MAX_DIMENSION;
+
+use crate::print_err;
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+use crate::convert::{self, Format};
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_style = handler.add(ArgOption::new(Some('s'), Some(Cow::Borrowed("style")), ArgCount::Required(1))).unwrap();
+	let opt_palette = handler.add(ArgOption::new(Some('p'), Some(Cow::Borrowed("palette")), ArgCount::Required(usize::MAX))).unwrap();
+	let opt_width = handler.add(ArgOption::new(Some('w'), Some(Cow::Borrowed("width")), ArgCount::Required(1))).unwrap();
+	let opt_dither = handler.add(ArgOption::new(Some('d'), Some(Cow::Borrowed("dither")), ArgCount::Forbidden)).unwrap();
+	let opt_output = handler.add(ArgOption::new(Some('o'), Some(Cow::Borrowed("output")), ArgCount::Required(1))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 1
+	{
+		eprintln!("Expected exactly one image (PNG or PPM)");
+		process::exit(1);
+	}
+	let path = literals[0].as_str();
+	let style = match handler.get_value(opt_style).get_value()
+	{
+		None => Style::Sorter,
+		Some(name) =>
+		{
+			let Some(s) = Style::by_name(name)
+			else
+			{
+				eprintln!("Unknown style {name:?}, expected sorter, inverted-sorter, wall or illuminator");
+				process::exit(1);
+			};
+			s
+		},
+	};
+	let mut palette = get_palette(style);
+	// only the named items or blocks
+	if let Some(names) = handler.get_value(opt_palette).get_values()
+	{
+		let names = Vec::from_iter(names.iter().flat_map(|n| n.split(',')).map(str::trim).filter(|n| !n.is_empty()));
+		for name in names.iter()
+		{
+			if !palette.iter().any(|s| s.block == *name || s.item.is_some_and(|i| i.get_name() == *name))
+			{
+				eprintln!("{name:?} is not part of the {style:?} palette");
+				process::exit(1);
+			}
+		}
+		palette.retain(|s| names.iter().any(|n| s.block == *n || s.item.is_some_and(|i| i.get_name() == *n)));
+	}
+	// checked before reading the image, the height can only be checked after
+	let width = handler.get_value(opt_width).get_value().map(|value|
+	{
+		let Some(width) = value.parse::<u32>().ok().filter(|&w| w > 0 && w <= MAX_DIMENSION as u32)
+		else
+		{
+			eprintln!("Invalid width {value:?}, expected a number from 1 to {MAX_DIMENSION}");
+			process::exit(1);
+		};
+		width
+	});
+	let mut image = match fs::read(path).map(|data| image::decode(&data))
+	{
+		Ok(Ok(image)) => image,
+		Ok(Err(e)) =>
+		{
+			print_err!(e, "Could not read image {path:?}");
+			process::exit(1);
+		},
+		Err(e) =>
+		{
+			print_err!(e, "Could not read file {path:?}");
+			process::exit(1);
+		},
+	};
+	if let Some(width) = width
+	{
+		let (w, h) = (image.get_width() as u64, image.get_height() as u64);
+		let height = ((h * width as u64 + w / 2) / w).max(1);
+		if height > MAX_DIMENSION as u64
+		{
+			// the widest that still keeps the height in bounds
+			let max = ((w * MAX_DIMENSION as u64) / h).max(1);
+			eprintln!("A width of {width} makes the image {height} tall, more than the maximum of {MAX_DIMENSION} (use a width of at most {max})");
+			process::exit(1);
+		}
+		image = image.resize(width, height as u32);
+	}
+	else if image.get_width().max(image.get_height()) > MAX_DIMENSION as u32
+	{
+		// shrink to the largest schematic, keeping the aspect ratio
+		let (w, h) = (image.get_width() as u64, image.get_height() as u64);
+		let scale = MAX_DIMENSION as u64;
+		let (width, height) = if w >= h {(scale, ((h * scale + w / 2) / w).max(1))} else {(((w * scale + h / 2) / h).max(1), scale)};
+		eprintln!("Scaling the image down to {width} x {height} to fit into a schematic");
+		image = image.resize(width as u32, height as u32);
+	}
+	
+	let reg = build_registry();
+	let mut schematic = match generate(&reg, &image, style, &palette, handler.get_value(opt_dither).is_present())
+	{
+		Ok(s) => s,
+		Err(e) =>
+		{
+			print_err!(e, "Could not build schematic");
+			process::exit(1);
+		},
+	};
+	if let Some(stem) = Path::new(path).file_stem()
+	{
+		schematic.get_tags_mut().insert("name".to_owned(), stem.to_string_lossy().into_owned());
+	}
+	let (cost, sandbox) = schematic.compute_total_cost();
+	eprintln!("{} blocks, build cost: {cost}{}", schematic.block_iter().count(), if sandbox {" (Sandbox only)"} else {""});
+	let output = handler.get_value(opt_output).get_value();
+	let format = output.and_then(|o| Path::new(o).extension()).and_then(|e| e.to_str()).and_then(Format::parse).unwrap_or(Format::Base64);
+	let Some(data) = convert::write_output(&reg, &schematic, format, 16)
+	else {process::exit(1);};
+	let result = match output
+	{
+		Some(out) => fs::write(out, data),
+		None => io::stdout().write_all(&data),
+	};
+	if let Err(e) = result
+	{
+		print_err!(e, "Could not write the schematic");
+		process::exit(1);
+	}
+}
@@ -1,11 +1,20 @@
 use std::borrow::Cow;
 use std::env::Args;
+use std::error::Error;
 use std::io::{self, Write};
 use std::fs;
 
 use plandustry::block::build_registry;
+use plandustry::content::Content;
 use plandustry::data::{DataRead, Serializer};
+use plandustry::data::index::Entry;
+use plandustry::data::json::Value;
+use plandustry::data::processors;
+use plandustry::data::render::render_canvases;
 use plandustry::data::schematic::{Schematic, SchematicSerializer};
+use plandustry::data::text::to_json;
+use plandustry::item::storage::Storage;
+use plandustry::logic::format::add_labels;
 
 use crate::print_err;
 use crate::args::{self, ArgCount, ArgOption, OptionHandler};
@@ -15,6 +24,8 @@ pub fn main(mut args: Args, arg_off: usize)
 	let mut handler = OptionHandler::new();
 	let opt_file = handler.add(ArgOption::new(Some('f'), Some(Cow::Borrowed("file")), ArgCount::Required(usize::MAX))).unwrap();
 	let opt_interact = handler.add(ArgOption::new(Some('i'), Some(Cow::Borrowed("interactive")), ArgCount::Forbidden)).unwrap();
+	let opt_format = handler.add(ArgOption::new(None, Some(Cow::Borrowed("format")), ArgCount::Required(1))).unwrap();
+	let opt_code = handler.add(ArgOption::new(Some('c'), Some(Cow::Borrowed("code")), ArgCount::Forbidden)).unwrap();
 	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
 	{
 		print_err!(e, "Command error");
@@ -23,6 +34,61 @@ pub fn main(mut args: Args, arg_off: usize)
 	
 	let reg = build_registry();
 	let mut ss = SchematicSerializer(&reg);
+	let format = match handler.get_value(opt_format).get_value().map(String::as_str)
+	{
+		None | Some("text") => None,
+		Some("json") => Some(Format::Json),
+		Some("tsv") => Some(Format::Tsv),
+		Some(other) =>
+		{
+			eprintln!("Unknown format {other:?}, expected text, json or tsv");
+			return;
+		},
+	};
+	if let Some(format) = format
+	{
+		let mut sources = Vec::<(String, Result<Vec<u8>, io::Error>)>::new();
+		for path in handler.get_value(opt_file).get_values().unwrap_or_default()
+		{
+			sources.push((format!("@{path}"), fs::read(path)));
+		}
+		let literals = handler.get_literals();
+		let interactive = handler.get_value(opt_interact).is_present() || (sources.is_empty() && literals.is_empty());
+		for curr in literals
+		{
+			print_machine(format, curr, ss.deserialize_base64(curr).map_err(|e| ("Could not read schematic", Box::new(e) as Box<dyn Error>)));
+		}
+		for (source, data) in sources
+		{
+			let result = match data
+			{
+				Ok(data) => ss.deserialize(&mut DataRead::new(&data)).map_err(|e| ("Could not read schematic", Box::new(e) as Box<dyn Error>)),
+				Err(e) => Err(("Could not read file", Box::new(e) as Box<dyn Error>)),
+			};
+			print_machine(format, &source, result);
+		}
+		// without prompts, one schematic per line until an empty one
+		if interactive
+		{
+			for line in io::stdin().lines()
+			{
+				let line = match line
+				{
+					Ok(l) => l,
+					Err(e) =>
+					{
+						print_machine(format, "stdin", Err(("Failed to read next schematic", Box::new(e))));
+						break;
+					},
+				};
+				let data = line.trim();
+				if data.is_empty() {break;}
+				print_machine(format, data, ss.deserialize_base64(data).map_err(|e| ("Could not read schematic", Box::new(e) as Box<dyn Error>)));
+			}
+		}
+		return;
+	}
+	let code = handler.get_value(opt_code).is_present();
 	let mut first = true;
 	let mut need_space = false;
 	// process the files if any
@@ -46,6 +112,7 @@ pub fn main(mut args: Args, arg_off: usize)
 								need_space = true;
 								println!("Schematic: @{path}");
 								print_schematic(&s);
+								if code {print_code(&s);}
 							},
 							// continue processing files, literals & maybe interactive mode
 							Err(e) =>
@@ -82,6 +149,7 @@ pub fn main(mut args: Args, arg_off: usize)
 				need_space = true;
 				println!("Schematic: {curr}");
 				print_schematic(&s);
+				if code {print_code(&s);}
 			},
 			// continue processing literals & maybe interactive mode
 			Err(e) =>
@@ -124,7 +192,8 @@ pub fn main(mut args: Args, arg_off: usize)
 						{
 							println!();
 							need_space = true;
-							print_schematic(&s)
+							print_schematic(&s);
+							if code {print_code(&s);}
 						},
 						// continue interactive mode, typos are especially likely here
 						Err(e) =>
@@ -146,6 +215,131 @@ pub fn main(mut args: Args, arg_off: usize)
 	}
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Format
+{
+	Json,
+	Tsv,
+}
+
+fn escape_tsv(value: &str) -> String
+{
+	value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn print_tsv(fields: &[&str])
+{
+	println!("{}", Vec::from_iter(fields.iter().map(|f| escape_tsv(f))).join("\t"));
+}
+
+// the messages of all errors that caused this one, innermost last
+pub(crate) fn error_chain(err: &dyn Error) -> Vec<String>
+{
+	let mut chain = Vec::new();
+	let mut err_ref = err.source();
+	while let Some(next) = err_ref
+	{
+		chain.push(next.to_string());
+		err_ref = next.source();
+	}
+	chain
+}
+
+pub(crate) fn error_json(source: &str, context: &str, err: &dyn Error) -> Value
+{
+	Value::Object(vec![
+		("source".to_owned(), Value::from(source)),
+		("error".to_owned(), Value::from(context)),
+		("message".to_owned(), Value::from(err.to_string())),
+		("sources".to_owned(), Value::Array(error_chain(err).into_iter().map(Value::from).collect())),
+	])
+}
+
+// the metadata, cost and blocks of a schematic
+pub(crate) fn schematic_json(source: &str, s: &Schematic) -> Value
+{
+	let (_, sandbox) = s.compute_total_cost();
+	let value = to_json(s);
+	let mut result = Entry::new(source.to_owned(), s).to_json();
+	let Value::Object(ref mut fields) = result
+	else {unreachable!()};
+	for (k, _) in fields.iter_mut()
+	{
+		if k == "path" {*k = "source".to_owned();}
+		if k == "blocks" {*k = "counts".to_owned();}
+	}
+	result.insert("sandbox", Value::from(sandbox));
+	result.insert("tags", value.get("tags").cloned().unwrap_or(Value::Null));
+	result.insert("blocks", value.get("blocks").cloned().unwrap_or(Value::Null));
+	result
+}
+
+// prints one schematic (or why it couldn't be read) as a json object per line, or as tab separated records which start
+// with the kind of record and the source
+fn print_machine(format: Format, source: &str, result: Result<Schematic, (&str, Box<dyn Error>)>)
+{
+	let s = match result
+	{
+		Ok(s) => s,
+		Err((context, e)) =>
+		{
+			match format
+			{
+				Format::Json => println!("{}", error_json(source, context, e.as_ref())),
+				Format::Tsv =>
+				{
+					let message = e.to_string();
+					let chain = error_chain(e.as_ref());
+					let mut fields = vec!["error", source, context, &message];
+					fields.extend(chain.iter().map(String::as_str));
+					print_tsv(&fields);
+				},
+			}
+			return;
+		},
+	};
+	match format
+	{
+		Format::Json => println!("{}", schematic_json(source, &s)),
+		Format::Tsv =>
+		{
+			let entry = Entry::new(source.to_owned(), &s);
+			let (_, sandbox) = s.compute_total_cost();
+			let value = to_json(&s);
+			let placements = value.get("blocks").and_then(Value::as_array).unwrap_or_default();
+			let (w, h) = (entry.width.to_string(), entry.height.to_string());
+			print_tsv(&["schematic", source, &entry.name, &w, &h, if sandbox {"sandbox"} else {""}]);
+			for label in entry.labels.iter()
+			{
+				print_tsv(&["label", source, label]);
+			}
+			let mut tags = Vec::from_iter(s.get_tags().iter());
+			tags.sort();
+			for (k, v) in tags
+			{
+				print_tsv(&["tag", source, k, v]);
+			}
+			for (name, cnt) in entry.blocks.iter()
+			{
+				print_tsv(&["count", source, name, &cnt.to_string()]);
+			}
+			for (ty, cnt) in entry.cost.iter_nonzero()
+			{
+				print_tsv(&["cost", source, ty.get_name(), &cnt.to_string()]);
+			}
+			for p in placements
+			{
+				let field = |key| p.get(key).map_or(String::new(), |v| v.as_str().map_or_else(|| v.to_string(), str::to_owned));
+				let (x, y, block, rot, config) = (field("x"), field("y"), field("block"), field("rotation"), field("config"));
+				let mut fields = vec!["block", source, &x, &y, &block, &rot, if config == "null" {""} else {&config}];
+				let code = field("code");
+				if p.get("code").is_some() {fields.push(&code);}
+				print_tsv(&fields);
+			}
+		},
+	}
+}
+
 pub fn print_schematic(s: &Schematic)
 {
 	if let Some(name) = s.get_tags().get("name")
@@ -154,11 +348,24 @@ pub fn print_schematic(s: &Schematic)
 	}
 	if let Some(desc) = s.get_tags().get("description")
 	{
-		if !desc.is_empty() {println!("Desc: {desc}");}
+		if !desc.is_empty()
+		{
+			// indent continuation lines so multi-line descriptions stay readable
+			let mut lines = desc.lines();
+			println!("Desc: {}", lines.next().unwrap_or(""));
+			for line in lines
+			{
+				println!("      {line}");
+			}
+		}
 	}
-	if let Some(labels) = s.get_tags().get("labels")
+	match s.get_labels()
 	{
-		if !labels.is_empty() && labels != "[]" {println!("Tags: {:?}", labels);}
+		Ok(labels) =>
+		{
+			if !labels.is_empty() {println!("Labels: {}", labels.join(", "));}
+		},
+		Err(e) => print_err!(e, "Invalid labels {:?}", s.get_tags().get("labels").unwrap()),
 	}
 	let (cost, sandbox) = s.compute_total_cost();
 	if !cost.is_empty()
@@ -170,4 +377,38 @@ pub fn print_schematic(s: &Schematic)
 		println!("Can only be built in the Sandbox");
 	}
 	println!("\n{s}");
+	let canvases = render_canvases(s);
+	if !canvases.is_empty() {print!("\n{canvases}");}
+}
+
+// the code of every processor, with labels instead of numeric jumps
+pub fn print_code(s: &Schematic)
+{
+	for (entry, code) in processors::export(s)
+	{
+		let links = Vec::from_iter(entry.links.iter().map(|(name, x, y)| format!("{name} ({x:+}, {y:+})")));
+		println!("\n{} at {} / {}{}{}:", entry.block, entry.x, entry.y, if links.is_empty() {""} else {", links: "}, links.join(", "));
+		print!("{}", add_labels(&code));
+	}
+}
+
+pub fn print_cost_delta(before: &Storage, after: &Storage)
+{
+	let mut delta = Vec::new();
+	for (ty, cnt) in after.iter_nonzero()
+	{
+		let diff = cnt as i64 - before.get(ty) as i64;
+		if diff != 0 {delta.push((ty, diff));}
+	}
+	for (ty, cnt) in before.iter_nonzero()
+	{
+		if after.get(ty) == 0 {delta.push((ty, -(cnt as i64)));}
+	}
+	if delta.is_empty() {println!("Build cost unchanged");}
+	else
+	{
+		delta.sort();
+		let delta = Vec::from_iter(delta.into_iter().map(|(ty, diff)| format!("{diff:+} {ty}")));
+		println!("Build cost change: {}", delta.join(", "));
+	}
 }
@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use plandustry::data::index::{read_index, Query};
+use plandustry::item;
+
+use crate::print_err;
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+
+// parses "<name>=<number>", where the number may be optional
+fn parse_pair(value: &str, default: Option<u32>) -> Option<(&str, u32)>
+{
+	match value.split_once('=')
+	{
+		None => default.map(|d| (value, d)),
+		Some((name, num)) => num.parse::<u32>().ok().map(|n| (name, n)),
+	}
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_index = handler.add(ArgOption::new(Some('i'), Some(Cow::Borrowed("index")), ArgCount::Required(1))).unwrap();
+	let opt_label = handler.add(ArgOption::new(Some('l'), Some(Cow::Borrowed("label")), ArgCount::Required(usize::MAX))).unwrap();
+	let opt_block = handler.add(ArgOption::new(Some('b'), Some(Cow::Borrowed("block")), ArgCount::Required(usize::MAX))).unwrap();
+	let opt_size = handler.add(ArgOption::new(Some('s'), Some(Cow::Borrowed("size")), ArgCount::Required(1))).unwrap();
+	let opt_cost = handler.add(ArgOption::new(Some('c'), Some(Cow::Borrowed("cost")), ArgCount::Required(usize::MAX))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let mut query = Query::default();
+	if !handler.get_literals().is_empty()
+	{
+		query.text = Some(handler.get_literals().join(" "));
+	}
+	query.labels = handler.get_value(opt_label).get_values().unwrap_or_default().to_vec();
+	for value in handler.get_value(opt_block).get_values().unwrap_or_default()
+	{
+		let Some((name, min)) = parse_pair(value, Some(1))
+		else
+		{
+			eprintln!("Invalid block {value:?}, expected <name> or <name>=<minimum count>");
+			process::exit(1);
+		};
+		query.blocks.push((name.to_owned(), min));
+	}
+	if let Some(value) = handler.get_value(opt_size).get_value()
+	{
+		let parsed = value.split_once('x').and_then(|(w, h)| Some((w.parse::<u16>().ok()?, h.parse::<u16>().ok()?)));
+		let Some((w, h)) = parsed
+		else
+		{
+			eprintln!("Invalid size {value:?}, expected <width>x<height>");
+			process::exit(1);
+		};
+		query.max_width = Some(w);
+		query.max_height = Some(h);
+	}
+	for value in handler.get_value(opt_cost).get_values().unwrap_or_default()
+	{
+		let Some((name, max)) = parse_pair(value, None)
+		else
+		{
+			eprintln!("Invalid cost limit {value:?}, expected <item>=<maximum amount>");
+			process::exit(1);
+		};
+		let Some(ty) = item::Type::by_name(name)
+		else
+		{
+			eprintln!("No such item {name:?}");
+			process::exit(1);
+		};
+		query.max_cost.push((ty, max));
+	}
+	
+	let path = handler.get_value(opt_index).get_value().map_or("index.json", |s| s.as_str());
+	let text = match fs::read_to_string(path)
+	{
+		Ok(t) => t,
+		Err(e) =>
+		{
+			print_err!(e, "Could not read file {path:?}");
+			process::exit(1);
+		},
+	};
+	let entries = match read_index(&text)
+	{
+		Ok(e) => e,
+		Err(e) =>
+		{
+			print_err!(e, "Could not read index {path:?}");
+			process::exit(1);
+		},
+	};
+	// entry paths are relative to the index
+	let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+	let mut found = 0;
+	for entry in entries.iter().filter(|e| query.matches(e))
+	{
+		found += 1;
+		let name = if entry.name.is_empty() {"<unnamed>"} else {&entry.name};
+		println!("{}: {name} ({} x {})", dir.join(&entry.path).display(), entry.width, entry.height);
+	}
+	println!("Found {found} of {} schematics", entries.len());
+}
@@ -0,0 +1,501 @@
+use std::borrow::Cow;
+use std::env::Args;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use plandustry::block::BlockRegistry;
+use plandustry::block::build_registry;
+use plandustry::block::logic::ProcessorState;
+use plandustry::content::Content;
+use plandustry::data::{DataRead, Serializer};
+use plandustry::data::json::Value;
+use plandustry::data::schematic::{Schematic, SchematicSerializer};
+use plandustry::registry::RegistryEntry;
+
+use crate::print_err;
+use crate::args::{self, ArgCount, ArgOption, OptionHandler};
+use crate::convert::{write_output, Format};
+use crate::print::{error_json, schematic_json};
+
+// large enough for any real schematic, small enough to not be a problem if someone sends garbage
+const MAX_BODY: usize = 16 << 20;
+const MAX_HEADERS: usize = 100;
+// connections beyond this are turned away, which also limits how much memory bodies can take up
+const MAX_CONNECTIONS: usize = 16;
+
+const USAGE: &str = "\
+POST a base-64 encoded or binary (.msch) schematic to one of these endpoints:
+/info                      name, tags, size, block counts, cost and blocks as json
+/cost                      build cost as json
+/validate                  problems with the schematic as json
+/render?format=F&scale=N   an image, F is png (default) or svg, N is from 1 to 64 (default 16)
+/convert?to=F              the schematic as F: msch, base64, text, json or ascii
+";
+
+struct Request
+{
+	method: String,
+	path: String,
+	query: Vec<(String, String)>,
+	body: Vec<u8>,
+}
+
+impl Request
+{
+	fn get_param(&self, key: &str) -> Option<&str>
+	{
+		self.query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+	}
+}
+
+#[derive(Debug)]
+struct Response
+{
+	status: u16,
+	content_type: &'static str,
+	body: Vec<u8>,
+}
+
+impl Response
+{
+	fn json(status: u16, value: &Value) -> Self
+	{
+		Self{status, content_type: "application/json", body: format!("{value}\n").into_bytes()}
+	}
+	
+	fn text(status: u16, text: &str) -> Self
+	{
+		Self{status, content_type: "text/plain; charset=utf-8", body: text.as_bytes().to_vec()}
+	}
+	
+	fn error(status: u16, message: &str) -> Self
+	{
+		Self::json(status, &Value::Object(vec![("error".to_owned(), Value::from(message))]))
+	}
+}
+
+fn status_text(status: u16) -> &'static str
+{
+	match status
+	{
+		200 => "OK",
+		400 => "Bad Request",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		408 => "Request Timeout",
+		411 => "Length Required",
+		413 => "Content Too Large",
+		431 => "Request Header Fields Too Large",
+		500 => "Internal Server Error",
+		501 => "Not Implemented",
+		503 => "Service Unavailable",
+		_ => "Unknown",
+	}
+}
+
+fn read_line(reader: &mut BufReader<&TcpStream>) -> Result<String, Response>
+{
+	let mut line = String::new();
+	match reader.by_ref().take(8192).read_line(&mut line)
+	{
+		Ok(0) => Err(Response::error(400, "connection closed")),
+		Ok(_) if !line.ends_with('\n') => Err(Response::error(431, "line too long")),
+		Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_owned()),
+		Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Err(Response::error(408, "timed out")),
+		Err(..) => Err(Response::error(400, "invalid request")),
+	}
+}
+
+// reads one request, or the response to send if it is not acceptable
+fn read_request(stream: &TcpStream) -> Result<Request, Response>
+{
+	let mut reader = BufReader::new(stream);
+	let line = read_line(&mut reader)?;
+	let mut parts = line.split(' ');
+	let (Some(method), Some(target), Some(version), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+	else {return Err(Response::error(400, "invalid request line"));};
+	if !version.starts_with("HTTP/1.") {return Err(Response::error(400, "unsupported http version"));}
+	let (path, query) = target.split_once('?').unwrap_or((target, ""));
+	let query = query.split('&').filter(|p| !p.is_empty())
+		.map(|p| p.split_once('=').map_or((p.to_owned(), String::new()), |(k, v)| (k.to_owned(), v.to_owned()))).collect();
+	
+	let mut length = None;
+	let mut expect_continue = false;
+	let mut count = 0;
+	loop
+	{
+		let line = read_line(&mut reader)?;
+		if line.is_empty() {break;}
+		count += 1;
+		if count > MAX_HEADERS {return Err(Response::error(431, "too many headers"));}
+		let Some((name, value)) = line.split_once(':')
+		else {return Err(Response::error(400, "invalid header"));};
+		let value = value.trim();
+		if name.eq_ignore_ascii_case("content-length")
+		{
+			match value.parse::<usize>()
+			{
+				Ok(n) if n <= MAX_BODY => length = Some(n),
+				Ok(..) => return Err(Response::error(413, "body too large")),
+				Err(..) => return Err(Response::error(400, "invalid content length")),
+			}
+		}
+		else if name.eq_ignore_ascii_case("transfer-encoding") {return Err(Response::error(501, "transfer encodings are not supported"));}
+		else if name.eq_ignore_ascii_case("expect") && value.eq_ignore_ascii_case("100-continue") {expect_continue = true;}
+	}
+	
+	let mut body = Vec::new();
+	if method == "POST"
+	{
+		let Some(length) = length
+		else {return Err(Response::error(411, "missing content length"));};
+		// clients like curl wait for this before sending larger bodies
+		if expect_continue
+		{
+			let mut out = stream;
+			if out.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").is_err() {return Err(Response::error(400, "connection closed"));}
+		}
+		// only grows with what is actually received, rather than what the client claims it will send
+		if reader.take(length as u64).read_to_end(&mut body).is_err() || body.len() != length
+		{
+			return Err(Response::error(400, "incomplete body"));
+		}
+	}
+	Ok(Request{method: method.to_owned(), path: path.to_owned(), query, body})
+}
+
+fn write_response(mut stream: &TcpStream, resp: &Response) -> io::Result<()>
+{
+	// every connection handles a single request, which keeps clients from blocking each other
+	write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		resp.status, status_text(resp.status), resp.content_type, resp.body.len())?;
+	stream.write_all(&resp.body)?;
+	stream.flush()
+}
+
+fn read_body<'l>(reg: &'l BlockRegistry<'l>, body: &[u8]) -> Result<Schematic<'l>, Box<dyn Error>>
+{
+	let mut ss = SchematicSerializer(reg);
+	if body.starts_with(b"msch") {Ok(ss.deserialize(&mut DataRead::new(body))?)}
+	else {Ok(ss.deserialize_base64(std::str::from_utf8(body)?.trim())?)}
+}
+
+// things that load fine but probably aren't intended
+fn validate(schematic: &Schematic) -> Vec<String>
+{
+	let mut problems = Vec::new();
+	if let Err(e) = schematic.get_labels() {problems.push(format!("invalid labels: {e}"));}
+	if schematic.get_tags().get("name").is_none_or(|n| n.trim().is_empty()) {problems.push("the schematic has no name".to_owned());}
+	let mut blocks = Vec::from_iter(schematic.block_iter());
+	blocks.sort_by_key(|p| (p.get_pos().1, p.get_pos().0));
+	for p in blocks
+	{
+		let pos = p.get_pos();
+		let name = p.get_block().get_name();
+		if p.get_block().get_build_cost().is_none() {problems.push(format!("{name} at {} / {} is sandbox only", pos.0, pos.1));}
+		let Some(proc) = p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>())
+		else {continue;};
+		for link in proc.get_links()
+		{
+			let (dx, dy) = link.get_pos();
+			let (x, y) = (pos.0 as i32 + dx as i32, pos.1 as i32 + dy as i32);
+			let target = u16::try_from(x).ok().zip(u16::try_from(y).ok()).and_then(|(x, y)| schematic.get(x, y).ok().flatten());
+			if target.is_none()
+			{
+				problems.push(format!("{name} at {} / {} links {:?} to {x} / {y}, which is empty", pos.0, pos.1, link.get_name()));
+			}
+		}
+	}
+	problems
+}
+
+fn handle(reg: &BlockRegistry, req: &Request) -> Response
+{
+	let endpoint = req.path.trim_end_matches('/');
+	if endpoint.is_empty()
+	{
+		return if req.method == "GET" {Response::text(200, USAGE)} else {Response::error(405, "expected GET")};
+	}
+	if !matches!(endpoint, "/info" | "/cost" | "/validate" | "/render" | "/convert") {return Response::error(404, "no such endpoint");}
+	if req.method != "POST" {return Response::error(405, "expected POST");}
+	let schematic = match read_body(reg, &req.body)
+	{
+		Ok(s) => s,
+		Err(e) =>
+		{
+			let mut value = error_json("body", "Could not read schematic", e.as_ref());
+			// an unreadable schematic is the answer to the question, not an error
+			if endpoint != "/validate" {return Response::json(400, &value);}
+			if let Value::Object(ref mut fields) = value {fields.insert(0, ("valid".to_owned(), Value::from(false)));}
+			return Response::json(200, &value);
+		},
+	};
+	match endpoint
+	{
+		"/info" => Response::json(200, &schematic_json("body", &schematic)),
+		"/cost" =>
+		{
+			let (cost, sandbox) = schematic.compute_total_cost();
+			let items = cost.iter_nonzero().map(|(ty, cnt)| (ty.get_name().to_owned(), Value::from(cnt))).collect();
+			Response::json(200, &Value::Object(vec![("cost".to_owned(), Value::Object(items)), ("sandbox".to_owned(), Value::from(sandbox))]))
+		},
+		"/validate" =>
+		{
+			let problems = validate(&schematic);
+			Response::json(200, &Value::Object(vec![
+				("valid".to_owned(), Value::from(true)),
+				("warnings".to_owned(), Value::Array(problems.into_iter().map(Value::from).collect())),
+			]))
+		},
+		"/render" | "/convert" =>
+		{
+			let (key, default) = if endpoint == "/render" {("format", Format::Png)} else {("to", Format::Base64)};
+			let format = match req.get_param(key).map(Format::parse)
+			{
+				None => default,
+				Some(Some(f)) if (endpoint == "/render") == matches!(f, Format::Png | Format::Svg) => f,
+				Some(..) => return Response::error(400, &format!("unsupported {key}")),
+			};
+			let scale = match req.get_param("scale").map(str::parse::<u32>)
+			{
+				None => 16,
+				Some(Ok(n)) if n > 0 && n <= 64 => n,
+				Some(..) => return Response::error(400, "invalid scale, expected a number from 1 to 64"),
+			};
+			let Some(body) = write_output(reg, &schematic, format, scale)
+			else {return Response::error(500, "could not serialize schematic");};
+			let content_type = match format
+			{
+				Format::Msch => "application/octet-stream",
+				Format::Json => "application/json",
+				Format::Png => "image/png",
+				Format::Svg => "image/svg+xml",
+				Format::Base64 | Format::Text | Format::Ascii => "text/plain; charset=utf-8",
+			};
+			Response{status: 200, content_type, body}
+		},
+		_ => unreachable!(),
+	}
+}
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	let opt_port = handler.add(ArgOption::new(Some('p'), Some(Cow::Borrowed("port")), ArgCount::Required(1))).unwrap();
+	let opt_address = handler.add(ArgOption::new(Some('a'), Some(Cow::Borrowed("address")), ArgCount::Required(1))).unwrap();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let port = match handler.get_value(opt_port).get_value()
+	{
+		None => 8080,
+		Some(s) =>
+		{
+			let Ok(port) = s.parse::<u16>()
+			else
+			{
+				eprintln!("Invalid port {s:?}");
+				process::exit(1);
+			};
+			port
+		},
+	};
+	// only reachable from this machine unless asked otherwise
+	let address = handler.get_value(opt_address).get_value().map_or("127.0.0.1", String::as_str);
+	let listener = match TcpListener::bind((address, port))
+	{
+		Ok(l) => l,
+		Err(e) =>
+		{
+			print_err!(e, "Could not listen on {address}:{port}");
+			process::exit(1);
+		},
+	};
+	match listener.local_addr()
+	{
+		Ok(addr) => eprintln!("Listening on http://{addr}/"),
+		Err(..) => eprintln!("Listening on {address}:{port}"),
+	}
+	serve(&listener, &build_registry());
+}
+
+fn serve(listener: &TcpListener, reg: &BlockRegistry)
+{
+	let active = AtomicUsize::new(0);
+	thread::scope(|scope|
+	{
+		for stream in listener.incoming()
+		{
+			let stream = match stream
+			{
+				Ok(s) => s,
+				Err(e) =>
+				{
+					print_err!(e, "Could not accept connection");
+					continue;
+				},
+			};
+			if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS
+			{
+				active.fetch_sub(1, Ordering::SeqCst);
+				// the response is small enough to not block, but don't rely on it
+				if stream.set_write_timeout(Some(Duration::from_secs(1))).is_ok()
+				{
+					let _ = write_response(&stream, &Response::error(503, "too many connections"));
+				}
+				eprintln!("- 503");
+				continue;
+			}
+			// a client that is slow to send its request shouldn't hold up everyone else
+			let active = &active;
+			scope.spawn(move ||
+			{
+				handle_connection(reg, stream);
+				active.fetch_sub(1, Ordering::SeqCst);
+			});
+		}
+	});
+}
+
+fn handle_connection(reg: &BlockRegistry, stream: TcpStream)
+{
+	// a client that stops sending shouldn't keep its thread around forever
+	let timeout = Some(Duration::from_secs(10));
+	if stream.set_read_timeout(timeout).is_err() || stream.set_write_timeout(timeout).is_err() {return;}
+	let (line, resp) = match read_request(&stream)
+	{
+		Ok(req) => (format!("{} {}", req.method, req.path), handle(reg, &req)),
+		Err(resp) => ("-".to_owned(), resp),
+	};
+	eprintln!("{line} {}", resp.status);
+	if let Err(e) = write_response(&stream, &resp)
+	{
+		print_err!(e, "Could not send response");
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use std::net::Shutdown;
+	
+	use plandustry::block::Rotation;
+	use plandustry::block::transport::CONVEYOR;
+	use plandustry::data::dynamic::DynData;
+	
+	use super::*;
+	
+	fn read_raw(raw: &[u8]) -> Result<Request, Response>
+	{
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		client.write_all(raw).unwrap();
+		client.shutdown(Shutdown::Write).unwrap();
+		let (stream, _) = listener.accept().unwrap();
+		read_request(&stream)
+	}
+	
+	fn post(path: &str, body: &[u8]) -> Request
+	{
+		let (path, query) = path.split_once('?').unwrap_or((path, ""));
+		let query = query.split('&').filter_map(|p| p.split_once('=')).map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+		Request{method: "POST".to_owned(), path: path.to_owned(), query, body: body.to_vec()}
+	}
+	
+	#[test]
+	fn read()
+	{
+		let req = read_raw(b"POST /render?format=svg&scale=4 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+		assert_eq!((req.method.as_str(), req.path.as_str()), ("POST", "/render"));
+		assert_eq!((req.get_param("format"), req.get_param("scale"), req.get_param("to")), (Some("svg"), Some("4"), None));
+		assert_eq!(req.body, b"hello");
+		let req = read_raw(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+		assert!(req.body.is_empty());
+		
+		let status = |raw: &[u8]| read_raw(raw).err().map(|r| r.status);
+		assert_eq!(status(b"GET /\r\n\r\n"), Some(400));
+		assert_eq!(status(b"GET / HTTP/2\r\n\r\n"), Some(400));
+		assert_eq!(status(b"GET / HTTP/1.1\r\nbroken\r\n\r\n"), Some(400));
+		assert_eq!(status(b"POST /info HTTP/1.1\r\n\r\n"), Some(411));
+		assert_eq!(status(b"POST /info HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n"), Some(413));
+		assert_eq!(status(b"POST /info HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"), Some(501));
+		assert_eq!(status(b"POST /info HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"), Some(400));
+		assert_eq!(status(&[b"GET / HTTP/1.1\r\n".as_slice(), &b"X: y\r\n".repeat(MAX_HEADERS + 1), b"\r\n"].concat()), Some(431));
+	}
+	
+	#[test]
+	fn endpoints()
+	{
+		let reg = build_registry();
+		let mut schematic = Schematic::new(2, 1);
+		schematic.set(0, 0, &CONVEYOR, DynData::Empty, Rotation::Right).unwrap();
+		schematic.set(1, 0, &CONVEYOR, DynData::Empty, Rotation::Right).unwrap();
+		let b64 = SchematicSerializer(&reg).serialize_base64(&schematic).unwrap();
+		
+		let get = Request{method: "GET".to_owned(), path: "/".to_owned(), query: Vec::new(), body: Vec::new()};
+		assert_eq!(handle(&reg, &get).status, 200);
+		assert_eq!(handle(&reg, &post("/", b"")).status, 405);
+		assert_eq!(handle(&reg, &post("/nothing", b"")).status, 404);
+		assert_eq!(handle(&reg, &Request{path: "/info".to_owned(), ..get}).status, 405);
+		
+		let resp = handle(&reg, &post("/cost", b64.as_bytes()));
+		assert_eq!((resp.status, resp.content_type), (200, "application/json"));
+		assert_eq!(String::from_utf8(resp.body).unwrap(), "{\"cost\":{\"copper\":2},\"sandbox\":false}\n");
+		assert_eq!(handle(&reg, &post("/info", b"garbage")).status, 400);
+		let resp = handle(&reg, &post("/validate", b"garbage"));
+		assert_eq!(resp.status, 200);
+		assert!(String::from_utf8(resp.body).unwrap().starts_with("{\"valid\":false"));
+		
+		let resp = handle(&reg, &post("/convert?to=msch", b64.as_bytes()));
+		assert_eq!((resp.status, resp.content_type), (200, "application/octet-stream"));
+		assert!(resp.body.starts_with(b"msch"));
+		assert_eq!(handle(&reg, &post("/convert?to=png", b64.as_bytes())).status, 400);
+		assert_eq!(handle(&reg, &post("/render?format=svg&scale=1", b64.as_bytes())).content_type, "image/svg+xml");
+		assert_eq!(handle(&reg, &post("/render?scale=0", b64.as_bytes())).status, 400);
+	}
+	
+	#[test]
+	fn slow_client()
+	{
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		thread::spawn(move || serve(&listener, &build_registry()));
+		// connects but never sends anything
+		let _idle = TcpStream::connect(addr).unwrap();
+		let mut client = TcpStream::connect(addr).unwrap();
+		client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+		let mut resp = String::new();
+		client.read_to_string(&mut resp).unwrap();
+		assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+	}
+	
+	#[test]
+	fn connection_limit()
+	{
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		thread::spawn(move || serve(&listener, &build_registry()));
+		// each of these announces a large body it never sends
+		let idle = Vec::from_iter((0..MAX_CONNECTIONS).map(|_|
+		{
+			let mut client = TcpStream::connect(addr).unwrap();
+			client.write_all(format!("POST /info HTTP/1.1\r\nContent-Length: {MAX_BODY}\r\n\r\n").as_bytes()).unwrap();
+			client
+		}));
+		let mut client = TcpStream::connect(addr).unwrap();
+		client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		let mut resp = String::new();
+		client.read_to_string(&mut resp).unwrap();
+		assert!(resp.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+		drop(idle);
+	}
+}
@@ -0,0 +1,30 @@
+use std::env::Args;
+use std::process;
+
+use plandustry::block::build_registry;
+use plandustry::data::text::to_text;
+
+use crate::{print_err, read_schematic};
+use crate::args::{self, OptionHandler};
+
+pub fn main(mut args: Args, arg_off: usize)
+{
+	let mut handler = OptionHandler::new();
+	if let Err(e) = args::parse(&mut args, &mut handler, arg_off)
+	{
+		print_err!(e, "Command error");
+		process::exit(1);
+	}
+	
+	let literals = handler.get_literals();
+	if literals.len() != 1
+	{
+		eprintln!("Expected a single schematic, got {} arguments", literals.len());
+		process::exit(1);
+	}
+	let reg = build_registry();
+	// git treats a failing textconv as an error instead of printing a misleading diff
+	let Some(schematic) = read_schematic(&reg, &literals[0])
+	else {process::exit(1);};
+	print!("{}", to_text(&schematic));
+}
@@ -64,3 +64,37 @@ impl fmt::Display for Type
 		}
 	}
 }
+
+impl Type
+{
+	// the color the game uses for the item (in sorters, conveyors and the like)
+	pub fn get_color(self) -> [u8; 3]
+	{
+		let rgb: u32 = match self
+		{
+			Self::Copper => 0xD99D73,
+			Self::Lead => 0x8C7FA9,
+			Self::Metaglass => 0xEBEEF5,
+			Self::Graphite => 0xB2C6D2,
+			Self::Sand => 0xF7CBA4,
+			Self::Coal => 0x272727,
+			Self::Titanium => 0x8DA1E3,
+			Self::Thorium => 0xF9A3C7,
+			Self::Scrap => 0x777777,
+			Self::Silicon => 0x53565C,
+			Self::Plastanium => 0xCBD97F,
+			Self::PhaseFabric => 0xF4BA6E,
+			Self::SurgeAlloy => 0xF3E979,
+			Self::SporePod => 0x7457CE,
+			Self::BlastCompound => 0xFF795E,
+			Self::Pyratite => 0xFFAA5F,
+			Self::Beryllium => 0x3A8F64,
+			Self::Tungsten => 0x768A9A,
+			Self::Oxide => 0xE4FFD6,
+			Self::Carbide => 0x89769A,
+			Self::FissileMatter => 0x5E988D,
+			Self::DormantCyst => 0xDF824D,
+		};
+		[(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+	}
+}
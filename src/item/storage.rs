@@ -275,7 +275,7 @@ impl Storage
 					let actual = (*l - min_each).min(*r - min_each);
 					*l -= actual;
 					*r -= actual;
-					subbed -= actual as u64;
+					subbed += actual as u64;
 				}
 			}
 			// update totals
@@ -297,7 +297,8 @@ impl Storage
 	
 	pub fn clear(&mut self)
 	{
-		self.base.clear()
+		self.base.clear();
+		self.total = 0;
 	}
 }
 
@@ -408,3 +409,33 @@ impl<'l> Iterator for Iter<'l>
 }
 
 impl<'l> FusedIterator for Iter<'l> where Enumerate<slice::Iter<'l, u32>>: FusedIterator {}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn diff_all()
+	{
+		let mut lhs = Storage::new();
+		lhs.set(item::Type::Copper, 10);
+		lhs.set(item::Type::Lead, 3);
+		let mut rhs = Storage::new();
+		rhs.set(item::Type::Copper, 4);
+		rhs.set(item::Type::Lead, 5);
+		assert_eq!(lhs.diff_all(&mut rhs, 0), (7, 6, 2));
+		assert_eq!(lhs.get(item::Type::Copper), 6);
+		assert_eq!(rhs.get(item::Type::Lead), 2);
+	}
+	
+	#[test]
+	fn clear()
+	{
+		let mut storage = Storage::new();
+		storage.set(item::Type::Copper, 5);
+		storage.clear();
+		assert!(storage.is_empty());
+		assert_eq!(storage.get_total(), 0);
+	}
+}
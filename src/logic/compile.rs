@@ -0,0 +1,896 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::logic::mlog::{self, MAX_CODE_LEN, MAX_INSTRUCTIONS};
+
+// compiled code with the links it refers to (relative to the processor)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Program
+{
+	pub code: String,
+	pub links: Vec<(String, i16, i16)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pos
+{
+	pub line: usize,
+	pub col: usize,
+}
+
+impl fmt::Display for Pos
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		write!(f, "{}:{}", self.line, self.col)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TokenKind
+{
+	Ident,
+	// numbers, strings, @constants and %colors are passed through as they are
+	Literal,
+	Punct,
+	End,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token<'s>
+{
+	kind: TokenKind,
+	text: &'s str,
+	pos: Pos,
+}
+
+const PUNCT: &[&str] = &[
+	"===", "**", "==", "!=", "<=", ">=", "<<", ">>", "&&", "||", "+=", "-=", "*=", "/=", "%=", "+", "-", "*", "/", "%", "<", ">", "!", "~", "&", "|",
+	"^", "=", "(", ")", "{", "}", ",", ";", ".",
+];
+
+// whether a percent sign starts a color (%rrggbb or %rrggbbaa) rather than being the remainder operator
+fn is_color(text: &str) -> bool
+{
+	let len = text[1..].bytes().take_while(u8::is_ascii_alphanumeric).count();
+	(len == 6 || len == 8) && text[1..=len].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token<'_>>, CompileError>
+{
+	let mut tokens = Vec::new();
+	for (line, text) in source.lines().enumerate()
+	{
+		let bytes = text.as_bytes();
+		let mut i = 0;
+		while i < bytes.len()
+		{
+			let c = bytes[i];
+			let pos = Pos{line: line + 1, col: text[..i].chars().count() + 1};
+			let start = i;
+			let kind = if c == b' ' || c == b'\t'
+			{
+				i += 1;
+				continue;
+			}
+			else if text[i..].starts_with("//") {break;}
+			else if c.is_ascii_alphabetic() || c == b'_'
+			{
+				while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {i += 1;}
+				TokenKind::Ident
+			}
+			else if c.is_ascii_digit() || c == b'@' || (c == b'%' && is_color(&text[i..]))
+			{
+				i += 1;
+				let hex = text[start..].starts_with("0x") || text[start..].starts_with("0b");
+				while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'_' | b'-' | b'+' | b'.'))
+				{
+					// a sign is only part of exponents and a minus of names, not other numbers (so 1-2 still works)
+					let exponent = c != b'@' && !hex && matches!(bytes[i - 1], b'e' | b'E');
+					if matches!(bytes[i], b'-' | b'+') && !exponent && (bytes[i] == b'+' || c != b'@') {break;}
+					// names end at a dot, which reads a property
+					if bytes[i] == b'.' && c == b'@' {break;}
+					i += 1;
+				}
+				if c != b'@' && !mlog::is_literal(&text[start..i])
+				{
+					return Err(CompileError::Syntax{pos, message: format!("invalid number {:?}", &text[start..i])});
+				}
+				TokenKind::Literal
+			}
+			else if c == b'"'
+			{
+				i += 1;
+				while i < bytes.len() && bytes[i] != b'"' {i += 1;}
+				if i >= bytes.len() {return Err(CompileError::Syntax{pos, message: "unterminated string".to_owned()});}
+				i += 1;
+				TokenKind::Literal
+			}
+			else
+			{
+				let Some(p) = PUNCT.iter().find(|p| text[i..].starts_with(**p))
+				else
+				{
+					let c = text[i..].chars().next().unwrap();
+					return Err(CompileError::Syntax{pos, message: format!("unexpected character {c:?}")});
+				};
+				i += p.len();
+				TokenKind::Punct
+			};
+			tokens.push(Token{kind, text: &text[start..i], pos});
+		}
+	}
+	let pos = Pos{line: source.lines().count() + 1, col: 1};
+	tokens.push(Token{kind: TokenKind::End, text: "", pos});
+	Ok(tokens)
+}
+
+#[derive(Clone, Debug)]
+enum Expr
+{
+	Literal(String),
+	Var(String, Pos),
+	// "-", "!" or "~"
+	Unary(&'static str, Box<Expr>),
+	// the operation as in mlog, except "lor" for short-circuiting or
+	Binary(&'static str, Box<Expr>, Box<Expr>),
+	Call(String, Vec<Expr>, Pos),
+	Sensor(Box<Expr>, String),
+}
+
+#[derive(Clone, Debug)]
+enum Stmt
+{
+	Assign(String, Expr, Pos),
+	If(Expr, Vec<Stmt>, Vec<Stmt>),
+	While(Expr, Vec<Stmt>),
+	Break(Pos),
+	Continue(Pos),
+	Call(String, Vec<Expr>, Pos),
+}
+
+struct Macro
+{
+	params: Vec<String>,
+	body: Vec<Stmt>,
+}
+
+struct Parser<'s>
+{
+	tokens: Vec<Token<'s>>,
+	idx: usize,
+	macros: HashMap<String, Macro>,
+	links: Vec<(String, i16, i16)>,
+	aliases: HashMap<String, String>,
+}
+
+const BINARY: &[&[(&str, &str)]] = &[
+	&[("||", "lor")],
+	&[("&&", "land")],
+	&[("|", "or")],
+	&[("^", "xor")],
+	&[("&", "and")],
+	&[("===", "strictEqual"), ("==", "equal"), ("!=", "notEqual")],
+	&[("<=", "lessThanEq"), (">=", "greaterThanEq"), ("<", "lessThan"), (">", "greaterThan")],
+	&[("<<", "shl"), (">>", "shr")],
+	&[("+", "add"), ("-", "sub")],
+	&[("*", "mul"), ("/", "div"), ("%", "mod")],
+];
+
+impl<'s> Parser<'s>
+{
+	fn peek(&self) -> Token<'s>
+	{
+		self.tokens[self.idx]
+	}
+	
+	fn next(&mut self) -> Token<'s>
+	{
+		let tok = self.tokens[self.idx];
+		if tok.kind != TokenKind::End {self.idx += 1;}
+		tok
+	}
+	
+	fn accept(&mut self, text: &str) -> bool
+	{
+		let tok = self.peek();
+		if tok.kind != TokenKind::Literal && tok.text == text
+		{
+			self.idx += 1;
+			true
+		}
+		else {false}
+	}
+	
+	fn unexpected(&self, expected: &str) -> CompileError
+	{
+		let tok = self.peek();
+		let found = if tok.kind == TokenKind::End {"the end".to_owned()} else {format!("{:?}", tok.text)};
+		CompileError::Syntax{pos: tok.pos, message: format!("expected {expected}, found {found}")}
+	}
+	
+	fn expect(&mut self, text: &str) -> Result<(), CompileError>
+	{
+		if self.accept(text) {Ok(())} else {Err(self.unexpected(&format!("{text:?}")))}
+	}
+	
+	fn ident(&mut self) -> Result<Token<'s>, CompileError>
+	{
+		if self.peek().kind == TokenKind::Ident {Ok(self.next())} else {Err(self.unexpected("a name"))}
+	}
+	
+	fn block(&mut self) -> Result<Vec<Stmt>, CompileError>
+	{
+		self.expect("{")?;
+		let mut body = Vec::new();
+		while !self.accept("}")
+		{
+			if self.peek().kind == TokenKind::End {return Err(self.unexpected("\"}\""));}
+			self.statement(&mut body)?;
+		}
+		Ok(body)
+	}
+	
+	fn args(&mut self) -> Result<Vec<Expr>, CompileError>
+	{
+		let mut args = Vec::new();
+		if self.accept(")") {return Ok(args);}
+		loop
+		{
+			args.push(self.expr(0)?);
+			if self.accept(")") {return Ok(args);}
+			self.expect(",")?;
+		}
+	}
+	
+	fn offset(&mut self) -> Result<i16, CompileError>
+	{
+		let neg = self.accept("-");
+		let tok = self.next();
+		match tok.text.parse::<i16>()
+		{
+			Ok(v) if tok.kind == TokenKind::Literal => Ok(if neg {-v} else {v}),
+			_ => Err(CompileError::Syntax{pos: tok.pos, message: format!("invalid offset {:?}", tok.text)}),
+		}
+	}
+	
+	// parses one statement, definitions are recorded instead of being added to the body
+	fn statement(&mut self, body: &mut Vec<Stmt>) -> Result<(), CompileError>
+	{
+		let tok = self.peek();
+		if tok.kind != TokenKind::Ident {return Err(self.unexpected("a statement"));}
+		match tok.text
+		{
+			"if" =>
+			{
+				self.next();
+				let cond = self.expr(0)?;
+				let then = self.block()?;
+				let otherwise = if self.accept("else")
+				{
+					if self.peek().text == "if"
+					{
+						let mut nested = Vec::new();
+						self.statement(&mut nested)?;
+						nested
+					}
+					else {self.block()?}
+				}
+				else {Vec::new()};
+				body.push(Stmt::If(cond, then, otherwise));
+			},
+			"while" =>
+			{
+				self.next();
+				let cond = self.expr(0)?;
+				body.push(Stmt::While(cond, self.block()?));
+			},
+			"break" | "continue" =>
+			{
+				self.next();
+				self.expect(";")?;
+				body.push(if tok.text == "break" {Stmt::Break(tok.pos)} else {Stmt::Continue(tok.pos)});
+			},
+			"macro" =>
+			{
+				self.next();
+				let name = self.ident()?;
+				self.expect("(")?;
+				let mut params = Vec::new();
+				if !self.accept(")")
+				{
+					loop
+					{
+						params.push(self.ident()?.text.to_owned());
+						if self.accept(")") {break;}
+						self.expect(",")?;
+					}
+				}
+				let body = self.block()?;
+				if self.macros.insert(name.text.to_owned(), Macro{params, body}).is_some()
+				{
+					return Err(CompileError::Duplicate{pos: name.pos, name: name.text.to_owned()});
+				}
+			},
+			"link" =>
+			{
+				self.next();
+				let name = self.ident()?;
+				if self.accept("at")
+				{
+					let (x, y) = (self.offset()?, self.offset()?);
+					if self.links.iter().any(|(n, ..)| n == name.text)
+					{
+						return Err(CompileError::Duplicate{pos: name.pos, name: name.text.to_owned()});
+					}
+					self.links.push((name.text.to_owned(), x, y));
+				}
+				else
+				{
+					self.expect("=")?;
+					let target = self.ident()?;
+					self.aliases.insert(name.text.to_owned(), target.text.to_owned());
+				}
+				self.expect(";")?;
+			},
+			_ =>
+			{
+				self.next();
+				// declarations are only for readability, every variable is global in mlog
+				if tok.text == "let" && self.peek().kind == TokenKind::Ident {return self.statement(body);}
+				if self.accept("(")
+				{
+					let args = self.args()?;
+					body.push(Stmt::Call(tok.text.to_owned(), args, tok.pos));
+				}
+				else
+				{
+					let op = self.peek().text;
+					let value = match op
+					{
+						"=" => {self.next(); self.expr(0)?},
+						"+=" | "-=" | "*=" | "/=" | "%=" =>
+						{
+							self.next();
+							let op = BINARY.iter().flat_map(|l| l.iter()).find(|(p, _)| *p == &op[..1]).unwrap().1;
+							Expr::Binary(op, Box::new(Expr::Var(tok.text.to_owned(), tok.pos)), Box::new(self.expr(0)?))
+						},
+						_ => return Err(self.unexpected("an assignment or call")),
+					};
+					body.push(Stmt::Assign(tok.text.to_owned(), value, tok.pos));
+				}
+				self.expect(";")?;
+			},
+		}
+		Ok(())
+	}
+	
+	fn expr(&mut self, level: usize) -> Result<Expr, CompileError>
+	{
+		if level >= BINARY.len() {return self.unary();}
+		let mut left = self.expr(level + 1)?;
+		'outer: loop
+		{
+			for &(p, op) in BINARY[level]
+			{
+				if self.accept(p)
+				{
+					left = Expr::Binary(op, Box::new(left), Box::new(self.expr(level + 1)?));
+					continue 'outer;
+				}
+			}
+			return Ok(left);
+		}
+	}
+	
+	fn unary(&mut self) -> Result<Expr, CompileError>
+	{
+		for op in ["-", "!", "~"]
+		{
+			if self.accept(op) {return Ok(Expr::Unary(op, Box::new(self.unary()?)));}
+		}
+		let base = self.postfix()?;
+		// right associative and binding tighter than unary minus
+		if self.accept("**") {Ok(Expr::Binary("pow", Box::new(base), Box::new(self.unary()?)))}
+		else {Ok(base)}
+	}
+	
+	fn postfix(&mut self) -> Result<Expr, CompileError>
+	{
+		let tok = self.next();
+		let mut result = match tok.kind
+		{
+			TokenKind::Literal => Expr::Literal(tok.text.to_owned()),
+			TokenKind::Ident if matches!(tok.text, "true" | "false" | "null") => Expr::Literal(tok.text.to_owned()),
+			TokenKind::Ident if self.accept("(") => Expr::Call(tok.text.to_owned(), self.args()?, tok.pos),
+			TokenKind::Ident => Expr::Var(tok.text.to_owned(), tok.pos),
+			TokenKind::Punct if tok.text == "(" =>
+			{
+				let inner = self.expr(0)?;
+				self.expect(")")?;
+				inner
+			},
+			_ =>
+			{
+				self.idx -= 1;
+				return Err(self.unexpected("a value"));
+			},
+		};
+		while self.accept(".")
+		{
+			let field = self.ident()?;
+			result = Expr::Sensor(Box::new(result), format!("@{}", field.text));
+		}
+		Ok(result)
+	}
+}
+
+enum Line
+{
+	Code(String),
+	// a jump to a label with the condition and its operands
+	Jump(usize, String),
+}
+
+struct Codegen<'p>
+{
+	macros: &'p HashMap<String, Macro>,
+	aliases: &'p HashMap<String, String>,
+	lines: Vec<Line>,
+	labels: Vec<Option<usize>>,
+	// the operands bound to the parameters of the macro being expanded
+	params: Vec<HashMap<String, String>>,
+	expanding: Vec<String>,
+	// continue and break labels of the enclosing loops
+	loops: Vec<(usize, usize)>,
+	temps: usize,
+}
+
+const VALUE_OPS: &[&str] = &[
+	"max", "min", "abs", "floor", "ceil", "sqrt", "sin", "cos", "tan", "asin", "acos", "atan", "log", "log10", "rand", "angle", "angleDiff", "len",
+	"noise", "idiv", "pow",
+];
+
+fn inverse(op: &str) -> Option<&'static str>
+{
+	match op
+	{
+		"equal" => Some("notEqual"),
+		"notEqual" => Some("equal"),
+		"lessThan" => Some("greaterThanEq"),
+		"lessThanEq" => Some("greaterThan"),
+		"greaterThan" => Some("lessThanEq"),
+		"greaterThanEq" => Some("lessThan"),
+		_ => None,
+	}
+}
+
+impl<'p> Codegen<'p>
+{
+	fn emit(&mut self, line: String)
+	{
+		self.lines.push(Line::Code(line));
+	}
+	
+	fn label(&mut self) -> usize
+	{
+		self.labels.push(None);
+		self.labels.len() - 1
+	}
+	
+	fn place(&mut self, label: usize)
+	{
+		self.labels[label] = Some(self.lines.len());
+	}
+	
+	fn jump(&mut self, label: usize, cond: String)
+	{
+		self.lines.push(Line::Jump(label, cond));
+	}
+	
+	fn temp(&mut self) -> String
+	{
+		self.temps += 1;
+		format!("__t{}", self.temps - 1)
+	}
+	
+	fn resolve(&self, name: &str) -> String
+	{
+		if let Some(bound) = self.params.last().and_then(|p| p.get(name)) {bound.clone()}
+		else {self.aliases.get(name).cloned().unwrap_or_else(|| name.to_owned())}
+	}
+	
+	fn finish(&mut self, value: String, dest: Option<&str>) -> String
+	{
+		match dest
+		{
+			Some(d) if d != value =>
+			{
+				self.emit(format!("set {d} {value}"));
+				d.to_owned()
+			},
+			_ => value,
+		}
+	}
+	
+	fn dest(&mut self, dest: Option<&str>) -> String
+	{
+		dest.map_or_else(|| self.temp(), str::to_owned)
+	}
+	
+	// evaluates the expression, into dest if given, and returns the operand holding the result
+	fn expr(&mut self, e: &Expr, dest: Option<&str>) -> Result<String, CompileError>
+	{
+		match e
+		{
+			Expr::Literal(v) => Ok(self.finish(v.clone(), dest)),
+			Expr::Var(name, _) =>
+			{
+				let v = self.resolve(name);
+				Ok(self.finish(v, dest))
+			},
+			Expr::Unary(op, a) =>
+			{
+				let a = self.expr(a, None)?;
+				let d = self.dest(dest);
+				match *op
+				{
+					"-" => self.emit(format!("op sub {d} 0 {a}")),
+					"!" => self.emit(format!("op equal {d} {a} false")),
+					_ => self.emit(format!("op not {d} {a} 0")),
+				}
+				Ok(d)
+			},
+			Expr::Binary("lor", ..) =>
+			{
+				let (yes, done) = (self.label(), self.label());
+				self.jump_if(e, yes, true)?;
+				let d = self.dest(dest);
+				self.emit(format!("set {d} false"));
+				self.jump(done, "always".to_owned());
+				self.place(yes);
+				self.emit(format!("set {d} true"));
+				self.place(done);
+				Ok(d)
+			},
+			Expr::Binary(op, a, b) =>
+			{
+				let a = self.expr(a, None)?;
+				let b = self.expr(b, None)?;
+				let d = self.dest(dest);
+				self.emit(format!("op {op} {d} {a} {b}"));
+				Ok(d)
+			},
+			Expr::Sensor(target, field) =>
+			{
+				let t = self.expr(target, None)?;
+				let d = self.dest(dest);
+				self.emit(format!("sensor {d} {t} {field}"));
+				Ok(d)
+			},
+			Expr::Call(name, args, pos) =>
+			{
+				let expected = match name.as_str()
+				{
+					n if VALUE_OPS.contains(&n) => if matches!(n, "max" | "min" | "angle" | "angleDiff" | "len" | "noise" | "idiv" | "pow") {2} else {1},
+					"sensor" | "read" | "lookup" => 2,
+					"getlink" => 1,
+					"packcolor" => 4,
+					_ if self.macros.contains_key(name) => return Err(CompileError::Value{pos: *pos, name: name.clone()}),
+					_ => return Err(CompileError::Undefined{pos: *pos, name: name.clone()}),
+				};
+				if args.len() != expected
+				{
+					return Err(CompileError::Arguments{pos: *pos, name: name.clone(), expected, found: args.len()});
+				}
+				let mut ops = Vec::with_capacity(args.len());
+				for a in args
+				{
+					ops.push(self.expr(a, None)?);
+				}
+				let d = self.dest(dest);
+				match name.as_str()
+				{
+					"lookup" => self.emit(format!("lookup {} {d} {}", ops[0], ops[1])),
+					n if VALUE_OPS.contains(&n) => self.emit(format!("op {n} {d} {} {}", ops[0], ops.get(1).map_or("0", String::as_str))),
+					n => self.emit(format!("{n} {d} {}", ops.join(" "))),
+				}
+				Ok(d)
+			},
+		}
+	}
+	
+	// jumps to the label if the condition is (or isn't) met
+	fn jump_if(&mut self, e: &Expr, target: usize, when: bool) -> Result<(), CompileError>
+	{
+		match e
+		{
+			Expr::Unary("!", a) => self.jump_if(a, target, !when)?,
+			Expr::Binary(op @ ("land" | "lor"), a, b) =>
+			{
+				// both need to fail for "or" to fail, and succeed for "and" to succeed
+				if (*op == "lor") == when
+				{
+					self.jump_if(a, target, when)?;
+					self.jump_if(b, target, when)?;
+				}
+				else
+				{
+					let skip = self.label();
+					self.jump_if(a, skip, !when)?;
+					self.jump_if(b, target, when)?;
+					self.place(skip);
+				}
+			},
+			Expr::Binary(op, a, b) if inverse(op).is_some() =>
+			{
+				let a = self.expr(a, None)?;
+				let b = self.expr(b, None)?;
+				let cond = if when {op} else {inverse(op).unwrap()};
+				self.jump(target, format!("{cond} {a} {b}"));
+			},
+			Expr::Literal(v) if matches!(v.as_str(), "true" | "false" | "null") || v.parse::<f64>().is_ok() =>
+			{
+				let truthy = v == "true" || v.parse::<f64>().is_ok_and(|n| n != 0.0);
+				if truthy == when {self.jump(target, "always".to_owned());}
+			},
+			_ =>
+			{
+				let v = self.expr(e, None)?;
+				self.jump(target, format!("{} {v} false", if when {"notEqual"} else {"equal"}));
+			},
+		}
+		Ok(())
+	}
+	
+	fn assign_target(&self, name: &str, pos: Pos) -> Result<String, CompileError>
+	{
+		let target = self.resolve(name);
+		if mlog::is_literal(&target) || (target.starts_with('@') && target != "@counter")
+		{
+			return Err(CompileError::Assign{pos, target});
+		}
+		Ok(target)
+	}
+	
+	fn statements(&mut self, body: &[Stmt]) -> Result<(), CompileError>
+	{
+		for stmt in body
+		{
+			// temporaries only live for one statement, except for macro arguments
+			if self.params.is_empty() {self.temps = 0;}
+			self.statement(stmt)?;
+		}
+		Ok(())
+	}
+	
+	fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError>
+	{
+		match stmt
+		{
+			Stmt::Assign(name, value, pos) =>
+			{
+				let target = self.assign_target(name, *pos)?;
+				self.expr(value, Some(&target))?;
+			},
+			Stmt::If(cond, then, otherwise) =>
+			{
+				let (other, end) = (self.label(), self.label());
+				self.jump_if(cond, other, false)?;
+				self.statements(then)?;
+				if !otherwise.is_empty() {self.jump(end, "always".to_owned());}
+				self.place(other);
+				self.statements(otherwise)?;
+				self.place(end);
+			},
+			Stmt::While(cond, body) =>
+			{
+				let (start, end) = (self.label(), self.label());
+				self.place(start);
+				self.jump_if(cond, end, false)?;
+				self.loops.push((start, end));
+				self.statements(body)?;
+				self.loops.pop();
+				self.jump(start, "always".to_owned());
+				self.place(end);
+			},
+			Stmt::Break(pos) | Stmt::Continue(pos) =>
+			{
+				let Some(&(start, end)) = self.loops.last()
+				else {return Err(CompileError::OutsideLoop{pos: *pos});};
+				self.jump(if matches!(stmt, Stmt::Break(..)) {end} else {start}, "always".to_owned());
+			},
+			Stmt::Call(name, args, pos) =>
+			{
+				if let Some(m) = self.macros.get(name)
+				{
+					if args.len() != m.params.len()
+					{
+						return Err(CompileError::Arguments{pos: *pos, name: name.clone(), expected: m.params.len(), found: args.len()});
+					}
+					if self.expanding.contains(name) {return Err(CompileError::Recursion{pos: *pos, name: name.clone()});}
+					let mut bound = HashMap::new();
+					for (param, a) in m.params.iter().zip(args)
+					{
+						bound.insert(param.clone(), self.expr(a, None)?);
+					}
+					// loops outside the macro can't be left from inside it
+					let loops = std::mem::take(&mut self.loops);
+					self.params.push(bound);
+					self.expanding.push(name.clone());
+					let result = self.statements(&m.body);
+					self.expanding.pop();
+					self.params.pop();
+					self.loops = loops;
+					return result;
+				}
+				if name == "print" && args.len() > 1
+				{
+					// one print per value, like string concatenation
+					for a in args
+					{
+						let v = self.expr(a, None)?;
+						self.emit(format!("print {v}"));
+					}
+					return Ok(());
+				}
+				let Some(info) = mlog::get_instruction(name)
+				else {return Err(CompileError::Undefined{pos: *pos, name: name.clone()});};
+				if args.len() > info.args.len()
+				{
+					return Err(CompileError::Arguments{pos: *pos, name: name.clone(), expected: info.args.len(), found: args.len()});
+				}
+				let mut line = name.clone();
+				for (i, a) in args.iter().enumerate()
+				{
+					let op = match a
+					{
+						// names of operations or outputs are used as they are
+						Expr::Var(n, p) if info.outputs.contains(&i) => self.assign_target(n, *p)?,
+						Expr::Var(n, _) if info.keywords.iter().any(|(k, _)| *k == i) => n.clone(),
+						_ => self.expr(a, None)?,
+					};
+					line.push(' ');
+					line.push_str(&op);
+				}
+				self.emit(line);
+			},
+		}
+		Ok(())
+	}
+}
+
+pub fn compile(source: &str) -> Result<Program, CompileError>
+{
+	let mut parser = Parser{tokens: tokenize(source)?, idx: 0, macros: HashMap::new(), links: Vec::new(), aliases: HashMap::new()};
+	let mut body = Vec::new();
+	while parser.peek().kind != TokenKind::End
+	{
+		parser.statement(&mut body)?;
+	}
+	let mut gen = Codegen
+	{
+		macros: &parser.macros, aliases: &parser.aliases, lines: Vec::new(), labels: Vec::new(), params: Vec::new(), expanding: Vec::new(),
+		loops: Vec::new(), temps: 0,
+	};
+	gen.statements(&body)?;
+	// jumps past the last instruction should start over, which the game doesn't do on its own
+	if gen.labels.contains(&Some(gen.lines.len())) {gen.emit("end".to_owned());}
+	if gen.lines.len() > MAX_INSTRUCTIONS {return Err(CompileError::TooManyInstructions(gen.lines.len()));}
+	let mut code = String::new();
+	for line in gen.lines.iter()
+	{
+		match line
+		{
+			Line::Code(s) => code.push_str(s),
+			Line::Jump(label, cond) => code.push_str(&format!("jump {} {cond}", gen.labels[*label].unwrap())),
+		}
+		code.push('\n');
+	}
+	if code.len() > MAX_CODE_LEN {return Err(CompileError::TooLong(code.len()));}
+	Ok(Program{code, links: parser.links})
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CompileError
+{
+	Syntax{pos: Pos, message: String},
+	Duplicate{pos: Pos, name: String},
+	Undefined{pos: Pos, name: String},
+	Value{pos: Pos, name: String},
+	Arguments{pos: Pos, name: String, expected: usize, found: usize},
+	Recursion{pos: Pos, name: String},
+	OutsideLoop{pos: Pos},
+	Assign{pos: Pos, target: String},
+	TooManyInstructions(usize),
+	TooLong(usize),
+}
+
+impl fmt::Display for CompileError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Syntax{pos, message} => write!(f, "{pos}: {message}"),
+			Self::Duplicate{pos, name} => write!(f, "{pos}: {name} is already defined"),
+			Self::Undefined{pos, name} => write!(f, "{pos}: no macro or instruction named {name}"),
+			Self::Value{pos, name} => write!(f, "{pos}: macro {name} has no value"),
+			Self::Arguments{pos, name, expected, found} => write!(f, "{pos}: {name} takes {expected} arguments, found {found}"),
+			Self::Recursion{pos, name} => write!(f, "{pos}: macro {name} expands to itself"),
+			Self::OutsideLoop{pos} => write!(f, "{pos}: break or continue outside of a loop"),
+			Self::Assign{pos, target} => write!(f, "{pos}: cannot assign to {target}"),
+			Self::TooManyInstructions(cnt) => write!(f, "too many instructions ({cnt}, at most {MAX_INSTRUCTIONS})"),
+			Self::TooLong(len) => write!(f, "code too long ({len} bytes, at most {MAX_CODE_LEN})"),
+		}
+	}
+}
+
+impl Error for CompileError {}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn control_flow()
+	{
+		let source = "
+			link screen at 1 -2;
+			macro clamp(v, lo, hi) { if v < lo { v = lo; } else if v > hi { v = hi; } }
+			let i = 0;
+			while i < 10 && !done {
+				i += 1;
+				if i % 2 == 0 { continue; }
+				clamp(i, 2, 8);
+				print(\"i = \", i);
+			}
+			printflush(screen);
+			x = max(i, 3) ** 2;
+		";
+		let program = compile(source).unwrap();
+		assert_eq!(program.links, vec![("screen".to_string(), 1, -2)]);
+		assert_eq!(program.code, "\
+			set i 0\n\
+			jump 15 greaterThanEq i 10\n\
+			jump 15 notEqual done false\n\
+			op add i i 1\n\
+			op mod __t0 i 2\n\
+			jump 7 notEqual __t0 0\n\
+			jump 1 always\n\
+			jump 10 greaterThanEq i 2\n\
+			set i 2\n\
+			jump 12 always\n\
+			jump 12 lessThanEq i 8\n\
+			set i 8\n\
+			print \"i = \"\n\
+			print i\n\
+			jump 1 always\n\
+			printflush screen\n\
+			op max __t0 i 3\n\
+			op pow x __t0 2\n");
+		assert!(mlog::check(&program.code).is_empty());
+	}
+	
+	#[test]
+	fn errors()
+	{
+		assert!(matches!(compile("x = ;"), Err(CompileError::Syntax{pos: Pos{line: 1, col: 5}, ..})));
+		assert!(matches!(compile("macro m() { m(); }\nm();"), Err(CompileError::Recursion{..})));
+		assert!(matches!(compile("break;"), Err(CompileError::OutsideLoop{..})));
+		assert!(matches!(compile("macro m(a) { a = 1; }\nm(5);"), Err(CompileError::Assign{..})));
+		assert!(matches!(compile("fly(1);"), Err(CompileError::Undefined{..})));
+		let long = "x = 1;\n".repeat(MAX_INSTRUCTIONS + 1);
+		assert_eq!(compile(&long), Err(CompileError::TooManyInstructions(MAX_INSTRUCTIONS + 1)));
+	}
+	
+	#[test]
+	fn tokens()
+	{
+		let program = compile("x = @unit.health;\ny = 1e-5 - 2E+3;\nz = 0x1e-5;").unwrap();
+		// a sensor, signed exponents and a hexadecimal number minus 5
+		assert_eq!(program.code, "sensor x @unit @health\nop sub y 1e-5 2E+3\nop sub z 0x1e 5\n");
+	}
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::block::{BlockRegistry, Rotation};
+use crate::data::dynamic::DynData;
+use crate::data::image::Image;
+use crate::data::processors::{self, ImportError};
+use crate::data::schematic::{PlaceError, Schematic};
+use crate::logic::mlog::MAX_INSTRUCTIONS;
+
+// draw commands a processor can buffer, any more are dropped until the next drawflush
+pub const MAX_DRAW_BUFFER: usize = 256;
+// the name the processors use for the display
+pub const LINK_NAME: &str = "display1";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DisplayKind
+{
+	Logic,
+	LargeLogic,
+}
+
+impl DisplayKind
+{
+	pub fn by_name(name: &str) -> Option<Self>
+	{
+		match name
+		{
+			"logic-display" => Some(Self::Logic),
+			"large-logic-display" => Some(Self::LargeLogic),
+			_ => None,
+		}
+	}
+	
+	pub fn get_name(self) -> &'static str
+	{
+		match self
+		{
+			Self::Logic => "logic-display",
+			Self::LargeLogic => "large-logic-display",
+		}
+	}
+	
+	// in display pixels
+	pub fn get_resolution(self) -> u32
+	{
+		match self
+		{
+			Self::Logic => 80,
+			Self::LargeLogic => 176,
+		}
+	}
+	
+	// in tiles
+	pub fn get_size(self) -> u16
+	{
+		match self
+		{
+			Self::Logic => 3,
+			Self::LargeLogic => 6,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DrawOptions
+{
+	pub display: DisplayKind,
+	// cells across the longer side of the image, at most the display's resolution
+	pub cells: u32,
+	// values per color channel, fewer means fewer distinct colors and so less code
+	pub levels: u16,
+}
+
+impl DrawOptions
+{
+	pub fn new(display: DisplayKind) -> Self
+	{
+		Self{display, cells: display.get_resolution(), levels: 256}
+	}
+}
+
+fn quantize(value: u8, levels: u16) -> u8
+{
+	if !(2..256).contains(&levels) {return value;}
+	let step = 255.0 / (levels - 1) as f32;
+	((value as f32 / step).round() * step).round() as u8
+}
+
+// a rectangle of cells with the same color (top left and exclusive bottom right)
+struct Rect
+{
+	color: [u8; 4],
+	x0: u32,
+	y0: u32,
+	x1: u32,
+	y1: u32,
+}
+
+// covers the image with as few single-colored rectangles as the greedy approach finds, transparent cells are skipped
+fn find_rects(cells: &[Option<[u8; 4]>], width: u32, height: u32, skip: Option<[u8; 4]>) -> Vec<Rect>
+{
+	let idx = |x: u32, y: u32| (x + y * width) as usize;
+	let mut done = vec![false; cells.len()];
+	let mut rects = Vec::new();
+	for y in 0..height
+	{
+		for x in 0..width
+		{
+			let Some(color) = cells[idx(x, y)]
+			else {continue;};
+			if done[idx(x, y)] || Some(color) == skip {continue;}
+			let same = |cx: u32, cy: u32, done: &[bool]| !done[idx(cx, cy)] && cells[idx(cx, cy)] == Some(color);
+			let mut x1 = x + 1;
+			while x1 < width && same(x1, y, &done) {x1 += 1;}
+			let mut y1 = y + 1;
+			while y1 < height && (x..x1).all(|cx| same(cx, y1, &done)) {y1 += 1;}
+			for cy in y..y1
+			{
+				for cx in x..x1
+				{
+					done[idx(cx, cy)] = true;
+				}
+			}
+			rects.push(Rect{color, x0: x, y0: y, x1, y1});
+		}
+	}
+	rects
+}
+
+// code for as many processors as needed to draw the image onto the display linked as LINK_NAME
+pub fn draw_code(image: &Image, options: &DrawOptions) -> Vec<String>
+{
+	let res = options.display.get_resolution();
+	let cells = options.cells.clamp(1, res);
+	let longest = image.get_width().max(image.get_height());
+	let width = ((image.get_width() * cells + longest / 2) / longest).max(1);
+	let height = ((image.get_height() * cells + longest / 2) / longest).max(1);
+	let scaled = image.resize(width, height);
+	let grid = Vec::from_iter((0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)|
+	{
+		let p = scaled.get_pixel(x, y);
+		let color = [quantize(p[0], options.levels), quantize(p[1], options.levels), quantize(p[2], options.levels), quantize(p[3], options.levels)];
+		(color[3] > 0).then_some(color)
+	}));
+	
+	// display pixels where a cell starts, centered and upwards as the display's y axis points up
+	let pos = |i: u32| i * res / cells;
+	let (ox, oy) = ((res - pos(width)) / 2, (res - pos(height)) / 2);
+	let rect_commands = |background: Option<[u8; 4]>|
+	{
+		let mut commands = Vec::<([u8; 4], String)>::new();
+		if let Some(color) = background {commands.push((color, format!("draw rect {ox} {oy} {} {}", pos(width), pos(height))));}
+		let mut rects = find_rects(&grid, width, height, background);
+		rects.sort_by_key(|r| r.color);
+		for r in rects
+		{
+			let (x, y) = (ox + pos(r.x0), oy + pos(height - r.y1));
+			commands.push((r.color, format!("draw rect {x} {y} {} {}", pos(r.x1) - pos(r.x0), pos(height - r.y0) - pos(height - r.y1))));
+		}
+		commands
+	};
+	// without transparency the most common color can be drawn as one rectangle first, but only with a single processor as
+	// it would cover what the others draw every time it loops
+	if grid.iter().all(Option::is_some)
+	{
+		let mut counts = HashMap::<[u8; 4], usize>::new();
+		for c in grid.iter().flatten()
+		{
+			*counts.entry(*c).or_default() += 1;
+		}
+		let color = counts.into_iter().max_by_key(|&(c, n)| (n, c)).map(|(c, _)| c).unwrap();
+		let programs = split_programs(rect_commands(Some(color)));
+		if programs.len() == 1 {return programs;}
+	}
+	split_programs(rect_commands(None))
+}
+
+// every flush starts with its own color, other processors may have changed it in between
+fn split_programs(commands: Vec<([u8; 4], String)>) -> Vec<String>
+{
+	let mut programs = Vec::new();
+	let mut code = Vec::<String>::new();
+	let mut buffered = 0;
+	let mut current = None;
+	let flush = |code: &mut Vec<String>, buffered: &mut usize, current: &mut Option<[u8; 4]>|
+	{
+		if *buffered > 0 {code.push(format!("drawflush {LINK_NAME}"));}
+		*buffered = 0;
+		*current = None;
+	};
+	for (color, rect) in commands
+	{
+		let mut need = if current == Some(color) {1} else {2};
+		if buffered + need > MAX_DRAW_BUFFER
+		{
+			flush(&mut code, &mut buffered, &mut current);
+			need = 2;
+		}
+		// room for the final flush
+		if code.len() + need + 1 > MAX_INSTRUCTIONS
+		{
+			flush(&mut code, &mut buffered, &mut current);
+			programs.push(code.join("\n") + "\n");
+			code.clear();
+			need = 2;
+		}
+		if need == 2
+		{
+			code.push(format!("draw color {} {} {} {}", color[0], color[1], color[2], color[3]));
+			current = Some(color);
+		}
+		code.push(rect);
+		buffered += need;
+	}
+	flush(&mut code, &mut buffered, &mut current);
+	if !code.is_empty() {programs.push(code.join("\n") + "\n");}
+	programs
+}
+
+// link range of the processors in tiles
+fn get_range(processor: &str) -> Option<u16>
+{
+	match processor
+	{
+		"micro-processor" => Some(10),
+		"logic-processor" => Some(22),
+		"hyper-processor" => Some(42),
+		_ => None,
+	}
+}
+
+// places the display with the processors running the programs next to it
+pub fn build_schematic<'l>(reg: &'l BlockRegistry<'l>, programs: &[String], display: DisplayKind, processor: &str)
+	-> Result<Schematic<'l>, BuildError>
+{
+	let proc_block = reg.get(processor).ok_or_else(|| BuildError::UnknownBlock(processor.to_owned()))?;
+	let range = get_range(processor).ok_or_else(|| BuildError::NotProcessor(processor.to_owned()))?;
+	let disp_block = reg.get(display.get_name()).ok_or_else(|| BuildError::UnknownBlock(display.get_name().to_owned()))?;
+	let size = display.get_size();
+	let proc_size = proc_block.get_size() as u16;
+	// columns of processors to the right of the display
+	let per_column = (size / proc_size).max(1);
+	let columns = (programs.len() as u16).div_ceil(per_column).max(1);
+	let mut schematic = Schematic::new(size + columns * proc_size, size.max(per_column * proc_size));
+	let (dx, dy) = ((size - 1) / 2, (size - 1) / 2);
+	schematic.set(dx, dy, disp_block, DynData::Empty, Rotation::Right)?;
+	let off = (proc_size - 1) / 2;
+	for (i, code) in programs.iter().enumerate()
+	{
+		let (col, row) = (i as u16 / per_column, i as u16 % per_column);
+		let (x, y) = (size + col * proc_size + off, row * proc_size + off);
+		// the range reaches to the edge of the display
+		let (lx, ly) = (dx as f32 - x as f32, dy as f32 - y as f32);
+		if (lx * lx + ly * ly).sqrt() > range as f32 + size as f32 / 2.0
+		{
+			return Err(BuildError::OutOfRange{count: programs.len()});
+		}
+		schematic.set(x, y, proc_block, DynData::Empty, Rotation::Right)?;
+		let link = vec![(LINK_NAME.to_owned(), dx as i16 - x as i16, dy as i16 - y as i16)];
+		processors::set_program(&mut schematic, x, y, code, &link)?;
+	}
+	Ok(schematic)
+}
+
+#[derive(Debug)]
+pub enum BuildError
+{
+	UnknownBlock(String),
+	NotProcessor(String),
+	OutOfRange{count: usize},
+	Place(PlaceError),
+	Program(ImportError),
+}
+
+impl From<PlaceError> for BuildError
+{
+	fn from(value: PlaceError) -> Self
+	{
+		Self::Place(value)
+	}
+}
+
+impl From<ImportError> for BuildError
+{
+	fn from(value: ImportError) -> Self
+	{
+		Self::Program(value)
+	}
+}
+
+impl fmt::Display for BuildError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::UnknownBlock(name) => write!(f, "no block named {name:?}"),
+			Self::NotProcessor(name) => write!(f, "{name:?} is not a processor"),
+			Self::OutOfRange{count} => write!(f, "{count} processors don't fit within range of the display (try fewer colors or cells)"),
+			Self::Place(..) => f.write_str("could not place block"),
+			Self::Program(..) => f.write_str("could not set processor code"),
+		}
+	}
+}
+
+impl Error for BuildError
+{
+	fn source(&self) -> Option<&(dyn Error + 'static)>
+	{
+		match self
+		{
+			Self::Place(e) => Some(e),
+			Self::Program(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	use crate::block::build_registry;
+	use crate::block::logic::ProcessorState;
+	use crate::logic::mlog;
+	
+	#[test]
+	fn draw_image()
+	{
+		// a red square on blue, then noise that needs several processors
+		let mut pixels = Vec::new();
+		for y in 0..4
+		{
+			for x in 0..4
+			{
+				pixels.extend_from_slice(if (1..3).contains(&x) && (1..3).contains(&y) {&[255, 0, 0, 255]} else {&[0, 0, 255, 255]});
+			}
+		}
+		let options = DrawOptions{cells: 4, ..DrawOptions::new(DisplayKind::Logic)};
+		let programs = draw_code(&Image::new(4, 4, pixels), &options);
+		assert_eq!(programs, vec![
+			"draw color 0 0 255 255\ndraw rect 0 0 80 80\ndraw color 255 0 0 255\ndraw rect 20 20 40 40\ndrawflush display1\n".to_string(),
+		]);
+		let noise = Vec::from_iter((0..176u32 * 176).flat_map(|i| [(i * 7919 % 251) as u8, (i * 104729 % 241) as u8, (i % 256) as u8, 255]));
+		let programs = draw_code(&Image::new(176, 176, noise), &DrawOptions::new(DisplayKind::LargeLogic));
+		assert!(programs.len() > 1);
+		// no background covering the other processors' rectangles
+		let full = format!("draw rect 0 0 {0} {0}", DisplayKind::LargeLogic.get_resolution());
+		for code in programs.iter()
+		{
+			assert!(!code.lines().any(|l| l == full));
+			assert!(code.lines().count() <= MAX_INSTRUCTIONS);
+			assert!(mlog::check(code).is_empty());
+		}
+		let reg = build_registry();
+		let schematic = build_schematic(&reg, &programs[..3], DisplayKind::LargeLogic, "micro-processor").unwrap();
+		assert_eq!(schematic.block_iter().count(), 4);
+		let proc = schematic.get(6, 0).unwrap().unwrap().get_state().unwrap().downcast_ref::<ProcessorState>().unwrap();
+		assert_eq!(proc.get_links()[0].get_pos(), (-4, 2));
+		assert!(matches!(build_schematic(&reg, &programs, DisplayKind::Logic, "logic-display"), Err(BuildError::NotProcessor(..))));
+	}
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use crate::logic::mlog::{self, Statement};
+
+const INDENT: &str = "    ";
+
+// one instruction per line with single spaces, which is how the game saves code
+fn write_instruction(out: &mut String, name: &str, args: &[&str])
+{
+	out.push_str(name);
+	for arg in args
+	{
+		out.push(' ');
+		out.push_str(arg);
+	}
+	out.push('\n');
+}
+
+// the jump target of an instruction, if it is a jump with a numeric target
+fn get_target(tokens: &[mlog::Token], count: usize) -> Option<usize>
+{
+	if tokens[0].text != "jump" {return None;}
+	tokens.get(1)?.text.parse::<usize>().ok().filter(|&t| t <= count)
+}
+
+// replaces numeric jump targets with labels (keeping existing ones) and formats the code consistently, comments are dropped
+pub fn add_labels(code: &str) -> String
+{
+	let statements = mlog::parse(code);
+	let instructions = Vec::from_iter(statements.iter().filter_map(|s| match s
+	{
+		Statement::Instruction(tokens) => Some(tokens.as_slice()),
+		Statement::Label(..) => None,
+	}));
+	let count = instructions.len();
+	// every name in the code, so synthesized labels can't clash with anything
+	let used = HashSet::<&str>::from_iter(statements.iter().flat_map(|s| match s
+	{
+		Statement::Label(tok) => std::slice::from_ref(tok),
+		Statement::Instruction(tokens) => tokens.as_slice(),
+	}).map(|t| t.text));
+	let mut labels = BTreeMap::<usize, Vec<String>>::new();
+	for (tok, index) in mlog::get_labels(&statements)
+	{
+		labels.entry(index).or_default().push(tok.text.to_owned());
+	}
+	for tokens in instructions.iter()
+	{
+		let Some(target) = get_target(tokens, count)
+		else {continue;};
+		labels.entry(target).or_insert_with(||
+		{
+			let mut name = format!("label{target}");
+			while used.contains(name.as_str()) {name.push('_');}
+			vec![name]
+		});
+	}
+	
+	let mut out = String::new();
+	for (i, tokens) in instructions.iter().chain(std::iter::once(&&[][..])).enumerate()
+	{
+		for name in labels.get(&i).into_iter().flatten()
+		{
+			out.push_str(name);
+			out.push_str(":\n");
+		}
+		if tokens.is_empty() {break;}
+		if !labels.is_empty() {out.push_str(INDENT);}
+		let mut args = Vec::from_iter(tokens[1..].iter().map(|t| t.text));
+		if let Some(target) = get_target(tokens, count)
+		{
+			args[0] = &labels[&target][0];
+		}
+		write_instruction(&mut out, tokens[0].text, &args);
+	}
+	out
+}
+
+// replaces labels with the number of the instruction they jump to, the inverse of add_labels
+pub fn remove_labels(code: &str) -> Result<String, LabelError>
+{
+	let statements = mlog::parse(code);
+	let labels = mlog::get_labels(&statements);
+	for (i, (tok, _)) in labels.iter().enumerate()
+	{
+		if labels[..i].iter().any(|(other, _)| other.text == tok.text)
+		{
+			return Err(LabelError::Duplicate{line: tok.line + 1, name: tok.text.to_owned()});
+		}
+	}
+	let mut out = String::new();
+	for stmt in statements.iter()
+	{
+		let Statement::Instruction(tokens) = stmt
+		else {continue;};
+		let mut args = Vec::from_iter(tokens[1..].iter().map(|t| t.text.to_owned()));
+		if tokens[0].text == "jump" && !args.is_empty() && !mlog::is_literal(&args[0])
+		{
+			let Some((_, index)) = labels.iter().find(|(l, _)| l.text == args[0])
+			else {return Err(LabelError::Undefined{line: tokens[1].line + 1, name: args[0].clone()});};
+			args[0] = index.to_string();
+		}
+		write_instruction(&mut out, tokens[0].text, &Vec::from_iter(args.iter().map(String::as_str)));
+	}
+	Ok(out)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LabelError
+{
+	Undefined{line: usize, name: String},
+	Duplicate{line: usize, name: String},
+}
+
+impl fmt::Display for LabelError
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self
+		{
+			Self::Undefined{line, name} => write!(f, "line {line}: jump to undefined label {name:?}"),
+			Self::Duplicate{line, name} => write!(f, "line {line}: label {name:?} is defined more than once"),
+		}
+	}
+}
+
+impl Error for LabelError {}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn roundtrip()
+	{
+		let code = "set i 0\njump 4   greaterThanEq i 10 # done\nop add i i 1\njump 1 always\nprint \"a  b\"\njump 6 always\n";
+		let labeled = add_labels(code);
+		assert_eq!(labeled, [
+			"    set i 0", "label1:", "    jump label4 greaterThanEq i 10", "    op add i i 1", "    jump label1 always",
+			"label4:", "    print \"a  b\"", "    jump label6 always", "label6:", "",
+		].join("\n"));
+		let numeric = remove_labels(&labeled).unwrap();
+		assert_eq!(numeric, code.replace("4   ", "4 ").replace(" # done", ""));
+		assert_eq!(add_labels(&numeric), labeled);
+		// existing labels are kept and names don't clash
+		assert_eq!(add_labels("label0:\nset label1 0\njump 1 always\n"), "label0:\n    set label1 0\nlabel1_:\n    jump label1_ always\n");
+		assert_eq!(remove_labels("jump x always\n"), Err(LabelError::Undefined{line: 1, name: "x".to_string()}));
+		assert!(matches!(remove_labels("a:\na:\nend\n"), Err(LabelError::Duplicate{..})));
+	}
+}
@@ -0,0 +1,564 @@
+use crate::block;
+use crate::content::Content;
+use crate::fluid;
+use crate::item;
+use crate::logic::LogicField;
+use crate::unit;
+
+// limits enforced by the game when loading code into a processor
+pub const MAX_CODE_LEN: usize = 500 * 1024;
+pub const MAX_INSTRUCTIONS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Token<'s>
+{
+	pub text: &'s str,
+	pub line: usize,
+	// byte offset of the first character within the line
+	pub start: usize,
+}
+
+impl<'s> Token<'s>
+{
+	pub fn get_end(&self) -> usize
+	{
+		self.start + self.text.len()
+	}
+	
+	pub fn contains(&self, line: usize, col: usize) -> bool
+	{
+		self.line == line && col >= self.start && col <= self.get_end()
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Statement<'s>
+{
+	// the name of a jump target (without the colon)
+	Label(Token<'s>),
+	// the instruction name followed by its arguments
+	Instruction(Vec<Token<'s>>),
+}
+
+fn finish_statement<'s>(mut tokens: Vec<Token<'s>>, statements: &mut Vec<Statement<'s>>)
+{
+	if tokens.is_empty() {return;}
+	let first = tokens[0];
+	if first.text.len() > 1 && first.text.ends_with(':') && !first.text.starts_with('"')
+	{
+		statements.push(Statement::Label(Token{text: &first.text[..first.text.len() - 1], ..first}));
+		tokens.remove(0);
+		if tokens.is_empty() {return;}
+	}
+	statements.push(Statement::Instruction(tokens));
+}
+
+fn parse_line<'s>(text: &'s str, line: usize, statements: &mut Vec<Statement<'s>>)
+{
+	// only ever split at ascii characters, so slicing stays on character boundaries
+	let bytes = text.as_bytes();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	loop
+	{
+		while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {i += 1;}
+		if i >= bytes.len() || bytes[i] == b'#' {break;}
+		if bytes[i] == b';'
+		{
+			finish_statement(std::mem::take(&mut tokens), statements);
+			i += 1;
+			continue;
+		}
+		let start = i;
+		if bytes[i] == b'"'
+		{
+			i += 1;
+			while i < bytes.len() && bytes[i] != b'"' {i += 1;}
+			i = (i + 1).min(bytes.len());
+		}
+		else
+		{
+			while i < bytes.len() && !matches!(bytes[i], b' ' | b'\t' | b';' | b'#') {i += 1;}
+		}
+		tokens.push(Token{text: &text[start..i], line, start});
+	}
+	finish_statement(tokens, statements);
+}
+
+// splits code into labels and instructions, the way the game does (without checking them)
+pub fn parse(code: &str) -> Vec<Statement<'_>>
+{
+	let mut statements = Vec::new();
+	for (line, text) in code.lines().enumerate()
+	{
+		parse_line(text, line, &mut statements);
+	}
+	statements
+}
+
+pub struct Instruction
+{
+	pub name: &'static str,
+	pub args: &'static [&'static str],
+	// arguments that are needed for the instruction to do anything
+	pub required: usize,
+	// arguments that select from a fixed set of names
+	pub keywords: &'static [(usize, &'static [&'static str])],
+	// arguments that receive a result, and so must be variables
+	pub outputs: &'static [usize],
+	pub description: &'static str,
+}
+
+pub const DRAW_OPS: &[&str] = &["clear", "color", "col", "stroke", "line", "rect", "lineRect", "poly", "linePoly", "triangle", "image"];
+pub const CONTROL_OPS: &[&str] = &["enabled", "shoot", "shootp", "config", "color"];
+pub const RADAR_TARGETS: &[&str] = &["any", "enemy", "ally", "player", "attacker", "flying", "boss", "ground"];
+pub const RADAR_SORTS: &[&str] = &["distance", "health", "shield", "armor", "maxHealth"];
+pub const OPS: &[&str] = &[
+	"add", "sub", "mul", "div", "idiv", "mod", "pow", "equal", "notEqual", "land", "lessThan", "lessThanEq", "greaterThan", "greaterThanEq",
+	"strictEqual", "shl", "shr", "or", "and", "xor", "not", "max", "min", "angle", "angleDiff", "len", "noise", "abs", "log", "log10", "floor",
+	"ceil", "sqrt", "rand", "sin", "cos", "tan", "asin", "acos", "atan",
+];
+pub const CONDITIONS: &[&str] = &["equal", "notEqual", "lessThan", "lessThanEq", "greaterThan", "greaterThanEq", "strictEqual", "always"];
+pub const LOOKUP_TYPES: &[&str] = &["block", "unit", "item", "liquid"];
+pub const UNIT_OPS: &[&str] = &[
+	"idle", "stop", "move", "approach", "pathfind", "autoPathfind", "boost", "target", "targetp", "itemDrop", "itemTake", "payDrop", "payTake",
+	"payEnter", "mine", "flag", "build", "getBlock", "within", "unbind",
+];
+pub const LOCATE_KINDS: &[&str] = &["building", "ore", "spawn", "damaged"];
+pub const LOCATE_GROUPS: &[&str] = &["core", "storage", "generator", "turret", "factory", "repair", "battery", "reactor"];
+
+macro_rules!instructions
+{
+	($($name:literal [$($arg:literal),*] $required:literal $({$($kidx:literal: $kw:expr),*})? $(=> [$($out:literal),*])? : $desc:literal),* $(,)?) =>
+	{
+		pub const INSTRUCTIONS: &[Instruction] = &[
+			$(Instruction
+			{
+				name: $name,
+				args: &[$($arg),*],
+				required: $required,
+				keywords: &[$($(($kidx, $kw)),*)?],
+				outputs: &[$($($out),*)?],
+				description: $desc,
+			},)*
+		];
+	};
+}
+
+instructions!
+{
+	"read" ["result", "cell", "at"] 3 => [0]: "Read a number from a memory cell",
+	"write" ["value", "cell", "at"] 3: "Write a number to a memory cell",
+	"draw" ["operation", "x", "y", "p1", "p2", "p3", "p4"] 1 {0: DRAW_OPS}: "Add a drawing operation to the draw buffer",
+	"print" ["value"] 1: "Add text to the print buffer",
+	"drawflush" ["display"] 1: "Send the draw buffer to a display",
+	"printflush" ["message"] 1: "Send the print buffer to a message block",
+	"getlink" ["result", "index"] 2 => [0]: "Get a linked block by its index",
+	"control" ["operation", "block", "p1", "p2", "p3", "p4"] 2 {0: CONTROL_OPS}: "Control a building",
+	"radar" ["target1", "target2", "target3", "sort", "block", "order", "result"] 7 {0: RADAR_TARGETS, 1: RADAR_TARGETS, 2: RADAR_TARGETS, 3: RADAR_SORTS}
+		=> [6]: "Find units near a building",
+	"sensor" ["result", "target", "property"] 3 => [0]: "Read a property of a building or unit",
+	"set" ["result", "value"] 2 => [0]: "Set a variable",
+	"op" ["operation", "result", "a", "b"] 3 {0: OPS} => [1]: "Compute a value",
+	"lookup" ["type", "result", "id"] 3 {0: LOOKUP_TYPES} => [1]: "Look up content by its id",
+	"packcolor" ["result", "r", "g", "b", "a"] 5 => [0]: "Pack a color into a single number",
+	"wait" ["seconds"] 1: "Wait for some time",
+	"stop" [] 0: "Halt the processor",
+	"end" [] 0: "Jump back to the first instruction",
+	"jump" ["target", "condition", "a", "b"] 2 {1: CONDITIONS}: "Jump to another instruction if a condition holds",
+	"ubind" ["type"] 1: "Bind the next unit of a type to @unit",
+	"ucontrol" ["operation", "p1", "p2", "p3", "p4", "p5"] 1 {0: UNIT_OPS}: "Control the bound unit",
+	"uradar" ["target1", "target2", "target3", "sort", "unused", "order", "result"] 7 {0: RADAR_TARGETS, 1: RADAR_TARGETS, 2: RADAR_TARGETS, 3: RADAR_SORTS}
+		=> [6]: "Find units near the bound unit",
+	"ulocate" ["find", "group", "enemy", "ore", "outX", "outY", "found", "building"] 8 {0: LOCATE_KINDS, 1: LOCATE_GROUPS} => [4, 5, 6, 7]
+		: "Locate buildings, ores or spawns with the bound unit",
+	"noop" [] 0: "Do nothing",
+}
+
+pub fn get_instruction(name: &str) -> Option<&'static Instruction>
+{
+	INSTRUCTIONS.iter().find(|i| i.name == name)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConstantKind
+{
+	Builtin,
+	Sensor,
+	Item,
+	Fluid,
+	Unit,
+	Block,
+	Team,
+}
+
+const BUILTINS: &[(&str, &str)] = &[
+	("counter", "index of the next instruction"),
+	("this", "the processor running the code"),
+	("thisx", "x coordinate of the processor"),
+	("thisy", "y coordinate of the processor"),
+	("ipt", "instructions executed per tick"),
+	("links", "number of linked blocks"),
+	("unit", "the bound unit"),
+	("time", "milliseconds since the world was created"),
+	("tick", "ticks since the world was created"),
+	("second", "seconds since the world was created"),
+	("minute", "minutes since the world was created"),
+	("waveNumber", "current wave"),
+	("waveTime", "time until the next wave"),
+	("mapw", "width of the map"),
+	("maph", "height of the map"),
+	("server", "whether the code runs on the server"),
+	("client", "whether the code runs on a client"),
+	("air", "the empty block"),
+	("solid", "generic solid block"),
+	("blockCount", "number of block types"),
+	("unitCount", "number of unit types"),
+	("itemCount", "number of item types"),
+	("liquidCount", "number of liquid types"),
+	("ctrlProcessor", "controlled by a processor"),
+	("ctrlPlayer", "controlled by a player"),
+	("ctrlCommand", "controlled by a command"),
+	("pi", "the ratio of a circle's circumference to its diameter"),
+	("e", "Euler's number"),
+	("degToRad", "multiply to convert degrees to radians"),
+	("radToDeg", "multiply to convert radians to degrees"),
+];
+
+const TEAMS: &[&str] = &["derelict", "sharded", "crux", "malis", "green", "blue"];
+
+// the kind of an @ constant (given without the @), if it exists
+pub fn get_constant(name: &str) -> Option<ConstantKind>
+{
+	if BUILTINS.iter().any(|&(n, _)| n == name) {Some(ConstantKind::Builtin)}
+	else if LogicField::by_name(name).is_some() {Some(ConstantKind::Sensor)}
+	else if item::Type::by_name(name).is_some() {Some(ConstantKind::Item)}
+	else if fluid::Type::by_name(name).is_some() {Some(ConstantKind::Fluid)}
+	else if unit::Type::by_name(name).is_some() {Some(ConstantKind::Unit)}
+	else if block::content::Type::by_name(name).is_some() {Some(ConstantKind::Block)}
+	else if TEAMS.contains(&name) {Some(ConstantKind::Team)}
+	else {None}
+}
+
+// every @ constant (without the @) with a short description
+pub fn get_constants() -> Vec<(String, ConstantKind, &'static str)>
+{
+	fn content<T: Content>(iter: impl Iterator<Item = T>, kind: ConstantKind, desc: &'static str) -> impl Iterator<Item = (String, ConstantKind, &'static str)>
+	{
+		iter.map(move |c| (c.get_name().to_owned(), kind, desc))
+	}
+	
+	let mut result = Vec::from_iter(BUILTINS.iter().map(|&(n, d)| (n.to_owned(), ConstantKind::Builtin, d)));
+	result.extend(LogicField::iter().map(|f| (f.get_name().to_owned(), ConstantKind::Sensor, if f.is_readable() {"sensor"} else {"control"})));
+	result.extend(content((0..).map_while(|id| item::Type::try_from(id).ok()), ConstantKind::Item, "item"));
+	result.extend(content((0..).map_while(|id| fluid::Type::try_from(id).ok()), ConstantKind::Fluid, "liquid"));
+	result.extend(content((0..).map_while(|id| unit::Type::try_from(id).ok()), ConstantKind::Unit, "unit"));
+	result.extend(content((0..).map_while(|id| block::content::Type::try_from(id).ok()), ConstantKind::Block, "block"));
+	result.extend(TEAMS.iter().map(|&t| (t.to_owned(), ConstantKind::Team, "team")));
+	result
+}
+
+// numbers, strings, colors and the like, which can't be assigned to
+pub fn is_literal(text: &str) -> bool
+{
+	text.starts_with('"') || text.starts_with('%') || matches!(text, "true" | "false" | "null")
+		|| text.parse::<f64>().is_ok() || text.strip_prefix("0x").is_some_and(|h| i64::from_str_radix(h, 16).is_ok())
+		|| text.strip_prefix("0b").is_some_and(|b| i64::from_str_radix(b, 2).is_ok())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity
+{
+	Error,
+	Warning,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic
+{
+	pub line: usize,
+	// byte offsets within the line
+	pub start: usize,
+	pub end: usize,
+	pub severity: Severity,
+	pub message: String,
+}
+
+impl Diagnostic
+{
+	fn new(token: &Token, severity: Severity, message: String) -> Self
+	{
+		Self{line: token.line, start: token.start, end: token.get_end(), severity, message}
+	}
+}
+
+// the labels in the code and the number of the instruction they jump to
+pub fn get_labels<'s>(statements: &[Statement<'s>]) -> Vec<(Token<'s>, usize)>
+{
+	let mut labels = Vec::new();
+	let mut index = 0;
+	for stmt in statements
+	{
+		match stmt
+		{
+			Statement::Label(tok) => labels.push((*tok, index)),
+			Statement::Instruction(..) => index += 1,
+		}
+	}
+	labels
+}
+
+// finds mistakes that the game would silently accept or ignore
+pub fn check(code: &str) -> Vec<Diagnostic>
+{
+	let statements = parse(code);
+	let labels = get_labels(&statements);
+	let count = statements.iter().filter(|s| matches!(s, Statement::Instruction(..))).count();
+	let mut result = Vec::new();
+	if code.len() > MAX_CODE_LEN
+	{
+		result.push(Diagnostic{line: 0, start: 0, end: 0, severity: Severity::Error, message: format!("code too long ({} bytes, at most {MAX_CODE_LEN})", code.len())});
+	}
+	for (i, (tok, _)) in labels.iter().enumerate()
+	{
+		if labels[..i].iter().any(|(other, _)| other.text == tok.text)
+		{
+			result.push(Diagnostic::new(tok, Severity::Warning, format!("duplicate label {:?}", tok.text)));
+		}
+	}
+	let mut index = 0;
+	for stmt in statements.iter()
+	{
+		let Statement::Instruction(tokens) = stmt
+		else {continue;};
+		index += 1;
+		if index == MAX_INSTRUCTIONS + 1
+		{
+			result.push(Diagnostic::new(&tokens[0], Severity::Error, format!("too many instructions ({count}, at most {MAX_INSTRUCTIONS})")));
+		}
+		let Some(info) = get_instruction(tokens[0].text)
+		else
+		{
+			result.push(Diagnostic::new(&tokens[0], Severity::Error, format!("unknown instruction {:?}", tokens[0].text)));
+			continue;
+		};
+		let args = &tokens[1..];
+		if args.len() > info.args.len()
+		{
+			let first = &args[info.args.len()];
+			let last = args.last().unwrap();
+			result.push(Diagnostic{end: last.get_end(), ..Diagnostic::new(first, Severity::Warning, format!("extra arguments are ignored, {} takes {}", info.name, info.args.len()))});
+		}
+		else if args.len() < info.required
+		{
+			let missing = info.args[args.len()..info.required].join(", ");
+			result.push(Diagnostic::new(&tokens[0], Severity::Warning, format!("missing arguments: {missing}")));
+		}
+		for (i, arg) in args.iter().enumerate().take(info.args.len())
+		{
+			if let Some(&(_, names)) = info.keywords.iter().find(|&&(k, _)| k == i)
+			{
+				if !names.contains(&arg.text)
+				{
+					result.push(Diagnostic::new(arg, Severity::Error, format!("unknown {} {:?}", info.args[i], arg.text)));
+				}
+				continue;
+			}
+			if info.outputs.contains(&i) && arg.text != "@counter" && (is_literal(arg.text) || arg.text.starts_with('@'))
+			{
+				result.push(Diagnostic::new(arg, Severity::Error, format!("cannot assign to {}", arg.text)));
+				continue;
+			}
+			if info.name == "jump" && i == 0
+			{
+				if let Ok(target) = arg.text.parse::<usize>()
+				{
+					if target >= count
+					{
+						result.push(Diagnostic::new(arg, Severity::Warning, format!("jump target {target} is past the last instruction")));
+					}
+				}
+				else if !labels.iter().any(|(l, _)| l.text == arg.text)
+				{
+					result.push(Diagnostic::new(arg, Severity::Error, format!("undefined label {:?}", arg.text)));
+				}
+				continue;
+			}
+			if let Some(name) = arg.text.strip_prefix('@')
+			{
+				match get_constant(name)
+				{
+					None => result.push(Diagnostic::new(arg, Severity::Warning, format!("unknown constant {}", arg.text))),
+					Some(ConstantKind::Sensor) if info.name == "sensor" && i == 2 && LogicField::by_name(name).is_some_and(|f| !f.is_readable()) =>
+					{
+						result.push(Diagnostic::new(arg, Severity::Warning, format!("{} cannot be read, only controlled", arg.text)));
+					},
+					_ => (),
+				}
+			}
+		}
+	}
+	result
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompletionKind
+{
+	Instruction,
+	Keyword,
+	Constant(ConstantKind),
+	Label,
+	Variable,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Completion
+{
+	pub label: String,
+	pub kind: CompletionKind,
+	pub detail: String,
+}
+
+// the statement that the position is in, and the index of the token being typed (which may not exist yet)
+fn statement_at(code: &str, line: usize, col: usize) -> Option<(Vec<Token<'_>>, usize)>
+{
+	let text = code.lines().nth(line).unwrap_or("");
+	let prefix = text.get(..col.min(text.len()))?;
+	let mut statements = Vec::new();
+	parse_line(prefix, line, &mut statements);
+	let end = statements.last().map_or(0, |s| match s
+	{
+		Statement::Label(tok) => tok.get_end() + 1,
+		Statement::Instruction(tokens) => tokens.last().unwrap().get_end(),
+	});
+	let rest = &prefix[end..];
+	// nothing to suggest in comments
+	if rest.contains('#') {return None;}
+	match statements.pop()
+	{
+		Some(Statement::Instruction(tokens)) if rest.is_empty() => Some((tokens.clone(), tokens.len() - 1)),
+		Some(Statement::Instruction(tokens)) if !rest.contains(';') => Some((tokens.clone(), tokens.len())),
+		_ => Some((Vec::new(), 0)),
+	}
+}
+
+// suggestions for the token at the position (a byte offset within the line), and where that token starts
+pub fn complete(code: &str, line: usize, col: usize) -> (usize, Vec<Completion>)
+{
+	let Some((tokens, index)) = statement_at(code, line, col)
+	else {return (col, Vec::new());};
+	let (start, partial) = match tokens.get(index)
+	{
+		Some(t) => (t.start, t.text),
+		None => (col, ""),
+	};
+	let mut result = Vec::new();
+	let mut add = |label: String, kind: CompletionKind, detail: String|
+	{
+		if label.starts_with(partial) {result.push(Completion{label, kind, detail});}
+	};
+	if index == 0
+	{
+		for i in INSTRUCTIONS
+		{
+			add(i.name.to_owned(), CompletionKind::Instruction, format!("{} {}", i.name, i.args.join(" ")));
+		}
+		return (start, result);
+	}
+	let info = get_instruction(tokens[0].text);
+	let arg = index - 1;
+	if let Some(&(_, names)) = info.and_then(|i| i.keywords.iter().find(|&&(k, _)| k == arg))
+	{
+		for name in names
+		{
+			add((*name).to_owned(), CompletionKind::Keyword, info.unwrap().args[arg].to_owned());
+		}
+	}
+	else if info.is_some_and(|i| i.name == "jump") && arg == 0
+	{
+		for (tok, target) in get_labels(&parse(code))
+		{
+			add(tok.text.to_owned(), CompletionKind::Label, format!("instruction {target}"));
+		}
+	}
+	else if partial.starts_with('@') || info.is_some_and(|i| i.name == "sensor") && arg == 2
+	{
+		let sensor = info.is_some_and(|i| i.name == "sensor") && arg == 2;
+		for (name, kind, desc) in get_constants()
+		{
+			// only things that can be read make sense as a property
+			if sensor && !matches!(kind, ConstantKind::Sensor | ConstantKind::Item | ConstantKind::Fluid) {continue;}
+			if sensor && kind == ConstantKind::Sensor && LogicField::by_name(&name).is_some_and(|f| !f.is_readable()) {continue;}
+			add(format!("@{name}"), CompletionKind::Constant(kind), desc.to_owned());
+		}
+	}
+	else
+	{
+		let mut seen = Vec::<&str>::new();
+		for stmt in parse(code)
+		{
+			let Statement::Instruction(tokens) = stmt
+			else {continue;};
+			for tok in tokens.iter().skip(1)
+			{
+				if tok.line == line && tok.start == start {continue;}
+				if is_literal(tok.text) || tok.text.starts_with('@') || seen.contains(&tok.text) {continue;}
+				let keyword = get_instruction(tokens[0].text).is_some_and(|i| i.keywords.iter().any(|(_, names)| names.contains(&tok.text)));
+				if keyword {continue;}
+				seen.push(tok.text);
+				add(tok.text.to_owned(), CompletionKind::Variable, "variable".to_owned());
+			}
+		}
+	}
+	(start, result)
+}
+
+// where the label used at the position is defined
+pub fn find_definition(code: &str, line: usize, col: usize) -> Option<Token<'_>>
+{
+	let statements = parse(code);
+	let name = statements.iter().find_map(|s| match s
+	{
+		Statement::Label(tok) if tok.contains(line, col) => Some(tok.text),
+		Statement::Instruction(tokens) if tokens[0].text == "jump" && tokens.get(1).is_some_and(|t| t.contains(line, col)) => Some(tokens[1].text),
+		_ => None,
+	})?;
+	get_labels(&statements).into_iter().map(|(tok, _)| tok).find(|tok| tok.text == name)
+}
+
+#[cfg(test)]
+mod test
+{
+	use super::*;
+	
+	#[test]
+	fn diagnostics()
+	{
+		let code = "loop:\nsensor x @this @totalItems; sensor y @this @shoot\nop foo z x 1\nset 5 x\njump loop always\njump nowhere equal x y\nfly 1\n";
+		let result = check(code);
+		let messages = Vec::from_iter(result.iter().map(|d| (d.line, d.message.as_str())));
+		assert_eq!(messages, vec![
+			(1, "@shoot cannot be read, only controlled"),
+			(2, "unknown operation \"foo\""),
+			(3, "cannot assign to 5"),
+			(5, "undefined label \"nowhere\""),
+			(6, "unknown instruction \"fly\""),
+		]);
+		assert_eq!((result[1].start, result[1].end), (3, 6));
+		assert!(check("set a 1 2\nend").iter().all(|d| d.severity == Severity::Warning));
+	}
+	
+	#[test]
+	fn completion()
+	{
+		let code = "start:\nsensor total @this @tot\nop a\njump st";
+		let (start, items) = complete(code, 1, 23);
+		assert_eq!(start, 19);
+		assert!(items.iter().any(|c| c.label == "@totalItems" && c.kind == CompletionKind::Constant(ConstantKind::Sensor)));
+		let (_, items) = complete(code, 2, 4);
+		assert!(items.iter().all(|c| c.kind == CompletionKind::Keyword) && items.iter().any(|c| c.label == "add"));
+		let (_, items) = complete(code, 3, 7);
+		assert_eq!(items.len(), 1);
+		assert_eq!(find_definition("start:\nend\njump start always", 2, 6).map(|t| (t.line, t.start)), Some((0, 0)));
+	}
+}
@@ -1,5 +1,10 @@
 use crate::content::numeric_enum;
 
+pub mod compile;
+pub mod display;
+pub mod format;
+pub mod mlog;
+
 numeric_enum!
 {
 	pub enum LogicField for u8 | TryFromU8Error
@@ -37,4 +42,68 @@ impl LogicField
 	{
 		match_select!(self, LogicField, Enabled, Shoot, ShootP, Config, Color)
 	}
+	
+	// the name used in logic code, after the @
+	pub fn get_name(&self) -> &'static str
+	{
+		match self
+		{
+			Self::TotalItems => "totalItems",
+			Self::FirstItem => "firstItem",
+			Self::TotalLiquids => "totalLiquids",
+			Self::TotalPower => "totalPower",
+			Self::ItemCapacity => "itemCapacity",
+			Self::LiquidCapacity => "liquidCapacity",
+			Self::PowerCapacity => "powerCapacity",
+			Self::PowerNetCapacity => "powerNetCapacity",
+			Self::PowerNetStored => "powerNetStored",
+			Self::PowerNetIn => "powerNetIn",
+			Self::PowerNetOut => "powerNetOut",
+			Self::Ammo => "ammo",
+			Self::AmmoCapacity => "ammoCapacity",
+			Self::Health => "health",
+			Self::MaxHealth => "maxHealth",
+			Self::Heat => "heat",
+			Self::Efficiency => "efficiency",
+			Self::Progress => "progress",
+			Self::Timescale => "timescale",
+			Self::Rotation => "rotation",
+			Self::PosX => "x",
+			Self::PosY => "y",
+			Self::ShootX => "shootX",
+			Self::ShootY => "shootY",
+			Self::Size => "size",
+			Self::Dead => "dead",
+			Self::Range => "range",
+			Self::Shooting => "shooting",
+			Self::Boosting => "boosting",
+			Self::MineX => "mineX",
+			Self::MineY => "mineY",
+			Self::Mining => "mining",
+			Self::Speed => "speed",
+			Self::Team => "team",
+			Self::Type => "type",
+			Self::Flag => "flag",
+			Self::Controlled => "controlled",
+			Self::Controller => "controller",
+			Self::Name => "name",
+			Self::PayloadCount => "payloadCount",
+			Self::PayloadType => "payloadType",
+			Self::Enabled => "enabled",
+			Self::Shoot => "shoot",
+			Self::ShootP => "shootp",
+			Self::Config => "config",
+			Self::Color => "color",
+		}
+	}
+	
+	pub fn by_name(name: &str) -> Option<Self>
+	{
+		Self::iter().find(|f| f.get_name() == name)
+	}
+	
+	pub fn iter() -> impl Iterator<Item = Self>
+	{
+		(0..).map_while(|id| Self::try_from(id).ok())
+	}
 }